@@ -0,0 +1,21 @@
+-- Add down migration script here
+ALTER TABLE projects ADD COLUMN project_id BIGINT;
+
+UPDATE projects SET project_id = (
+    SELECT MIN(project_id)
+    FROM projects_to_toggl_projects
+    WHERE projects_to_toggl_projects.project_key = projects.project_key
+);
+
+DELETE FROM projects WHERE project_id IS NULL;
+
+ALTER TABLE projects ALTER COLUMN project_id SET NOT NULL;
+ALTER TABLE projects ADD UNIQUE (user_key, project_id);
+
+DROP TABLE projects_to_toggl_tags;
+
+DROP TABLE projects_to_toggl_clients;
+
+DROP TABLE projects_to_toggl_projects;
+
+ALTER TABLE projects DROP COLUMN name;
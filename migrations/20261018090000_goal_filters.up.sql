@@ -0,0 +1,25 @@
+-- Add up migration script here
+ALTER TABLE projects ADD COLUMN name TEXT;
+
+CREATE TABLE projects_to_toggl_projects (
+    project_key BIGINT NOT NULL REFERENCES projects(project_key) ON DELETE CASCADE,
+    project_id BIGINT NOT NULL,
+    PRIMARY KEY (project_key, project_id)
+);
+
+CREATE TABLE projects_to_toggl_clients (
+    project_key BIGINT NOT NULL REFERENCES projects(project_key) ON DELETE CASCADE,
+    client TEXT NOT NULL,
+    PRIMARY KEY (project_key, client)
+);
+
+CREATE TABLE projects_to_toggl_tags (
+    project_key BIGINT NOT NULL REFERENCES projects(project_key) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (project_key, tag)
+);
+
+INSERT INTO projects_to_toggl_projects(project_key, project_id)
+SELECT project_key, project_id FROM projects;
+
+ALTER TABLE projects DROP COLUMN project_id;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE projects_to_description_patterns;
+
+ALTER TABLE projects DROP COLUMN no_project;
@@ -0,0 +1,9 @@
+-- Add up migration script here
+ALTER TABLE projects ADD COLUMN no_project BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE projects_to_description_patterns (
+    project_key BIGINT NOT NULL REFERENCES projects(project_key) ON DELETE CASCADE,
+    pattern TEXT NOT NULL,
+    regex BOOLEAN NOT NULL,
+    PRIMARY KEY (project_key, pattern, regex)
+);
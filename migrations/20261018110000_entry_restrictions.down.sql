@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE projects_to_tag_restrictions;
+
+ALTER TABLE projects DROP COLUMN billable_only;
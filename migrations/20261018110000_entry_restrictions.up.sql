@@ -0,0 +1,9 @@
+-- Add up migration script here
+ALTER TABLE projects ADD COLUMN billable_only BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE projects_to_tag_restrictions (
+    project_key BIGINT NOT NULL REFERENCES projects(project_key) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    excluded BOOLEAN NOT NULL,
+    PRIMARY KEY (project_key, tag)
+);
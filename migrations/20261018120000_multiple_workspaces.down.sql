@@ -0,0 +1,23 @@
+-- Add down migration script here
+ALTER TABLE users ADD COLUMN workspace_id BIGINT;
+
+UPDATE users SET workspace_id = (
+    SELECT MIN(workspace_id)
+    FROM users_to_workspaces
+    WHERE users_to_workspaces.user_key = users.user_key
+);
+
+ALTER TABLE users ALTER COLUMN workspace_id SET NOT NULL;
+
+DELETE FROM projects_to_toggl_projects
+USING projects
+INNER JOIN users
+ON projects.user_key = users.user_key
+WHERE projects_to_toggl_projects.project_key = projects.project_key
+AND projects_to_toggl_projects.workspace_id <> users.workspace_id;
+
+ALTER TABLE projects_to_toggl_projects DROP CONSTRAINT projects_to_toggl_projects_pkey;
+ALTER TABLE projects_to_toggl_projects DROP COLUMN workspace_id;
+ALTER TABLE projects_to_toggl_projects ADD PRIMARY KEY (project_key, project_id);
+
+DROP TABLE users_to_workspaces;
@@ -0,0 +1,24 @@
+-- Add up migration script here
+CREATE TABLE users_to_workspaces (
+    user_key BIGINT NOT NULL REFERENCES users(user_key) ON DELETE CASCADE,
+    workspace_id BIGINT NOT NULL,
+    PRIMARY KEY (user_key, workspace_id)
+);
+
+INSERT INTO users_to_workspaces(user_key, workspace_id)
+SELECT user_key, workspace_id FROM users;
+
+ALTER TABLE projects_to_toggl_projects ADD COLUMN workspace_id BIGINT;
+
+UPDATE projects_to_toggl_projects
+SET workspace_id = users.workspace_id
+FROM projects
+INNER JOIN users
+ON projects.user_key = users.user_key
+WHERE projects_to_toggl_projects.project_key = projects.project_key;
+
+ALTER TABLE projects_to_toggl_projects ALTER COLUMN workspace_id SET NOT NULL;
+ALTER TABLE projects_to_toggl_projects DROP CONSTRAINT projects_to_toggl_projects_pkey;
+ALTER TABLE projects_to_toggl_projects ADD PRIMARY KEY (project_key, workspace_id, project_id);
+
+ALTER TABLE users DROP COLUMN workspace_id;
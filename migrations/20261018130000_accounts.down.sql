@@ -0,0 +1,9 @@
+-- Add down migration script here
+-- Encrypted tokens can't be recovered without the server's key, so those users are removed
+DELETE FROM users WHERE toggl_api_key IS NULL;
+
+ALTER TABLE users ALTER COLUMN toggl_api_key SET NOT NULL;
+ALTER TABLE users DROP COLUMN toggl_api_key_ciphertext;
+ALTER TABLE users DROP COLUMN toggl_api_key_digest;
+ALTER TABLE users DROP COLUMN password_hash;
+ALTER TABLE users DROP COLUMN username;
@@ -0,0 +1,9 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN username TEXT UNIQUE;
+ALTER TABLE users ADD COLUMN password_hash TEXT;
+
+-- The plaintext token is encrypted into these columns by the server on startup, since only the
+-- server knows the key
+ALTER TABLE users ADD COLUMN toggl_api_key_digest BYTEA UNIQUE;
+ALTER TABLE users ADD COLUMN toggl_api_key_ciphertext BYTEA;
+ALTER TABLE users ALTER COLUMN toggl_api_key DROP NOT NULL;
@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE api_tokens (
+    api_token_key BIGSERIAL PRIMARY KEY,
+    user_key BIGINT NOT NULL REFERENCES users(user_key) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    digest BYTEA UNIQUE NOT NULL,
+
+    read_status BOOLEAN NOT NULL,
+    write_days_off BOOLEAN NOT NULL,
+    admin BOOLEAN NOT NULL,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMPTZ
+);
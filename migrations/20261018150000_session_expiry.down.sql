@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE session_tokens DROP COLUMN ip_address;
+ALTER TABLE session_tokens DROP COLUMN user_agent;
+ALTER TABLE session_tokens DROP COLUMN last_seen_at;
+ALTER TABLE session_tokens DROP COLUMN created_at;
+ALTER TABLE session_tokens DROP COLUMN session_key;
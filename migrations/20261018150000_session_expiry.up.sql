@@ -0,0 +1,6 @@
+-- Add up migration script here
+ALTER TABLE session_tokens ADD COLUMN session_key BIGSERIAL UNIQUE NOT NULL;
+ALTER TABLE session_tokens ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
+ALTER TABLE session_tokens ADD COLUMN last_seen_at TIMESTAMPTZ NOT NULL DEFAULT now();
+ALTER TABLE session_tokens ADD COLUMN user_agent TEXT;
+ALTER TABLE session_tokens ADD COLUMN ip_address TEXT;
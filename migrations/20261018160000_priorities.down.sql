@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE projects DROP COLUMN weight;
+ALTER TABLE projects DROP COLUMN priority;
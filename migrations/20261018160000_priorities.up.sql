@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE projects ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE projects ADD COLUMN weight INTEGER NOT NULL DEFAULT 1 CHECK (weight > 0);
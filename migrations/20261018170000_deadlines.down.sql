@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE projects DROP CONSTRAINT deadline_has_target;
+ALTER TABLE projects DROP COLUMN target_total;
+ALTER TABLE projects DROP COLUMN deadline;
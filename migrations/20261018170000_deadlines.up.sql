@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE projects ADD COLUMN deadline DATE;
+ALTER TABLE projects ADD COLUMN target_total BIGINT;
+ALTER TABLE projects ADD CONSTRAINT deadline_has_target
+    CHECK ((deadline IS NULL) = (target_total IS NULL));
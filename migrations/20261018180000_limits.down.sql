@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE projects DROP CONSTRAINT limit_or_deadline;
+ALTER TABLE projects DROP CONSTRAINT limit_has_time;
+ALTER TABLE projects DROP COLUMN limit_time;
+ALTER TABLE projects DROP COLUMN limit_period;
@@ -0,0 +1,7 @@
+-- Add up migration script here
+ALTER TABLE projects ADD COLUMN limit_period TEXT CHECK (limit_period IN ('day', 'week'));
+ALTER TABLE projects ADD COLUMN limit_time BIGINT;
+ALTER TABLE projects ADD CONSTRAINT limit_has_time
+    CHECK ((limit_period IS NULL) = (limit_time IS NULL));
+ALTER TABLE projects ADD CONSTRAINT limit_or_deadline
+    CHECK (limit_period IS NULL OR deadline IS NULL);
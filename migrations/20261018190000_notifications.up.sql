@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE notifications (
+    notification_key BIGSERIAL PRIMARY KEY,
+    user_key BIGINT NOT NULL REFERENCES users(user_key) ON DELETE CASCADE,
+
+    -- When to send it, in the user's timezone
+    local_time TIME NOT NULL,
+    channel TEXT NOT NULL CHECK (channel IN ('email', 'webhook')),
+    destination TEXT NOT NULL,
+
+    -- The user's date when it was last sent, so that it goes out once a day
+    last_sent DATE
+);
@@ -0,0 +1,46 @@
+-- Add up migration script here
+CREATE TABLE webhooks (
+    webhook_key BIGSERIAL PRIMARY KEY,
+    user_key BIGINT NOT NULL REFERENCES users(user_key) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    secret_ciphertext BYTEA NOT NULL,
+
+    -- The events the webhook is subscribed to
+    goal_met BOOLEAN NOT NULL,
+    debt_cleared BOOLEAN NOT NULL,
+    debt_exceeded BOOLEAN NOT NULL,
+    day_started BOOLEAN NOT NULL,
+
+    -- How much total debt, in seconds, counts as exceeded
+    debt_threshold BIGINT NOT NULL DEFAULT 0 CHECK (debt_threshold >= 0),
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE webhook_deliveries (
+    delivery_key BIGSERIAL PRIMARY KEY,
+    webhook_key BIGINT NOT NULL REFERENCES webhooks(webhook_key) ON DELETE CASCADE,
+    event TEXT NOT NULL
+        CHECK (event IN ('goal_met', 'debt_cleared', 'debt_exceeded', 'day_started')),
+
+    -- The user's date and the project an event is about, so that each event is only sent once
+    event_date DATE NOT NULL,
+    project_key BIGINT REFERENCES projects(project_key) ON DELETE CASCADE,
+
+    -- Stored as text so that the bytes that are signed stay the same between attempts
+    payload TEXT NOT NULL,
+
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- NULL once the delivery has succeeded or been given up on
+    next_attempt_at TIMESTAMPTZ DEFAULT now(),
+    delivered_at TIMESTAMPTZ,
+    last_status INTEGER,
+    last_error TEXT,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    UNIQUE NULLS NOT DISTINCT (webhook_key, event, event_date, project_key)
+);
+
+CREATE INDEX webhook_deliveries_pending ON webhook_deliveries(next_attempt_at)
+    WHERE next_attempt_at IS NOT NULL;
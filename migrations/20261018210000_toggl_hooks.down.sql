@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE toggl_projects;
+DROP TABLE toggl_entries;
+DROP TABLE toggl_workspaces;
@@ -0,0 +1,5 @@
+-- Add down migration script here
+DELETE FROM toggl_workspaces WHERE hooked_at IS NULL;
+
+ALTER TABLE toggl_workspaces ALTER COLUMN hooked_at SET DEFAULT now();
+ALTER TABLE toggl_workspaces ALTER COLUMN hooked_at SET NOT NULL;
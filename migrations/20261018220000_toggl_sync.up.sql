@@ -0,0 +1,6 @@
+-- Add up migration script here
+
+-- Every user's workspaces are stored by the background sync, not only the ones Toggl sends webhook
+-- events for
+ALTER TABLE toggl_workspaces ALTER COLUMN hooked_at DROP NOT NULL;
+ALTER TABLE toggl_workspaces ALTER COLUMN hooked_at DROP DEFAULT;
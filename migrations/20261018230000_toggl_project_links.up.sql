@@ -0,0 +1,6 @@
+-- Add up migration script here
+
+-- So that projects counting a Toggl project that's been archived or deleted can still be named
+ALTER TABLE projects_to_toggl_projects ADD COLUMN last_known_name TEXT;
+
+ALTER TABLE projects ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE toggl_projects DROP COLUMN color;
@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE toggl_projects ADD COLUMN color TEXT;
@@ -0,0 +1,9 @@
+-- Add up migration script here
+
+-- The last status calculated for each user, which is shown while Toggl can't be reached
+CREATE TABLE last_statuses (
+    user_key BIGINT PRIMARY KEY REFERENCES users(user_key) ON DELETE CASCADE,
+    -- As JSON, which is null if the user had no projects
+    status TEXT NOT NULL,
+    calculated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Add up migration script here
+
+-- A new webhook's secret is shown once, on the first visit to the webhooks page after it's added
+ALTER TABLE webhooks ADD COLUMN secret_shown BOOLEAN NOT NULL DEFAULT TRUE;
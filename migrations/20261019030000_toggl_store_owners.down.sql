@@ -0,0 +1,41 @@
+-- Add down migration script here
+DROP TABLE toggl_projects;
+DROP TABLE toggl_entries;
+DROP TABLE toggl_workspaces;
+
+ALTER TABLE users DROP COLUMN toggl_user_id;
+
+CREATE TABLE toggl_workspaces (
+    workspace_id BIGINT PRIMARY KEY,
+    hooked_at TIMESTAMPTZ,
+
+    -- The day the stored entries go back to, which is NULL until they've first been fetched
+    entries_since DATE,
+    synced_at TIMESTAMPTZ
+);
+
+CREATE TABLE toggl_entries (
+    workspace_id BIGINT NOT NULL REFERENCES toggl_workspaces(workspace_id) ON DELETE CASCADE,
+    entry_id BIGINT NOT NULL,
+    project_id BIGINT,
+    description TEXT NOT NULL,
+    client TEXT,
+    tags TEXT[] NOT NULL,
+    billable BOOLEAN NOT NULL,
+    start TIMESTAMPTZ NOT NULL,
+    -- In milliseconds, as the Reports API gives it
+    duration BIGINT NOT NULL,
+    PRIMARY KEY (workspace_id, entry_id)
+);
+
+CREATE INDEX toggl_entries_start ON toggl_entries(workspace_id, start);
+
+CREATE TABLE toggl_projects (
+    workspace_id BIGINT NOT NULL REFERENCES toggl_workspaces(workspace_id) ON DELETE CASCADE,
+    project_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    active BOOLEAN NOT NULL,
+    client TEXT,
+    color TEXT,
+    PRIMARY KEY (workspace_id, project_id)
+);
@@ -0,0 +1,54 @@
+-- Add up migration script here
+
+-- What a Toggl API token can see of a workspace depends on whose it is, so what's stored is kept
+-- for each Toggl user rather than shared by everyone in the workspace. It's all fetched again.
+DROP TABLE toggl_projects;
+DROP TABLE toggl_entries;
+DROP TABLE toggl_workspaces;
+
+-- Looked up the first time it's needed, and forgotten when the user's API token is replaced
+ALTER TABLE users ADD COLUMN toggl_user_id BIGINT;
+
+CREATE TABLE toggl_workspaces (
+    -- Whose API token or webhook subscription what's stored was seen with
+    toggl_user_id BIGINT NOT NULL,
+    workspace_id BIGINT NOT NULL,
+    hooked_at TIMESTAMPTZ,
+
+    -- The day the stored entries go back to, which is NULL until they've first been fetched
+    entries_since DATE,
+    synced_at TIMESTAMPTZ,
+    PRIMARY KEY (toggl_user_id, workspace_id)
+);
+
+CREATE TABLE toggl_entries (
+    toggl_user_id BIGINT NOT NULL,
+    workspace_id BIGINT NOT NULL,
+    entry_id BIGINT NOT NULL,
+    project_id BIGINT,
+    description TEXT NOT NULL,
+    client TEXT,
+    tags TEXT[] NOT NULL,
+    billable BOOLEAN NOT NULL,
+    start TIMESTAMPTZ NOT NULL,
+    -- In milliseconds, as the Reports API gives it
+    duration BIGINT NOT NULL,
+    PRIMARY KEY (toggl_user_id, workspace_id, entry_id),
+    FOREIGN KEY (toggl_user_id, workspace_id)
+        REFERENCES toggl_workspaces(toggl_user_id, workspace_id) ON DELETE CASCADE
+);
+
+CREATE INDEX toggl_entries_start ON toggl_entries(toggl_user_id, workspace_id, start);
+
+CREATE TABLE toggl_projects (
+    toggl_user_id BIGINT NOT NULL,
+    workspace_id BIGINT NOT NULL,
+    project_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    active BOOLEAN NOT NULL,
+    client TEXT,
+    color TEXT,
+    PRIMARY KEY (toggl_user_id, workspace_id, project_id),
+    FOREIGN KEY (toggl_user_id, workspace_id)
+        REFERENCES toggl_workspaces(toggl_user_id, workspace_id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE projects_to_toggl_projects DROP COLUMN last_known_active;
+ALTER TABLE projects_to_toggl_projects DROP COLUMN last_known_client;
+ALTER TABLE projects_to_toggl_projects DROP COLUMN last_known_color;
@@ -0,0 +1,7 @@
+-- Add up migration script here
+
+-- So that a user's projects can be shown as they were last seen in Toggl while it can't be reached,
+-- without relying on what anyone else in the workspace has stored
+ALTER TABLE projects_to_toggl_projects ADD COLUMN last_known_color TEXT;
+ALTER TABLE projects_to_toggl_projects ADD COLUMN last_known_client TEXT;
+ALTER TABLE projects_to_toggl_projects ADD COLUMN last_known_active BOOLEAN;
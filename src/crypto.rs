@@ -0,0 +1,228 @@
+use argon2::{
+    password_hash::{phc::PasswordHash, PasswordHasher, PasswordVerifier},
+    Argon2,
+};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use chacha20poly1305::{
+    aead::{Aead, Generate, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hmac::{Hmac, Mac};
+use rand::distr::{Alphanumeric, SampleString};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::errors::{AppError, AppResult};
+
+mod tests;
+
+pub static SECRET_KEY_VARIABLE: &str = "YOTTACLOCK_SECRET_KEY";
+pub static API_TOKEN_PREFIX: &str = "yc_";
+pub static WEBHOOK_SECRET_PREFIX: &str = "whsec_";
+
+const NONCE_LENGTH: usize = 12;
+
+/// The server's secret, used to encrypt the Toggl API tokens stored in the database and to
+/// derive lookup digests for them
+#[derive(Clone)]
+pub struct ServerKey {
+    cipher: ChaCha20Poly1305,
+    digest_key: Vec<u8>,
+    csrf_key: Vec<u8>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServerKeyError {
+    #[error("{SECRET_KEY_VARIABLE} is not valid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("{SECRET_KEY_VARIABLE} must be at least 32 bytes long")]
+    TooShort,
+}
+
+impl ServerKey {
+    pub fn from_base64(encoded: &str) -> Result<Self, ServerKeyError> {
+        let secret = STANDARD.decode(encoded.trim())?;
+
+        if secret.len() < 32 {
+            return Err(ServerKeyError::TooShort);
+        }
+
+        // Use separate keys for encryption and for digests
+        let encryption_key = derive_key(&secret, b"toggl api token encryption");
+        let digest_key = derive_key(&secret, b"toggl api token digest");
+        let csrf_key = derive_key(&secret, b"csrf token");
+
+        Ok(Self {
+            cipher: ChaCha20Poly1305::new(
+                &Key::try_from(encryption_key.as_slice()).expect("HMAC-SHA256 outputs 32 bytes"),
+            ),
+            digest_key,
+            csrf_key,
+        })
+    }
+
+    /// Encrypt a secret, prefixing the ciphertext with the nonce that was used
+    pub fn encrypt(&self, plaintext: &str) -> AppResult<Vec<u8>> {
+        let nonce = Nonce::generate();
+
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| AppError::Encryption)?;
+
+        Ok(nonce.iter().copied().chain(ciphertext).collect())
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> AppResult<String> {
+        if data.len() < NONCE_LENGTH {
+            return Err(AppError::Encryption);
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+        let nonce = Nonce::try_from(nonce).map_err(|_| AppError::Encryption)?;
+
+        let plaintext = self
+            .cipher
+            .decrypt(&nonce, ciphertext)
+            .map_err(|_| AppError::Encryption)?;
+
+        String::from_utf8(plaintext).map_err(|_| AppError::Encryption)
+    }
+
+    /// A deterministic digest of a secret, so that it can be looked up without storing it
+    pub fn digest(&self, secret: &str) -> Vec<u8> {
+        derive_key(&self.digest_key, secret.as_bytes())
+    }
+
+    /// The CSRF token for a session, derived from the session token so that it doesn't need to be
+    /// stored
+    pub fn csrf_token(&self, session_token: &str) -> String {
+        URL_SAFE_NO_PAD.encode(derive_key(&self.csrf_key, session_token.as_bytes()))
+    }
+
+    pub fn verify_csrf_token(&self, session_token: &str, csrf_token: &str) -> bool {
+        let Ok(tag) = URL_SAFE_NO_PAD.decode(csrf_token) else {
+            return false;
+        };
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.csrf_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(session_token.as_bytes());
+        mac.verify_slice(&tag).is_ok()
+    }
+}
+
+fn derive_key(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+pub fn hash_password(password: &str) -> AppResult<String> {
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes())?
+        .to_string())
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(password_hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// A new personal API token, shown to the user once and only stored as a hash
+pub fn generate_api_token() -> String {
+    format!(
+        "{}{}",
+        API_TOKEN_PREFIX,
+        Alphanumeric.sample_string(&mut rand::rng(), 40),
+    )
+}
+
+/// API tokens are random enough that a fast hash is sufficient, unlike passwords
+pub fn hash_api_token(api_token: &str) -> Vec<u8> {
+    Sha256::digest(api_token.as_bytes()).to_vec()
+}
+
+/// A new webhook signing secret, shown to the user once and stored encrypted so that payloads can
+/// be signed with it
+pub fn generate_webhook_secret() -> String {
+    format!(
+        "{}{}",
+        WEBHOOK_SECRET_PREFIX,
+        Alphanumeric.sample_string(&mut rand::rng(), 40),
+    )
+}
+
+/// The signature of a webhook payload, written as `sha256=<hex HMAC-SHA256 of the payload>`
+pub fn sign_webhook_payload(secret: &str, payload: &[u8]) -> String {
+    let tag = derive_key(secret.as_bytes(), payload);
+
+    let hex: String = tag.iter().map(|byte| format!("{byte:02x}")).collect();
+
+    format!("sha256={hex}")
+}
+
+/// Whether a signature written as `sha256=<hex HMAC-SHA256 of the payload>` is the payload's, as
+/// Toggl signs the webhook events it sends
+pub fn verify_webhook_signature(secret: &str, payload: &[u8], signature: &str) -> bool {
+    let Some(hex) = signature.strip_prefix("sha256=") else {
+        return false;
+    };
+
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return false;
+    }
+
+    let tag: Option<Vec<u8>> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect();
+
+    let Some(tag) = tag else {
+        return false;
+    };
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    mac.verify_slice(&tag).is_ok()
+}
+
+/// Encrypt the Toggl API tokens of users who signed up before they were encrypted at rest
+pub async fn encrypt_legacy_toggl_api_keys(pool: &PgPool, server_key: &ServerKey) -> AppResult<()> {
+    let records = sqlx::query!(
+        r#"SELECT user_key, toggl_api_key AS "toggl_api_key!"
+        FROM users
+        WHERE toggl_api_key IS NOT NULL"#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !records.is_empty() {
+        info!("Encrypting {} legacy Toggl API tokens", records.len());
+    }
+
+    for record in records {
+        sqlx::query!(
+            "UPDATE users
+            SET toggl_api_key = NULL, toggl_api_key_digest = $1, toggl_api_key_ciphertext = $2
+            WHERE user_key = $3",
+            server_key.digest(&record.toggl_api_key),
+            server_key.encrypt(&record.toggl_api_key)?,
+            record.user_key,
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+#![cfg(test)]
+
+use super::*;
+
+fn example_server_key() -> ServerKey {
+    ServerKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap()
+}
+
+#[test]
+fn test_encryption_round_trip() {
+    let server_key = example_server_key();
+
+    let ciphertext = server_key
+        .encrypt("1971800d4d82861d8f2c1651fea4d212")
+        .unwrap();
+
+    assert_ne!(ciphertext, b"1971800d4d82861d8f2c1651fea4d212");
+    assert_eq!(
+        server_key.decrypt(&ciphertext).unwrap(),
+        "1971800d4d82861d8f2c1651fea4d212",
+    );
+}
+
+#[test]
+fn test_digest_is_deterministic() {
+    let server_key = example_server_key();
+
+    assert_eq!(
+        server_key.digest("1971800d4d82861d8f2c1651fea4d212"),
+        server_key.digest("1971800d4d82861d8f2c1651fea4d212"),
+    );
+    assert_ne!(
+        server_key.digest("1971800d4d82861d8f2c1651fea4d212"),
+        server_key.digest("1971800d4d82861d8f2c1651fea4d213"),
+    );
+}
+
+#[test]
+fn test_short_server_key() {
+    assert!(matches!(
+        ServerKey::from_base64("c2hvcnQ="),
+        Err(ServerKeyError::TooShort),
+    ));
+}
+
+#[test]
+fn test_password_hash() {
+    let password_hash = hash_password("correct horse battery staple").unwrap();
+
+    assert!(verify_password(
+        "correct horse battery staple",
+        &password_hash
+    ));
+    assert!(!verify_password("Tr0ub4dor&3", &password_hash));
+}
+
+#[test]
+fn test_api_token_hash() {
+    let api_token = generate_api_token();
+
+    assert!(api_token.starts_with(API_TOKEN_PREFIX));
+    assert_ne!(api_token, generate_api_token());
+    assert_eq!(hash_api_token(&api_token), hash_api_token(&api_token));
+    assert_ne!(
+        hash_api_token(&api_token),
+        hash_api_token(&generate_api_token())
+    );
+}
+
+#[test]
+fn test_webhook_signature() {
+    let secret = generate_webhook_secret();
+
+    assert!(secret.starts_with(WEBHOOK_SECRET_PREFIX));
+    assert_ne!(secret, generate_webhook_secret());
+
+    assert_eq!(
+        sign_webhook_payload("whsec_example", br#"{"event":"day_started"}"#),
+        "sha256=c987278ba79247ed4475699e42c2b57c7e14f05b2e4fa460d2b60f7c5e6e8424",
+    );
+}
+
+#[test]
+fn test_verify_webhook_signature() {
+    let payload = br#"{"event":"day_started"}"#;
+    let signature = sign_webhook_payload("whsec_example", payload);
+
+    assert!(verify_webhook_signature(
+        "whsec_example",
+        payload,
+        &signature
+    ));
+    assert!(verify_webhook_signature(
+        "whsec_example",
+        payload,
+        &signature.to_uppercase().replace("SHA256=", "sha256="),
+    ));
+    assert!(!verify_webhook_signature(
+        "whsec_other",
+        payload,
+        &signature
+    ));
+    assert!(!verify_webhook_signature(
+        "whsec_example",
+        b"{}",
+        &signature
+    ));
+    assert!(!verify_webhook_signature(
+        "whsec_example",
+        payload,
+        signature.trim_start_matches("sha256="),
+    ));
+    assert!(!verify_webhook_signature(
+        "whsec_example",
+        payload,
+        "sha256=not hex"
+    ));
+    assert!(!verify_webhook_signature(
+        "whsec_example",
+        payload,
+        "sha256=abc"
+    ));
+}
+
+#[test]
+fn test_csrf_token() {
+    let server_key = example_server_key();
+
+    let csrf_token = server_key.csrf_token("session");
+
+    assert!(server_key.verify_csrf_token("session", &csrf_token));
+    assert!(!server_key.verify_csrf_token("other session", &csrf_token));
+    assert!(!server_key.verify_csrf_token("session", "not base64!"));
+    assert!(!server_key.verify_csrf_token("session", ""));
+}
@@ -0,0 +1,105 @@
+use axum::{
+    body::{self, Body},
+    extract::{Extension, FromRequest, FromRequestParts, Request},
+    http::{header::CONTENT_TYPE, request::Parts},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::{CookieJar, Form, FormRejection};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{crypto::ServerKey, errors::AppError, session::SESSION_COOKIE_NAME};
+
+mod tests;
+
+/// Form bodies larger than this are rejected, matching axum's default body limit
+const FORM_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+fn invalid_form(rejection: FormRejection) -> Response {
+    AppError::Validation(rejection.body_text()).into_response()
+}
+
+/// The current session's CSRF token, to be rendered into every form as `csrf_token`
+pub struct CsrfToken(pub String);
+
+impl<S> FromRequestParts<S> for CsrfToken
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Extension(server_key) = Extension::<ServerKey>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let cookie_jar = CookieJar::from_headers(&parts.headers);
+
+        let Some(session_token) = cookie_jar.get(SESSION_COOKIE_NAME) else {
+            return Err(Redirect::to("/login/").into_response());
+        };
+
+        Ok(Self(server_key.csrf_token(session_token.value())))
+    }
+}
+
+#[derive(Deserialize)]
+struct CsrfField {
+    #[serde(default)]
+    csrf_token: String,
+}
+
+/// For forms that have nothing but a CSRF token
+#[derive(Deserialize)]
+pub struct NoFields {}
+
+/// A form that is only accepted if it was submitted with the current session's CSRF token
+pub struct CsrfForm<T>(pub T);
+
+impl<S, T> FromRequest<S> for CsrfForm<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let (mut parts, body) = request.into_parts();
+
+        let Extension(server_key) = Extension::<ServerKey>::from_request_parts(&mut parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let bytes = body::to_bytes(body, FORM_BODY_LIMIT).await.map_err(|_| {
+            AppError::Validation("The form is too large".to_owned()).into_response()
+        })?;
+
+        // The form is parsed twice: Once for the CSRF token and once for the fields the handler wants
+        let mut csrf_request = Request::new(Body::from(bytes.clone()));
+        *csrf_request.method_mut() = parts.method.clone();
+        if let Some(content_type) = parts.headers.get(CONTENT_TYPE) {
+            csrf_request
+                .headers_mut()
+                .insert(CONTENT_TYPE, content_type.clone());
+        }
+
+        let Form(CsrfField { csrf_token }) = Form::from_request(csrf_request, state)
+            .await
+            .map_err(invalid_form)?;
+
+        let cookie_jar = CookieJar::from_headers(&parts.headers);
+        let session_token = cookie_jar
+            .get(SESSION_COOKIE_NAME)
+            .map(|cookie| cookie.value())
+            .unwrap_or_default();
+
+        if session_token.is_empty() || !server_key.verify_csrf_token(session_token, &csrf_token) {
+            return Err(AppError::CsrfMismatch.into_response());
+        }
+
+        let Form(form) = Form::from_request(Request::from_parts(parts, Body::from(bytes)), state)
+            .await
+            .map_err(invalid_form)?;
+
+        Ok(Self(form))
+    }
+}
@@ -0,0 +1,72 @@
+#![cfg(test)]
+
+use axum::http::{header::COOKIE, StatusCode};
+
+use super::*;
+
+fn example_server_key() -> ServerKey {
+    ServerKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap()
+}
+
+#[derive(Deserialize)]
+struct ExampleForm {
+    name: String,
+}
+
+fn form_request(session_token: Option<&str>, body: String) -> Request {
+    let mut request = Request::post("/")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(body))
+        .unwrap();
+
+    if let Some(session_token) = session_token {
+        request.headers_mut().insert(
+            COOKIE,
+            format!("{SESSION_COOKIE_NAME}={session_token}")
+                .parse()
+                .unwrap(),
+        );
+    }
+
+    request.extensions_mut().insert(example_server_key());
+    request
+}
+
+#[tokio::test]
+async fn test_csrf_form() {
+    let csrf_token = example_server_key().csrf_token("session");
+
+    let accepted = CsrfForm::<ExampleForm>::from_request(
+        form_request(
+            Some("session"),
+            format!("name=Thesis&csrf_token={csrf_token}"),
+        ),
+        &(),
+    )
+    .await;
+    assert_eq!(accepted.ok().unwrap().0.name, "Thesis");
+
+    let missing_token = CsrfForm::<ExampleForm>::from_request(
+        form_request(Some("session"), "name=Thesis".to_owned()),
+        &(),
+    )
+    .await;
+    assert_eq!(missing_token.err().unwrap().status(), StatusCode::FORBIDDEN);
+
+    let other_session = CsrfForm::<ExampleForm>::from_request(
+        form_request(
+            Some("other session"),
+            format!("name=Thesis&csrf_token={csrf_token}"),
+        ),
+        &(),
+    )
+    .await;
+    assert_eq!(other_session.err().unwrap().status(), StatusCode::FORBIDDEN);
+
+    let no_session = CsrfForm::<ExampleForm>::from_request(
+        form_request(None, format!("name=Thesis&csrf_token={csrf_token}")),
+        &(),
+    )
+    .await;
+    assert_eq!(no_session.err().unwrap().status(), StatusCode::FORBIDDEN);
+}
@@ -1,23 +1,57 @@
-use std::{num::ParseIntError, str::FromStr};
+use std::{
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+    str::FromStr,
+};
 
+use askama::Template;
 use axum::{
-    http::{header::InvalidHeaderValue, StatusCode},
-    response::{IntoResponse, Response},
+    extract::rejection::{ExtensionRejection, JsonRejection, PathRejection, QueryRejection},
+    http::{header::InvalidHeaderValue, header::CONTENT_TYPE, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
 };
 use chrono_tz::Tz;
+use serde::Serialize;
 use tracing::error;
+use utoipa::ToSchema;
+
+use crate::validation::FieldErrors;
+
+mod tests;
 
 #[derive(thiserror::Error, Debug)]
-pub enum InternalError {
+pub enum AppError {
+    #[error("Validation error: {0}")]
+    Validation(String),
+
+    #[error("Validation error: {0:?}")]
+    InvalidFields(FieldErrors),
+
+    #[error("Not found")]
+    NotFound,
+
+    #[error("Unauthenticated")]
+    Unauthenticated,
+
+    #[error("Forbidden")]
+    Forbidden,
+
+    #[error("CSRF token mismatch")]
+    CsrfMismatch,
+
+    #[error("Toggl error: {0}")]
+    Toggl(#[from] reqwest::Error),
+
+    #[error("Fatal missing extension error: {0}")]
+    MissingExtension(#[from] ExtensionRejection),
+
     #[error("Fatal sqlx error: {0}")]
     Sqlx(#[from] sqlx::Error),
 
     #[error("Fatal askama error: {0}")]
     Askama(#[from] askama::Error),
 
-    #[error("Fatal reqwest error: {0}")]
-    Reqwest(#[from] reqwest::Error),
-
     #[error("Fatal InvalidHeaderValue error: {0}")]
     InvalidHeaderValue(#[from] InvalidHeaderValue),
 
@@ -26,13 +60,245 @@ pub enum InternalError {
 
     #[error("Fatal ParseInt error: {0}")]
     ParseInt(#[from] ParseIntError),
+
+    #[error("Fatal regex error: {0}")]
+    Regex(#[from] regex::Error),
+
+    #[error("Fatal encryption error")]
+    Encryption,
+
+    #[error("Fatal password hash error: {0}")]
+    PasswordHash(#[from] argon2::password_hash::Error),
+}
+
+pub type AppResult<T, E = AppError> = Result<T, E>;
+
+/// Who's at fault for an error, which decides how it's reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Validation,
+    Auth,
+    Upstream,
+    Internal,
+}
+
+/// Why Toggl couldn't be relied on for a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TogglOutage {
+    /// Toggl couldn't be connected to or didn't respond in time
+    Unreachable,
+    /// Toggl kept limiting how often requests could be sent
+    RateLimited,
+    /// Toggl kept failing with errors of its own
+    ServerError,
+}
+
+impl Display for TogglOutage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TogglOutage::Unreachable => write!(f, "Toggl couldn't be reached"),
+            TogglOutage::RateLimited => {
+                write!(f, "Toggl is limiting how often data can be requested")
+            }
+            TogglOutage::ServerError => write!(f, "Toggl is having problems"),
+        }
+    }
+}
+
+impl AppError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Validation(_) | Self::InvalidFields(_) | Self::NotFound => {
+                ErrorCategory::Validation
+            }
+            Self::Unauthenticated | Self::Forbidden | Self::CsrfMismatch => ErrorCategory::Auth,
+            Self::Toggl(_) => ErrorCategory::Upstream,
+            _ => ErrorCategory::Internal,
+        }
+    }
+
+    /// Why Toggl couldn't be relied on, if the error is its fault rather than a problem with the
+    /// request
+    pub fn toggl_outage(&self) -> Option<TogglOutage> {
+        let Self::Toggl(error) = self else {
+            return None;
+        };
+
+        match error.status() {
+            Some(StatusCode::TOO_MANY_REQUESTS) => Some(TogglOutage::RateLimited),
+            Some(status) if status.is_server_error() => Some(TogglOutage::ServerError),
+            None if error.is_connect() || error.is_timeout() => Some(TogglOutage::Unreachable),
+            _ => None,
+        }
+    }
+
+    /// Whether Toggl couldn't be reached or wouldn't respond, as opposed to refusing the request
+    pub fn toggl_unreachable(&self) -> bool {
+        self.toggl_outage().is_some()
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::Validation(_) => StatusCode::BAD_REQUEST,
+            // The request could be read, but what's in it can't be used
+            Self::InvalidFields(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Self::Forbidden | Self::CsrfMismatch => StatusCode::FORBIDDEN,
+            Self::Toggl(_) => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// A short summary of the error that's safe to show to the user
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Validation(_) | Self::InvalidFields(_) => "Invalid input",
+            Self::NotFound => "Not found",
+            Self::Unauthenticated => "Not logged in",
+            Self::Forbidden => "Not allowed",
+            Self::CsrfMismatch => "Form expired",
+            Self::Toggl(_) => "Toggl error",
+            _ => "Something went wrong",
+        }
+    }
+
+    /// An explanation of the error that's safe to show to the user
+    pub fn detail(&self) -> String {
+        match self {
+            Self::Validation(message) => message.clone(),
+            Self::InvalidFields(errors) => errors.summary(),
+            Self::NotFound => "There's nothing here, or it belongs to someone else.".to_owned(),
+            Self::Unauthenticated => "Log in or provide a valid API token.".to_owned(),
+            Self::Forbidden => "Your API token doesn't have the scope needed for this.".to_owned(),
+            Self::CsrfMismatch => "This form couldn't be verified, probably because it was \
+                opened before you last logged in. Go back, reload the page and try again."
+                .to_owned(),
+            Self::Toggl(error) => match error.status() {
+                Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => "Toggl didn't accept \
+                    your API token. Replace it in your account settings."
+                    .to_owned(),
+                Some(StatusCode::TOO_MANY_REQUESTS) => "Toggl is limiting how often your data \
+                    can be requested. Try again in a minute."
+                    .to_owned(),
+                _ => "Toggl couldn't be reached. Try again later.".to_owned(),
+            },
+            _ => "The error has been logged.".to_owned(),
+        }
+    }
+
+    /// Errors that aren't the user's fault are logged before they're reported
+    pub fn log(&self) {
+        if matches!(
+            self.category(),
+            ErrorCategory::Upstream | ErrorCategory::Internal
+        ) {
+            error!("{}", self);
+        }
+    }
 }
 
-pub type InternalResult<T, E = InternalError> = Result<T, E>;
+#[derive(Template)]
+#[template(path = "error.html")]
+struct ErrorTemplate<'a> {
+    title: &'a str,
+    detail: &'a str,
+}
 
-impl IntoResponse for InternalError {
+/// Browser routes get an HTML error page
+impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        error!("{}", self);
-        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        self.log();
+
+        let template = ErrorTemplate {
+            title: self.title(),
+            detail: &self.detail(),
+        };
+
+        match template.render() {
+            Ok(body) => (self.status(), Html(body)).into_response(),
+            Err(render_error) => {
+                error!("Fatal askama error: {}", render_error);
+                self.status().into_response()
+            }
+        }
+    }
+}
+
+/// An error from a JSON API route, which is reported as a problem document (RFC 9457)
+#[derive(Debug)]
+pub struct ApiError(pub AppError);
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+impl<E> From<E> for ApiError
+where
+    E: Into<AppError>,
+{
+    fn from(error: E) -> Self {
+        Self(error.into())
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::Validation(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::Validation(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::Validation(rejection.body_text())
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProblemDocument {
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    pub title: &'static str,
+    pub status: u16,
+    pub detail: String,
+    pub category: ErrorCategory,
+    /// What's wrong with each field of the request body, for invalid input
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<std::collections::BTreeMap<String, String>>)]
+    pub errors: Option<FieldErrors>,
+}
+
+impl From<&AppError> for ProblemDocument {
+    fn from(error: &AppError) -> Self {
+        Self {
+            problem_type: "about:blank",
+            title: error.title(),
+            status: error.status().as_u16(),
+            detail: error.detail(),
+            category: error.category(),
+            errors: match error {
+                AppError::InvalidFields(errors) => Some(errors.clone()),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        self.0.log();
+
+        (
+            self.0.status(),
+            [(CONTENT_TYPE, "application/problem+json")],
+            Json(ProblemDocument::from(&self.0)),
+        )
+            .into_response()
     }
 }
@@ -0,0 +1,44 @@
+#![cfg(test)]
+
+use axum::body;
+
+use super::*;
+
+#[test]
+fn test_error_categories() {
+    let validation = AppError::Validation("The daily goal must be positive".to_owned());
+    assert_eq!(validation.category(), ErrorCategory::Validation);
+    assert_eq!(validation.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(validation.detail(), "The daily goal must be positive");
+
+    let invalid_fields = AppError::InvalidFields(FieldErrors::new());
+    assert_eq!(invalid_fields.category(), ErrorCategory::Validation);
+    assert_eq!(invalid_fields.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    assert_eq!(AppError::Unauthenticated.category(), ErrorCategory::Auth);
+    assert_eq!(AppError::Unauthenticated.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(AppError::CsrfMismatch.status(), StatusCode::FORBIDDEN);
+
+    let internal = AppError::Sqlx(sqlx::Error::RowNotFound);
+    assert_eq!(internal.category(), ErrorCategory::Internal);
+    assert_eq!(internal.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(!internal.detail().contains("row"));
+    assert_eq!(internal.toggl_outage(), None);
+}
+
+#[tokio::test]
+async fn test_problem_document() {
+    let response = ApiError(AppError::Forbidden).into_response();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/problem+json");
+
+    let body = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(problem["status"], 403);
+    assert_eq!(problem["category"], "auth");
+    assert_eq!(problem["title"], "Not allowed");
+}
@@ -27,3 +27,17 @@ impl Display for HumanDuration {
         write!(f, "{}{}:{:0>2}:{:0>2}", sign, hours, minutes, seconds)
     }
 }
+
+/// Durations as a number of milliseconds, for fields with `#[serde(with = "milliseconds")]`
+pub mod milliseconds {
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(duration.num_milliseconds())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::milliseconds(i64::deserialize(deserializer)?))
+    }
+}
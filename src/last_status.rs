@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use sqlx::PgPool;
+use tracing::warn;
+
+use crate::{
+    crypto::ServerKey,
+    errors::{AppResult, TogglOutage},
+    session::UserKey,
+    toggl::{calculate_goals, Status},
+};
+
+mod tests;
+
+/// Why a status is the last one calculated rather than a current one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Staleness {
+    /// When the status was calculated
+    pub as_of: DateTime<Utc>,
+    pub outage: TogglOutage,
+}
+
+/// Where the user stands, or where they stood when it was last calculated if Toggl can't be
+/// reached
+///
+/// The status is `None` if the user has no projects, and the staleness is `None` unless the status
+/// is the last one calculated.
+pub async fn calculate_goals_or_last(
+    user_key: UserKey,
+    pool: &PgPool,
+    client: Client,
+    server_key: ServerKey,
+) -> AppResult<(Option<Status>, Option<Staleness>)> {
+    let error = match calculate_goals(user_key, pool.clone(), client, server_key).await {
+        Ok(status) => {
+            save_status(user_key, status.as_ref(), Utc::now(), pool).await?;
+
+            return Ok((status, None));
+        }
+        Err(error) => error,
+    };
+
+    let Some(outage) = error.toggl_outage() else {
+        return Err(error);
+    };
+
+    let Some((status, as_of)) = get_last_status(user_key, pool).await? else {
+        return Err(error);
+    };
+
+    warn!(
+        "Showing user {} the status calculated at {as_of}: {error}",
+        user_key.0,
+    );
+
+    Ok((status, Some(Staleness { as_of, outage })))
+}
+
+/// Keep a status calculated for the user in place of the last one
+pub async fn save_status(
+    user_key: UserKey,
+    status: Option<&Status>,
+    calculated_at: DateTime<Utc>,
+    pool: &PgPool,
+) -> AppResult<()> {
+    let status = serde_json::to_string(&status).expect("Statuses can always be serialized");
+
+    sqlx::query!(
+        "INSERT INTO last_statuses(user_key, status, calculated_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_key) DO UPDATE
+        SET status = EXCLUDED.status, calculated_at = EXCLUDED.calculated_at",
+        user_key.0,
+        status,
+        calculated_at,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The last status calculated for the user and when it was calculated, if there is one
+///
+/// A status that was saved in a format that's since changed is as good as none.
+pub async fn get_last_status(
+    user_key: UserKey,
+    pool: &PgPool,
+) -> AppResult<Option<(Option<Status>, DateTime<Utc>)>> {
+    let record = sqlx::query!(
+        "SELECT status, calculated_at FROM last_statuses WHERE user_key = $1",
+        user_key.0,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record.and_then(|record| {
+        let status = serde_json::from_str(&record.status).ok()?;
+
+        Some((status, record.calculated_at))
+    }))
+}
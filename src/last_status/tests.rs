@@ -0,0 +1,101 @@
+#![cfg(test)]
+
+use chrono::{Duration, NaiveDate, TimeZone};
+
+use super::*;
+use crate::toggl::{Deadline, Goal, Limit, LimitPeriod, LimitUsage, ProjectKey};
+
+fn example_status() -> Status {
+    let goal = |project_key: i64, name: &str| Goal {
+        project_key: ProjectKey(project_key),
+        name: name.to_owned(),
+        project_ids: Vec::new(),
+        time: Duration::minutes(90),
+        goal_today: Duration::hours(1),
+        logged_today: Duration::milliseconds(1500),
+        day_off_today: false,
+        priority: 0,
+        weight: 1,
+        share_today: Duration::minutes(45),
+        deadline: None,
+        projected_finish: None,
+        limit: None,
+    };
+
+    Status {
+        goals: Vec::from([
+            Goal {
+                deadline: Some(Deadline {
+                    date: NaiveDate::from_ymd_opt(2026, 12, 1).unwrap(),
+                    total: Duration::hours(40),
+                }),
+                projected_finish: NaiveDate::from_ymd_opt(2026, 11, 20),
+                ..goal(1, "Novel")
+            },
+            Goal {
+                limit: Some(LimitUsage {
+                    limit: Limit {
+                        time: Duration::hours(1),
+                        period: LimitPeriod::Day,
+                    },
+                    used: Duration::minutes(20),
+                }),
+                ..goal(2, "Games")
+            },
+        ]),
+        total_debt: Duration::minutes(90),
+        today: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(),
+    }
+}
+
+#[sqlx::test]
+async fn test_last_status(pool: PgPool) -> sqlx::Result<()> {
+    let user_key = UserKey(1);
+
+    sqlx::query!(
+        "INSERT INTO users(user_key, toggl_api_key, daily_max, timezone)
+        VALUES ($1, '1971800d4d82861d8f2c1651fea4d212', 3600, 'UTC')",
+        user_key.0,
+    )
+    .execute(&pool)
+    .await?;
+
+    assert!(get_last_status(user_key, &pool).await.unwrap().is_none());
+
+    let calculated_at = Utc.with_ymd_and_hms(2026, 10, 18, 21, 0, 0).unwrap();
+    let status = example_status();
+    save_status(user_key, Some(&status), calculated_at, &pool)
+        .await
+        .unwrap();
+
+    let (last_status, as_of) = get_last_status(user_key, &pool).await.unwrap().unwrap();
+    let last_status = last_status.unwrap();
+    assert_eq!(as_of, calculated_at);
+    assert_eq!(last_status.total_debt, status.total_debt);
+    assert_eq!(last_status.today, status.today);
+    assert_eq!(last_status.goals[0].deadline, status.goals[0].deadline);
+    assert_eq!(
+        last_status.goals[0].projected_finish,
+        status.goals[0].projected_finish
+    );
+    assert_eq!(last_status.goals[1].limit, status.goals[1].limit);
+    assert_eq!(
+        last_status.goals[1].logged_today,
+        Duration::milliseconds(1500)
+    );
+
+    // A user without projects is remembered as such
+    save_status(user_key, None, calculated_at, &pool)
+        .await
+        .unwrap();
+    let (last_status, _) = get_last_status(user_key, &pool).await.unwrap().unwrap();
+    assert!(last_status.is_none());
+
+    // A status saved in an older format is ignored
+    sqlx::query!("UPDATE last_statuses SET status = '{\"debt\": 60}'")
+        .execute(&pool)
+        .await?;
+    assert!(get_last_status(user_key, &pool).await.unwrap().is_none());
+
+    Ok(())
+}
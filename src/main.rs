@@ -1,19 +1,34 @@
 #![forbid(unsafe_code)]
 
 use axum::extract::Extension;
+use crypto::{encrypt_legacy_toggl_api_keys, ServerKey, SECRET_KEY_VARIABLE};
 use dotenvy::dotenv;
+use notifications::Mailer;
 use reqwest::Client;
+use session::TrustedProxies;
 use sqlx::postgres::PgPoolOptions;
 use std::net::SocketAddr;
+use toggl_hooks::TogglHookSecret;
 use tokio::net::TcpListener;
 use tower_http::trace::TraceLayer;
 use tracing::info;
 
+mod crypto;
+mod csrf;
 mod errors;
 mod human_duration;
+mod last_status;
+mod notifications;
+mod plan;
+mod project_settings;
 mod routes;
 mod session;
 mod toggl;
+mod toggl_hooks;
+mod toggl_scheduler;
+mod toggl_sync;
+mod validation;
+mod webhooks;
 
 #[tokio::main]
 async fn main() {
@@ -33,23 +48,74 @@ async fn main() {
         .await
         .expect("Could not run database migrations");
 
+    let server_key = ServerKey::from_base64(
+        &std::env::var(SECRET_KEY_VARIABLE).expect("YOTTACLOCK_SECRET_KEY environmental variable"),
+    )
+    .expect("Invalid server secret key");
+
+    encrypt_legacy_toggl_api_keys(&pool, &server_key)
+        .await
+        .expect("Could not encrypt legacy Toggl API tokens");
+
     let client = Client::builder()
         .use_rustls_tls()
         .https_only(true)
         .build()
         .expect("Could not create reqwest client");
 
+    let notification_client = notifications::notification_client()
+        .expect("Could not create reqwest client for notifications");
+
+    let mailer = Mailer::from_env().expect("Invalid SMTP settings");
+
+    if mailer.is_none() {
+        info!("No SMTP server set up, so email notifications won't be sent");
+    }
+
+    let toggl_hook_secret = TogglHookSecret::from_env();
+
+    if toggl_hook_secret.is_none() {
+        info!("No Toggl webhook secret set up, so Toggl data will be fetched on a schedule");
+    }
+
+    let trusted_proxies = TrustedProxies::from_env().expect("Invalid trusted proxy addresses");
+
+    tokio::spawn(notifications::run(
+        pool.clone(),
+        client.clone(),
+        notification_client.clone(),
+        server_key.clone(),
+        mailer.clone(),
+    ));
+
+    tokio::spawn(webhooks::run(
+        pool.clone(),
+        client.clone(),
+        notification_client,
+        server_key.clone(),
+    ));
+
+    tokio::spawn(toggl_sync::run(
+        pool.clone(),
+        client.clone(),
+        server_key.clone(),
+    ));
+
     let app = routes::router()
         .layer(TraceLayer::new_for_http())
         .layer(Extension(pool))
-        .layer(Extension(client));
+        .layer(Extension(client))
+        .layer(Extension(server_key))
+        .layer(Extension(mailer))
+        .layer(Extension(toggl_hook_secret))
+        .layer(Extension(trusted_proxies));
 
     info!("Starting hyper server");
     axum::serve(
         TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], 8000)))
             .await
             .unwrap(),
-        app,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
     )
     .await
     .expect("Error running hyper server");
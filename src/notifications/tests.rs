@@ -0,0 +1,282 @@
+#![cfg(test)]
+
+use axum::{extract::Extension, routing::post, Json, Router};
+use chrono::Duration;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::mpsc,
+};
+
+use super::*;
+use crate::toggl::{Goal, Limit, LimitPeriod, LimitUsage, ProjectKey};
+
+fn example_summary() -> Summary {
+    let goal = |name: &str, time, limit| Goal {
+        project_key: ProjectKey(1),
+        name: name.to_owned(),
+        project_ids: Vec::new(),
+        time,
+        goal_today: Duration::hours(1),
+        logged_today: Duration::zero(),
+        day_off_today: false,
+        priority: 0,
+        weight: 1,
+        share_today: Duration::minutes(45),
+        deadline: None,
+        projected_finish: None,
+        limit,
+    };
+
+    Summary::from(Status {
+        goals: Vec::from([
+            goal("Thesis", Duration::hours(2), None),
+            goal(
+                "Email",
+                Duration::zero(),
+                Some(LimitUsage {
+                    limit: Limit {
+                        time: Duration::hours(1),
+                        period: LimitPeriod::Day,
+                    },
+                    used: Duration::minutes(80),
+                }),
+            ),
+        ]),
+        total_debt: Duration::minutes(45),
+        today: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(),
+    })
+}
+
+fn example_notification(channel: Channel, destination: String) -> Notification {
+    Notification {
+        notification_key: 1,
+        user_key: UserKey(1),
+        local_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+        channel,
+        destination,
+        last_sent: None,
+    }
+}
+
+#[test]
+fn test_is_due() {
+    let eight = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+    let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+    let yesterday = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+
+    assert!(!is_due(eight, None, today.and_hms_opt(7, 59, 0).unwrap()));
+    assert!(is_due(eight, None, today.and_hms_opt(8, 0, 0).unwrap()));
+    assert!(is_due(
+        eight,
+        Some(yesterday),
+        today.and_hms_opt(13, 0, 0).unwrap()
+    ));
+    assert!(!is_due(
+        eight,
+        Some(today),
+        today.and_hms_opt(13, 0, 0).unwrap()
+    ));
+}
+
+#[test]
+fn test_validate_destination() {
+    assert!(validate_destination(Channel::Email, "someone@example.com").is_empty());
+    assert!(validate_destination(Channel::Email, "someone").has("destination"));
+    assert!(validate_destination(Channel::Webhook, "https://example.com/hook").is_empty());
+    assert!(validate_destination(Channel::Webhook, "ftp://example.com/").has("destination"));
+    assert!(validate_destination(Channel::Webhook, "example.com").has("destination"));
+    assert!(validate_destination(Channel::Webhook, "http://127.0.0.1:8080/").has("destination"));
+}
+
+#[test]
+fn test_is_webhook_url() {
+    assert!(is_webhook_url("https://example.com/hook"));
+    assert!(is_webhook_url("http://93.184.215.14/hook"));
+    assert!(is_webhook_url(
+        "http://[2606:2800:21f:cb07:6820:80da:af6b:8b2c]/hook"
+    ));
+
+    assert!(!is_webhook_url("http://169.254.169.254/latest/meta-data/"));
+    assert!(!is_webhook_url("http://10.0.0.1/"));
+    assert!(!is_webhook_url("http://100.64.0.1/"));
+    assert!(!is_webhook_url("http://0.0.0.0:8080/"));
+    assert!(!is_webhook_url("http://[::1]/"));
+    assert!(!is_webhook_url("http://[fd00::1]/"));
+    assert!(!is_webhook_url("http://[::ffff:127.0.0.1]/"));
+}
+
+#[tokio::test]
+async fn test_public_resolver() {
+    let localhost = PublicResolver.resolve("localhost".parse().unwrap()).await;
+    assert!(localhost.is_err());
+}
+
+#[test]
+fn test_summary_text() {
+    assert_eq!(
+        example_summary().text(),
+        "Left to do today: 0:45:00\n\n\
+        Thesis: 2:00:00 to go, 0:45:00 of it today\n\
+        Email: 0:20:00 over the limit\n",
+    );
+}
+
+#[tokio::test]
+async fn test_deliver_webhook() {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<serde_json::Value>();
+
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(
+                |Extension(sender): Extension<mpsc::UnboundedSender<serde_json::Value>>,
+                 Json(body): Json<serde_json::Value>| async move {
+                    sender.send(body).unwrap();
+                },
+            ),
+        )
+        .layer(Extension(sender));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    // Named rather than given as an address, which webhooks can't be sent to if it's private
+    let port = address.port();
+    let notification =
+        example_notification(Channel::Webhook, format!("http://localhost:{port}/hook"));
+    deliver(&notification, &example_summary(), &Client::new(), None)
+        .await
+        .unwrap();
+
+    let body = receiver.recv().await.unwrap();
+    assert_eq!(body["date"], "2026-10-18");
+    assert_eq!(body["total_debt"], 2700);
+    assert_eq!(body["goals"][0]["name"], "Thesis");
+    assert_eq!(body["goals"][1]["limit_remaining"], -1200);
+
+    let missing =
+        example_notification(Channel::Webhook, format!("http://localhost:{port}/missing"));
+    assert!(deliver(&missing, &example_summary(), &Client::new(), None)
+        .await
+        .is_err());
+
+    let private = example_notification(Channel::Webhook, format!("http://{address}/hook"));
+    assert!(matches!(
+        deliver(&private, &example_summary(), &Client::new(), None).await,
+        Err(NotificationError::PrivateDestination(_)),
+    ));
+}
+
+/// Accept one SMTP connection and return the message sent over it
+async fn fake_smtp_server(listener: TcpListener) -> String {
+    let (stream, _) = listener.accept().await.unwrap();
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+
+    let mut message = String::new();
+    let mut in_data = false;
+    while let Some(line) = lines.next_line().await.unwrap() {
+        if in_data {
+            if line == "." {
+                in_data = false;
+                writer.write_all(b"250 OK\r\n").await.unwrap();
+            } else {
+                message.push_str(&line);
+                message.push('\n');
+            }
+        } else if line.starts_with("DATA") {
+            in_data = true;
+            writer.write_all(b"354 Go ahead\r\n").await.unwrap();
+        } else if line.starts_with("QUIT") {
+            writer.write_all(b"221 Bye\r\n").await.unwrap();
+            break;
+        } else {
+            writer.write_all(b"250 OK\r\n").await.unwrap();
+        }
+    }
+
+    message
+}
+
+#[tokio::test]
+async fn test_deliver_email() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(fake_smtp_server(listener));
+
+    let mailer = Mailer::new(
+        &format!("smtp://{address}"),
+        "Yottaclock <yottaclock@example.com>",
+    )
+    .unwrap();
+
+    let notification = example_notification(Channel::Email, "someone@example.com".to_owned());
+    deliver(
+        &notification,
+        &example_summary(),
+        &Client::new(),
+        Some(&mailer),
+    )
+    .await
+    .unwrap();
+
+    let message = server.await.unwrap();
+    assert!(message.contains("To: someone@example.com"));
+    assert!(message.contains("Subject: Yottaclock summary for 2026-10-18"));
+    assert!(message.contains("Thesis: 2:00:00 to go"));
+
+    assert!(matches!(
+        deliver(&notification, &example_summary(), &Client::new(), None).await,
+        Err(NotificationError::EmailUnavailable),
+    ));
+}
+
+#[sqlx::test]
+async fn test_get_due_notifications(pool: PgPool) -> AppResult<()> {
+    let mut user_keys = Vec::new();
+    for (username, timezone) in [("utc", "UTC"), ("tokyo", "Asia/Tokyo")] {
+        let user_key = sqlx::query!(
+            "INSERT INTO users(username, daily_max, timezone)
+            VALUES ($1, 0, $2)
+            RETURNING user_key",
+            username,
+            timezone,
+        )
+        .fetch_one(&pool)
+        .await?
+        .user_key;
+
+        sqlx::query!(
+            "INSERT INTO notifications(user_key, local_time, channel, destination)
+            VALUES ($1, '12:00', 'webhook', 'https://example.com/hook')",
+            user_key,
+        )
+        .execute(&pool)
+        .await?;
+
+        user_keys.push(user_key);
+    }
+
+    // Noon has passed in Tokyo but not in UTC
+    let now = NaiveDate::from_ymd_opt(2026, 10, 18)
+        .unwrap()
+        .and_hms_opt(6, 0, 0)
+        .unwrap()
+        .and_utc();
+
+    let due = get_due_notifications(&pool, now).await?;
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].0.user_key, UserKey(user_keys[1]));
+    assert_eq!(due[0].1, NaiveDate::from_ymd_opt(2026, 10, 18).unwrap());
+
+    assert_eq!(
+        get_notifications(UserKey(user_keys[0]), &pool).await?[0].channel,
+        Channel::Webhook,
+    );
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use std::cmp::{min, Reverse};
+
+use chrono::Duration;
+
+use crate::toggl::{Goal, ProjectKey};
+
+mod tests;
+
+/// Time set aside for one project in a plan
+#[derive(Debug, Clone, PartialEq)]
+pub struct Allocation {
+    pub project_key: ProjectKey,
+    pub name: String,
+    pub time: Duration,
+}
+
+/// Split the time the user has left today between the projects they owe time to
+///
+/// The projects with the highest priority come first, then the ones with the nearest deadline, then
+/// the ones with the most debt, and each gets all the time it's owed before the next project gets
+/// any, until the time runs out. No more than `daily_max` is planned, and projects that are even or
+/// ahead aren't planned at all.
+pub fn plan(goals: &[Goal], available: Duration, daily_max: Duration) -> Vec<Allocation> {
+    let mut remaining = min(available, daily_max);
+
+    let mut owed: Vec<&Goal> = goals
+        .iter()
+        .filter(|goal| goal.time > Duration::zero())
+        .collect();
+    owed.sort_by_key(|goal| {
+        let deadline = goal.deadline.as_ref().map(|deadline| deadline.date);
+
+        (
+            Reverse(goal.priority),
+            // Projects without a deadline come after the ones with one
+            deadline.is_none(),
+            deadline,
+            Reverse(goal.time),
+            goal.name.as_str(),
+        )
+    });
+
+    let mut allocations = Vec::new();
+    for goal in owed {
+        if remaining <= Duration::zero() {
+            break;
+        }
+
+        let time = min(goal.time, remaining);
+        remaining -= time;
+
+        allocations.push(Allocation {
+            project_key: goal.project_key,
+            name: goal.name.clone(),
+            time,
+        });
+    }
+
+    allocations
+}
+
+/// What a project is owed, and how it ranks against the others when there isn't enough to go round
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Claim {
+    pub owed: Duration,
+    pub priority: i32,
+    pub weight: i32,
+}
+
+/// Split a budget between claims, returning each claim's share in the same order
+///
+/// Higher priorities are paid in full before lower ones get anything. Claims with the same
+/// priority split what's left in proportion to their weights, and whatever a claim doesn't need
+/// goes to the others. No claim gets more than it's owed, so the shares only add up to the whole
+/// budget if the claims do.
+pub fn share(claims: &[Claim], budget: Duration) -> Vec<Duration> {
+    let mut shares = vec![0; claims.len()];
+    let mut remaining = budget.num_seconds().max(0);
+
+    let owed = |index: usize| claims[index].owed.num_seconds().max(0);
+    let weight = |index: usize| i64::from(claims[index].weight.max(1));
+
+    let mut priorities: Vec<i32> = claims.iter().map(|claim| claim.priority).collect();
+    priorities.sort_unstable_by_key(|&priority| Reverse(priority));
+    priorities.dedup();
+
+    for priority in priorities {
+        let mut open: Vec<usize> = (0..claims.len())
+            .filter(|&index| claims[index].priority == priority && owed(index) > 0)
+            .collect();
+
+        while remaining > 0 && !open.is_empty() {
+            let total_weight: i64 = open.iter().map(|&index| weight(index)).sum();
+
+            let mut handed_out = 0;
+            for &index in &open {
+                let fair_share = remaining * weight(index) / total_weight;
+                let given = min(fair_share, owed(index) - shares[index]);
+
+                shares[index] += given;
+                handed_out += given;
+            }
+
+            if handed_out == 0 {
+                // Only a few seconds are left over from rounding down, so hand them out in order
+                for &index in &open {
+                    let given = min(remaining, owed(index) - shares[index]);
+
+                    shares[index] += given;
+                    remaining -= given;
+                }
+            }
+
+            remaining -= handed_out;
+            open.retain(|&index| shares[index] < owed(index));
+        }
+    }
+
+    shares.into_iter().map(Duration::seconds).collect()
+}
@@ -0,0 +1,188 @@
+#![cfg(test)]
+
+use chrono::NaiveDate;
+
+use super::*;
+use crate::toggl::Deadline;
+
+fn goal(project_key: i64, name: &str, time: Duration) -> Goal {
+    Goal {
+        project_key: ProjectKey(project_key),
+        name: name.to_owned(),
+        project_ids: Vec::new(),
+        time,
+        goal_today: Duration::hours(1),
+        logged_today: Duration::zero(),
+        day_off_today: false,
+        priority: 0,
+        weight: 1,
+        share_today: Duration::zero(),
+        deadline: None,
+        projected_finish: None,
+        limit: None,
+    }
+}
+
+#[test]
+fn test_plan() {
+    let goals = [
+        goal(1, "Reading", Duration::minutes(30)),
+        goal(2, "Thesis", Duration::hours(2)),
+        goal(3, "Exercise", Duration::minutes(-20)),
+        goal(4, "Chores", Duration::minutes(30)),
+    ];
+
+    assert_eq!(
+        plan(&goals, Duration::hours(3), Duration::hours(8)),
+        Vec::from([
+            Allocation {
+                project_key: ProjectKey(2),
+                name: "Thesis".to_owned(),
+                time: Duration::hours(2),
+            },
+            Allocation {
+                project_key: ProjectKey(4),
+                name: "Chores".to_owned(),
+                time: Duration::minutes(30),
+            },
+            Allocation {
+                project_key: ProjectKey(1),
+                name: "Reading".to_owned(),
+                time: Duration::minutes(30),
+            },
+        ]),
+    );
+
+    // The daily max is the most that's planned, however much time is available
+    let capped = plan(&goals, Duration::hours(3), Duration::minutes(150));
+    assert_eq!(capped.len(), 2);
+    assert_eq!(capped[1].time, Duration::minutes(30));
+
+    assert!(plan(&goals, Duration::zero(), Duration::hours(8)).is_empty());
+}
+
+#[test]
+fn test_plan_priorities() {
+    let goals = [
+        goal(1, "Reading", Duration::minutes(30)),
+        Goal {
+            priority: 1,
+            ..goal(2, "Chores", Duration::minutes(10))
+        },
+        goal(3, "Thesis", Duration::hours(2)),
+    ];
+
+    let order: Vec<ProjectKey> = plan(&goals, Duration::hours(8), Duration::hours(8))
+        .into_iter()
+        .map(|allocation| allocation.project_key)
+        .collect();
+
+    assert_eq!(order, [ProjectKey(2), ProjectKey(3), ProjectKey(1)]);
+}
+
+#[test]
+fn test_plan_deadlines() {
+    let deadline = |date| {
+        Some(Deadline {
+            date: NaiveDate::from_ymd_opt(2026, 11, date).unwrap(),
+            total: Duration::hours(20),
+        })
+    };
+
+    let goals = [
+        goal(1, "Thesis", Duration::hours(2)),
+        Goal {
+            deadline: deadline(30),
+            ..goal(2, "Essay", Duration::minutes(30))
+        },
+        Goal {
+            deadline: deadline(5),
+            ..goal(3, "Report", Duration::minutes(10))
+        },
+        Goal {
+            priority: 1,
+            ..goal(4, "Chores", Duration::minutes(10))
+        },
+    ];
+
+    // The nearest deadline comes first among projects with the same priority, whatever they owe
+    let order: Vec<ProjectKey> = plan(&goals, Duration::hours(8), Duration::hours(8))
+        .into_iter()
+        .map(|allocation| allocation.project_key)
+        .collect();
+
+    assert_eq!(
+        order,
+        [ProjectKey(4), ProjectKey(3), ProjectKey(2), ProjectKey(1)]
+    );
+}
+
+fn claim(minutes: i64, priority: i32, weight: i32) -> Claim {
+    Claim {
+        owed: Duration::minutes(minutes),
+        priority,
+        weight,
+    }
+}
+
+#[test]
+fn test_share_by_weight() {
+    let claims = [claim(120, 0, 1), claim(120, 0, 3)];
+
+    assert_eq!(
+        share(&claims, Duration::minutes(60)),
+        [Duration::minutes(15), Duration::minutes(45)],
+    );
+}
+
+#[test]
+fn test_share_leftovers() {
+    // What the small claim doesn't need goes to the others
+    let claims = [claim(10, 0, 1), claim(120, 0, 1), claim(120, 0, 1)];
+
+    assert_eq!(
+        share(&claims, Duration::minutes(90)),
+        [
+            Duration::minutes(10),
+            Duration::minutes(40),
+            Duration::minutes(40)
+        ],
+    );
+
+    // Nobody gets more than they're owed, and nothing is owed by claims that are ahead
+    let claims = [claim(10, 0, 1), claim(-30, 0, 1)];
+
+    assert_eq!(
+        share(&claims, Duration::minutes(90)),
+        [Duration::minutes(10), Duration::zero()],
+    );
+}
+
+#[test]
+fn test_share_priorities() {
+    let claims = [claim(60, 0, 1), claim(45, 2, 1), claim(60, 1, 1)];
+
+    assert_eq!(
+        share(&claims, Duration::minutes(90)),
+        [
+            Duration::zero(),
+            Duration::minutes(45),
+            Duration::minutes(45)
+        ],
+    );
+}
+
+#[test]
+fn test_share_rounding() {
+    let claims = [claim(60, 0, 1), claim(60, 0, 1), claim(60, 0, 1)];
+    let shares = share(&claims, Duration::seconds(100));
+
+    assert_eq!(
+        shares
+            .iter()
+            .fold(Duration::zero(), |total, &share| total + share),
+        Duration::seconds(100),
+    );
+    assert_eq!(shares[0], Duration::seconds(34));
+    assert_eq!(shares[2], Duration::seconds(33));
+}
@@ -0,0 +1,609 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use regex::Regex;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::{
+    errors::AppResult,
+    session::UserKey,
+    toggl::{
+        Deadline, DescriptionRegex, GoalFilter, Limit, ProjectId, ProjectKey, TogglProject,
+        WhichWeekdays, WorkspaceId,
+    },
+    validation::FieldErrors,
+};
+
+mod tests;
+
+/// How far ahead a project can start, to catch typos in the year
+const MAX_DAYS_UNTIL_START: i64 = 365;
+
+/// How far ahead a deadline can be, which keeps the days left until it cheap to count
+const MAX_DAYS_UNTIL_DEADLINE: i64 = 10 * 365;
+
+/// The biggest total target a deadline can have, in hours, which keeps what's owed to it and what's
+/// logged towards it from getting too big to add up
+pub const MAX_TARGET_TOTAL_HOURS: i64 = 1_000_000;
+
+/// The heaviest a project can be weighted, so that every project gets a noticeable share
+pub const MAX_WEIGHT: i32 = 100;
+
+/// The Toggl projects that a project can count: the active ones, and the archived ones it already
+/// counts, so that archiving a project in Toggl doesn't stop the ones counting it from being saved
+pub fn allowed_project_ids(
+    toggl_projects: &[TogglProject],
+    existing_project_ids: &[ProjectId],
+) -> Vec<ProjectId> {
+    toggl_projects
+        .iter()
+        .filter(|toggl_project| {
+            toggl_project.active || existing_project_ids.contains(&toggl_project.project_id())
+        })
+        .map(TogglProject::project_id)
+        .collect()
+}
+
+/// A project as the user set it up, before it's matched against their Toggl data
+///
+/// Both the new project form and the JSON API are turned into this before they're checked and
+/// stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectSettings {
+    pub name: Option<String>,
+    pub project_ids: Vec<ProjectId>,
+    pub clients: Vec<String>,
+    pub tags: Vec<String>,
+    pub no_project: bool,
+    pub description_substrings: Vec<String>,
+    pub description_regexes: Vec<String>,
+    pub billable_only: bool,
+    pub required_tags: Vec<String>,
+    pub excluded_tags: Vec<String>,
+    pub starting_date: NaiveDate,
+    pub daily_goal: Duration,
+    pub weekdays: WhichWeekdays,
+    /// Projects with a higher priority get their share of the day before any others do
+    pub priority: i32,
+    /// How big a share of the day the project gets compared to others with the same priority
+    pub weight: i32,
+    /// Replaces `daily_goal`, which is stored as zero, if the project has one
+    pub deadline: Option<Deadline>,
+    /// Makes the project a cap rather than a goal, in which case `daily_goal` is stored as zero
+    pub limit: Option<Limit>,
+}
+
+impl ProjectSettings {
+    pub fn filter(&self) -> AppResult<GoalFilter> {
+        let description_regexes = self
+            .description_regexes
+            .iter()
+            .map(|pattern| Ok(DescriptionRegex(Regex::new(pattern)?)))
+            .collect::<AppResult<_>>()?;
+
+        Ok(GoalFilter {
+            project_ids: self.project_ids.iter().copied().collect(),
+            clients: self.clients.iter().cloned().collect(),
+            tags: self.tags.iter().cloned().collect(),
+            no_project: self.no_project,
+            description_substrings: self.description_substrings.iter().cloned().collect(),
+            description_regexes,
+            billable_only: self.billable_only,
+            required_tags: self.required_tags.iter().cloned().collect(),
+            excluded_tags: self.excluded_tags.iter().cloned().collect(),
+        })
+    }
+
+    /// A project with a deadline works out its daily goal as it goes and a limit has none, so
+    /// neither of them has one stored
+    fn stored_daily_goal(&self) -> Duration {
+        if self.deadline.is_some() || self.limit.is_some() {
+            Duration::zero()
+        } else {
+            self.daily_goal
+        }
+    }
+
+    /// Check the settings against the Toggl projects the user can choose from and today's date
+    pub fn validate(&self, available_project_ids: &[ProjectId], today: NaiveDate) -> FieldErrors {
+        let mut errors = FieldErrors::new();
+
+        // A project has to count at least some of the user's time
+        errors.require(
+            !self.project_ids.is_empty()
+                || !self.clients.is_empty()
+                || !self.tags.is_empty()
+                || self.no_project
+                || !self.description_substrings.is_empty()
+                || !self.description_regexes.is_empty(),
+            "filters",
+            "Choose at least one Toggl project, client, tag or description to count",
+        );
+
+        errors.require(
+            self.project_ids
+                .iter()
+                .all(|project_id| available_project_ids.contains(project_id)),
+            "project_ids",
+            "Some of these Toggl projects aren't in your linked workspaces",
+        );
+
+        if let Some(pattern) = self
+            .description_regexes
+            .iter()
+            .find(|pattern| Regex::new(pattern).is_err())
+        {
+            errors.add(
+                "description_regexes",
+                format!("/{pattern}/ isn't a valid regular expression"),
+            );
+        }
+
+        match (self.deadline, self.limit) {
+            (None, None) => {
+                errors.require(
+                    self.daily_goal > Duration::zero(),
+                    "daily_goal",
+                    "The daily goal has to be more than zero",
+                );
+                errors.require(
+                    self.daily_goal <= Duration::days(1),
+                    "daily_goal",
+                    "The daily goal can't be more than a day",
+                );
+            }
+            (Some(_), Some(_)) => {
+                errors.add("limit", "A project can't have both a deadline and a limit")
+            }
+            (None, Some(limit)) => {
+                errors.require(
+                    limit.time > Duration::zero(),
+                    "limit",
+                    "The limit has to be more than zero",
+                );
+                errors.require(
+                    limit.time <= limit.period.length(),
+                    "limit",
+                    format!("The limit can't be more than a whole {}", limit.period),
+                );
+            }
+            (Some(deadline), None) => {
+                errors.require(
+                    deadline.total > Duration::zero(),
+                    "target_total",
+                    "The total target has to be more than zero",
+                );
+                errors.require(
+                    deadline.total <= Duration::hours(MAX_TARGET_TOTAL_HOURS),
+                    "target_total",
+                    format!("The total target can't be more than {MAX_TARGET_TOTAL_HOURS} hours"),
+                );
+                errors.require(
+                    deadline.date >= self.starting_date,
+                    "deadline",
+                    "The deadline can't be before the starting date",
+                );
+                errors.require(
+                    deadline.date <= today + Duration::days(MAX_DAYS_UNTIL_DEADLINE),
+                    "deadline",
+                    "The deadline can't be more than ten years from now",
+                );
+                // Every day of the week comes up within a week
+                errors.require(
+                    self.starting_date
+                        .iter_days()
+                        .take(7)
+                        .take_while(|&date| date <= deadline.date)
+                        .any(|date| self.weekdays.includes(date.weekday())),
+                    "deadline",
+                    "None of the chosen days of the week fall before the deadline",
+                );
+            }
+        }
+
+        errors.require(
+            self.starting_date <= today + Duration::days(MAX_DAYS_UNTIL_START),
+            "starting_date",
+            "The starting date can't be more than a year from now",
+        );
+
+        errors.require(
+            self.weekdays.num_days() > 0,
+            "weekdays",
+            "Choose at least one day of the week",
+        );
+
+        errors.require(
+            (1..=MAX_WEIGHT).contains(&self.weight),
+            "weight",
+            format!("The weight has to be between 1 and {MAX_WEIGHT}"),
+        );
+
+        errors
+    }
+}
+
+/// Every one of the user's projects
+pub async fn get_all_project_settings(
+    user_key: UserKey,
+    pool: &PgPool,
+) -> AppResult<Vec<(ProjectKey, ProjectSettings)>> {
+    query_project_settings(user_key, None, None, 0, pool).await
+}
+
+/// One of the user's projects, or `None` if they don't have one with this key
+pub async fn get_project_settings(
+    user_key: UserKey,
+    project_key: ProjectKey,
+    pool: &PgPool,
+) -> AppResult<Option<ProjectSettings>> {
+    Ok(
+        query_project_settings(user_key, Some(project_key), None, 0, pool)
+            .await?
+            .pop()
+            .map(|(_, settings)| settings),
+    )
+}
+
+/// Some of the user's projects, ordered by when they were created
+pub async fn get_project_settings_page(
+    user_key: UserKey,
+    limit: i64,
+    offset: i64,
+    pool: &PgPool,
+) -> AppResult<Vec<(ProjectKey, ProjectSettings)>> {
+    query_project_settings(user_key, None, Some(limit), offset, pool).await
+}
+
+async fn query_project_settings(
+    user_key: UserKey,
+    project_key: Option<ProjectKey>,
+    limit: Option<i64>,
+    offset: i64,
+    pool: &PgPool,
+) -> AppResult<Vec<(ProjectKey, ProjectSettings)>> {
+    let records = sqlx::query!(
+        r#"SELECT project_key, name, no_project, billable_only, starting_date, daily_goal,
+            monday, tuesday, wednesday, thursday, friday, saturday, sunday, priority, weight,
+            deadline, target_total, limit_period, limit_time,
+            ARRAY(
+                SELECT workspace_id
+                FROM projects_to_toggl_projects
+                WHERE projects_to_toggl_projects.project_key = projects.project_key
+                ORDER BY workspace_id, project_id
+            ) AS "project_workspace_ids!",
+            ARRAY(
+                SELECT project_id
+                FROM projects_to_toggl_projects
+                WHERE projects_to_toggl_projects.project_key = projects.project_key
+                ORDER BY workspace_id, project_id
+            ) AS "project_ids!",
+            ARRAY(
+                SELECT client
+                FROM projects_to_toggl_clients
+                WHERE projects_to_toggl_clients.project_key = projects.project_key
+                ORDER BY client
+            ) AS "clients!",
+            ARRAY(
+                SELECT tag
+                FROM projects_to_toggl_tags
+                WHERE projects_to_toggl_tags.project_key = projects.project_key
+                ORDER BY tag
+            ) AS "tags!",
+            ARRAY(
+                SELECT pattern
+                FROM projects_to_description_patterns
+                WHERE projects_to_description_patterns.project_key = projects.project_key
+                AND NOT regex
+                ORDER BY pattern
+            ) AS "description_substrings!",
+            ARRAY(
+                SELECT pattern
+                FROM projects_to_description_patterns
+                WHERE projects_to_description_patterns.project_key = projects.project_key
+                AND regex
+                ORDER BY pattern
+            ) AS "description_regexes!",
+            ARRAY(
+                SELECT tag
+                FROM projects_to_tag_restrictions
+                WHERE projects_to_tag_restrictions.project_key = projects.project_key
+                AND NOT excluded
+                ORDER BY tag
+            ) AS "required_tags!",
+            ARRAY(
+                SELECT tag
+                FROM projects_to_tag_restrictions
+                WHERE projects_to_tag_restrictions.project_key = projects.project_key
+                AND excluded
+                ORDER BY tag
+            ) AS "excluded_tags!"
+        FROM projects
+        WHERE user_key = $1
+        AND ($2::BIGINT IS NULL OR project_key = $2)
+        ORDER BY project_key
+        LIMIT $3
+        OFFSET $4"#,
+        user_key.0,
+        project_key.map(|project_key| project_key.0),
+        limit,
+        offset,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|record| {
+            let limit = match record.limit_period.zip(record.limit_time) {
+                Some((period, time)) => Some(Limit {
+                    time: Duration::seconds(time),
+                    period: period
+                        .parse()
+                        .map_err(|error| sqlx::Error::Decode(Box::new(error)))?,
+                }),
+                None => None,
+            };
+
+            let settings = ProjectSettings {
+                name: record.name,
+                project_ids: record
+                    .project_workspace_ids
+                    .into_iter()
+                    .zip(record.project_ids)
+                    .map(|(workspace_id, project_id)| ProjectId {
+                        workspace_id: WorkspaceId(workspace_id),
+                        project_id,
+                    })
+                    .collect(),
+                clients: record.clients,
+                tags: record.tags,
+                no_project: record.no_project,
+                description_substrings: record.description_substrings,
+                description_regexes: record.description_regexes,
+                billable_only: record.billable_only,
+                required_tags: record.required_tags,
+                excluded_tags: record.excluded_tags,
+                starting_date: record.starting_date,
+                daily_goal: Duration::seconds(record.daily_goal),
+                weekdays: WhichWeekdays {
+                    monday: record.monday,
+                    tuesday: record.tuesday,
+                    wednesday: record.wednesday,
+                    thursday: record.thursday,
+                    friday: record.friday,
+                    saturday: record.saturday,
+                    sunday: record.sunday,
+                },
+                priority: record.priority,
+                weight: record.weight,
+                deadline: record
+                    .deadline
+                    .zip(record.target_total)
+                    .map(|(date, target_total)| Deadline {
+                        date,
+                        total: Duration::seconds(target_total),
+                    }),
+                limit,
+            };
+
+            Ok((ProjectKey(record.project_key), settings))
+        })
+        .collect()
+}
+
+pub async fn count_projects(user_key: UserKey, pool: &PgPool) -> AppResult<i64> {
+    Ok(sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM projects WHERE user_key = $1"#,
+        user_key.0,
+    )
+    .fetch_one(pool)
+    .await?
+    .count)
+}
+
+pub async fn insert_project(
+    user_key: UserKey,
+    settings: &ProjectSettings,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> AppResult<ProjectKey> {
+    let project_key = sqlx::query!(
+        "INSERT INTO projects(user_key, name, no_project, billable_only, starting_date, daily_goal,
+            monday, tuesday, wednesday, thursday, friday, saturday, sunday, priority, weight,
+            deadline, target_total, limit_period, limit_time)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,
+            $19)
+        RETURNING project_key",
+        user_key.0,
+        settings.name,
+        settings.no_project,
+        settings.billable_only,
+        settings.starting_date,
+        settings.stored_daily_goal().num_seconds(),
+        settings.weekdays.monday,
+        settings.weekdays.tuesday,
+        settings.weekdays.wednesday,
+        settings.weekdays.thursday,
+        settings.weekdays.friday,
+        settings.weekdays.saturday,
+        settings.weekdays.sunday,
+        settings.priority,
+        settings.weight,
+        settings.deadline.map(|deadline| deadline.date),
+        settings
+            .deadline
+            .map(|deadline| deadline.total.num_seconds()),
+        settings.limit.map(|limit| limit.period.to_string()),
+        settings.limit.map(|limit| limit.time.num_seconds()),
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .project_key;
+
+    let project_key = ProjectKey(project_key);
+
+    insert_filter(project_key, settings, transaction).await?;
+
+    Ok(project_key)
+}
+
+/// Replace the settings of one of the user's projects, keeping its days off
+///
+/// Returns `false` if the user doesn't have a project with this key.
+pub async fn update_project(
+    user_key: UserKey,
+    project_key: ProjectKey,
+    settings: &ProjectSettings,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> AppResult<bool> {
+    let updated = sqlx::query!(
+        "UPDATE projects
+        SET name = $3, no_project = $4, billable_only = $5, starting_date = $6, daily_goal = $7,
+            monday = $8, tuesday = $9, wednesday = $10, thursday = $11, friday = $12,
+            saturday = $13, sunday = $14, priority = $15, weight = $16, deadline = $17,
+            target_total = $18, limit_period = $19, limit_time = $20
+        WHERE project_key = $1
+        AND user_key = $2",
+        project_key.0,
+        user_key.0,
+        settings.name,
+        settings.no_project,
+        settings.billable_only,
+        settings.starting_date,
+        settings.stored_daily_goal().num_seconds(),
+        settings.weekdays.monday,
+        settings.weekdays.tuesday,
+        settings.weekdays.wednesday,
+        settings.weekdays.thursday,
+        settings.weekdays.friday,
+        settings.weekdays.saturday,
+        settings.weekdays.sunday,
+        settings.priority,
+        settings.weight,
+        settings.deadline.map(|deadline| deadline.date),
+        settings
+            .deadline
+            .map(|deadline| deadline.total.num_seconds()),
+        settings.limit.map(|limit| limit.period.to_string()),
+        settings.limit.map(|limit| limit.time.num_seconds()),
+    )
+    .execute(&mut **transaction)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if !updated {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "DELETE FROM projects_to_toggl_projects WHERE project_key = $1",
+        project_key.0,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM projects_to_toggl_clients WHERE project_key = $1",
+        project_key.0,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM projects_to_toggl_tags WHERE project_key = $1",
+        project_key.0,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM projects_to_description_patterns WHERE project_key = $1",
+        project_key.0,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM projects_to_tag_restrictions WHERE project_key = $1",
+        project_key.0,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    insert_filter(project_key, settings, transaction).await?;
+
+    Ok(true)
+}
+
+async fn insert_filter(
+    project_key: ProjectKey,
+    settings: &ProjectSettings,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> AppResult<()> {
+    let (workspace_ids, project_ids): (Vec<i64>, Vec<i64>) = settings
+        .project_ids
+        .iter()
+        .map(|project_id| (project_id.workspace_id.0, project_id.project_id))
+        .unzip();
+
+    sqlx::query!(
+        "INSERT INTO projects_to_toggl_projects(project_key, workspace_id, project_id)
+        SELECT $1, workspace_id, project_id
+        FROM UNNEST($2::BIGINT[], $3::BIGINT[]) AS ids(workspace_id, project_id)
+        ON CONFLICT DO NOTHING",
+        project_key.0,
+        &workspace_ids,
+        &project_ids,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO projects_to_toggl_clients(project_key, client)
+        SELECT $1, client FROM UNNEST($2::TEXT[]) AS client
+        ON CONFLICT DO NOTHING",
+        project_key.0,
+        &settings.clients,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO projects_to_toggl_tags(project_key, tag)
+        SELECT $1, tag FROM UNNEST($2::TEXT[]) AS tag
+        ON CONFLICT DO NOTHING",
+        project_key.0,
+        &settings.tags,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO projects_to_description_patterns(project_key, pattern, regex)
+        SELECT $1::BIGINT, pattern, FALSE FROM UNNEST($2::TEXT[]) AS pattern
+        UNION
+        SELECT $1, pattern, TRUE FROM UNNEST($3::TEXT[]) AS pattern
+        ON CONFLICT DO NOTHING",
+        project_key.0,
+        &settings.description_substrings,
+        &settings.description_regexes,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    // A tag that is both required and excluded is just excluded
+    sqlx::query!(
+        "INSERT INTO projects_to_tag_restrictions(project_key, tag, excluded)
+        SELECT $1::BIGINT, tag, TRUE FROM UNNEST($2::TEXT[]) AS tag
+        UNION
+        SELECT $1::BIGINT, tag, FALSE FROM UNNEST($3::TEXT[]) AS tag
+        WHERE tag <> ALL($2::TEXT[])
+        ON CONFLICT DO NOTHING",
+        project_key.0,
+        &settings.excluded_tags,
+        &settings.required_tags,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
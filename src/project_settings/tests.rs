@@ -0,0 +1,296 @@
+#![cfg(test)]
+
+use super::*;
+use crate::toggl::LimitPeriod;
+
+fn example_project_id() -> ProjectId {
+    ProjectId {
+        workspace_id: WorkspaceId(1234567),
+        project_id: 7654321,
+    }
+}
+
+fn example_settings() -> ProjectSettings {
+    ProjectSettings {
+        name: Some("Thesis".to_owned()),
+        project_ids: Vec::from([example_project_id()]),
+        clients: Vec::from(["University".to_owned()]),
+        tags: Vec::new(),
+        no_project: false,
+        description_substrings: Vec::from(["chapter".to_owned()]),
+        description_regexes: Vec::from(["^draft".to_owned()]),
+        billable_only: false,
+        required_tags: Vec::new(),
+        excluded_tags: Vec::from(["break".to_owned()]),
+        starting_date: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(),
+        daily_goal: Duration::hours(2),
+        weekdays: WhichWeekdays {
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: false,
+            sunday: false,
+        },
+        priority: 0,
+        weight: 1,
+        deadline: None,
+        limit: None,
+    }
+}
+
+#[test]
+fn test_validate_project_settings() {
+    let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+    let available_project_ids = [example_project_id()];
+
+    assert!(example_settings()
+        .validate(&available_project_ids, today)
+        .is_empty());
+
+    let unavailable_project = example_settings();
+    assert!(unavailable_project.validate(&[], today).has("project_ids"));
+
+    let no_weekdays = ProjectSettings {
+        weekdays: WhichWeekdays {
+            monday: false,
+            tuesday: false,
+            wednesday: false,
+            thursday: false,
+            friday: false,
+            saturday: false,
+            sunday: false,
+        },
+        ..example_settings()
+    };
+    assert!(no_weekdays
+        .validate(&available_project_ids, today)
+        .has("weekdays"));
+
+    let too_long_goal = ProjectSettings {
+        daily_goal: Duration::hours(25),
+        ..example_settings()
+    };
+    assert!(too_long_goal
+        .validate(&available_project_ids, today)
+        .has("daily_goal"));
+
+    let deadline = ProjectSettings {
+        daily_goal: Duration::zero(),
+        deadline: Some(Deadline {
+            date: NaiveDate::from_ymd_opt(2026, 12, 15).unwrap(),
+            total: Duration::hours(80),
+        }),
+        ..example_settings()
+    };
+    assert!(deadline.validate(&available_project_ids, today).is_empty());
+
+    let deadline_before_start = ProjectSettings {
+        deadline: Some(Deadline {
+            date: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            total: Duration::hours(80),
+        }),
+        ..deadline.clone()
+    };
+    assert!(deadline_before_start
+        .validate(&available_project_ids, today)
+        .has("deadline"));
+
+    let distant_deadline = ProjectSettings {
+        deadline: Some(Deadline {
+            date: NaiveDate::from_ymd_opt(9999, 12, 31).unwrap(),
+            total: Duration::hours(80),
+        }),
+        ..deadline.clone()
+    };
+    assert!(distant_deadline
+        .validate(&available_project_ids, today)
+        .has("deadline"));
+
+    let too_big_target = ProjectSettings {
+        deadline: Some(Deadline {
+            date: NaiveDate::from_ymd_opt(2026, 12, 15).unwrap(),
+            total: Duration::hours(MAX_TARGET_TOTAL_HOURS + 1),
+        }),
+        ..deadline.clone()
+    };
+    assert!(too_big_target
+        .validate(&available_project_ids, today)
+        .has("target_total"));
+
+    // The 18th is a Sunday, which isn't one of the project's days
+    let no_days_before_deadline = ProjectSettings {
+        deadline: Some(Deadline {
+            date: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(),
+            total: Duration::hours(80),
+        }),
+        ..deadline
+    };
+    assert!(no_days_before_deadline
+        .validate(&available_project_ids, today)
+        .has("deadline"));
+
+    let limit = ProjectSettings {
+        daily_goal: Duration::zero(),
+        limit: Some(Limit {
+            time: Duration::hours(1),
+            period: LimitPeriod::Day,
+        }),
+        ..example_settings()
+    };
+    assert!(limit.validate(&available_project_ids, today).is_empty());
+
+    let limit_and_deadline = ProjectSettings {
+        deadline: Some(Deadline {
+            date: NaiveDate::from_ymd_opt(2026, 12, 15).unwrap(),
+            total: Duration::hours(80),
+        }),
+        ..limit.clone()
+    };
+    assert!(limit_and_deadline
+        .validate(&available_project_ids, today)
+        .has("limit"));
+
+    let weekly_limit = ProjectSettings {
+        limit: Some(Limit {
+            time: Duration::hours(25),
+            period: LimitPeriod::Week,
+        }),
+        ..limit.clone()
+    };
+    assert!(weekly_limit
+        .validate(&available_project_ids, today)
+        .is_empty());
+
+    let too_long_limit = ProjectSettings {
+        limit: Some(Limit {
+            time: Duration::hours(25),
+            period: LimitPeriod::Day,
+        }),
+        ..limit.clone()
+    };
+    assert!(too_long_limit
+        .validate(&available_project_ids, today)
+        .has("limit"));
+
+    let empty_limit = ProjectSettings {
+        limit: Some(Limit {
+            time: Duration::zero(),
+            period: LimitPeriod::Week,
+        }),
+        ..limit
+    };
+    assert!(empty_limit
+        .validate(&available_project_ids, today)
+        .has("limit"));
+
+    let weightless = ProjectSettings {
+        weight: 0,
+        ..example_settings()
+    };
+    assert!(weightless
+        .validate(&available_project_ids, today)
+        .has("weight"));
+}
+
+#[test]
+fn test_allowed_project_ids() {
+    let toggl_project = |project_id, active| TogglProject {
+        active,
+        name: "Thesis".to_owned(),
+        id: project_id,
+        workspace_id: WorkspaceId(1234567),
+        client_id: None,
+        color: None,
+    };
+    let toggl_projects = [toggl_project(1, true), toggl_project(2, false)];
+
+    let project_id = |project_id| ProjectId {
+        workspace_id: WorkspaceId(1234567),
+        project_id,
+    };
+
+    assert_eq!(
+        allowed_project_ids(&toggl_projects, &[]),
+        Vec::from([project_id(1)]),
+    );
+
+    // An archived Toggl project can only be kept, not chosen again
+    assert_eq!(
+        allowed_project_ids(&toggl_projects, &[project_id(2), project_id(3)]),
+        Vec::from([project_id(1), project_id(2)]),
+    );
+}
+
+#[sqlx::test]
+async fn test_store_project_settings(pool: PgPool) -> AppResult<()> {
+    let user_key = UserKey(
+        sqlx::query!(
+            "INSERT INTO users(username, daily_max, timezone)
+            VALUES ('someone', 0, 'UTC')
+            RETURNING user_key"
+        )
+        .fetch_one(&pool)
+        .await?
+        .user_key,
+    );
+
+    let mut transaction = pool.begin().await?;
+    let project_key = insert_project(user_key, &example_settings(), &mut transaction).await?;
+    transaction.commit().await?;
+
+    assert_eq!(
+        get_project_settings(user_key, project_key, &pool).await?,
+        Some(example_settings()),
+    );
+
+    let changed = ProjectSettings {
+        name: None,
+        project_ids: Vec::new(),
+        no_project: true,
+        required_tags: Vec::from(["deep work".to_owned()]),
+        daily_goal: Duration::zero(),
+        priority: 2,
+        weight: 3,
+        deadline: Some(Deadline {
+            date: NaiveDate::from_ymd_opt(2026, 12, 15).unwrap(),
+            total: Duration::hours(80),
+        }),
+        ..example_settings()
+    };
+
+    let mut transaction = pool.begin().await?;
+    assert!(update_project(user_key, project_key, &changed, &mut transaction).await?);
+    assert!(!update_project(user_key, ProjectKey(-1), &changed, &mut transaction).await?);
+    transaction.commit().await?;
+
+    assert_eq!(
+        get_all_project_settings(user_key, &pool).await?,
+        Vec::from([(project_key, changed)]),
+    );
+
+    let limit = ProjectSettings {
+        daily_goal: Duration::zero(),
+        limit: Some(Limit {
+            time: Duration::hours(5),
+            period: LimitPeriod::Week,
+        }),
+        ..example_settings()
+    };
+
+    let mut transaction = pool.begin().await?;
+    assert!(update_project(user_key, project_key, &limit, &mut transaction).await?);
+    transaction.commit().await?;
+
+    assert_eq!(
+        get_project_settings(user_key, project_key, &pool).await?,
+        Some(limit),
+    );
+    assert_eq!(count_projects(user_key, &pool).await?, 1);
+    assert!(get_project_settings_page(user_key, 10, 1, &pool)
+        .await?
+        .is_empty());
+
+    Ok(())
+}
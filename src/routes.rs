@@ -1,35 +1,105 @@
 use axum::{
-    routing::{get, post},
+    extract::Extension,
+    middleware,
+    routing::{delete, get, post, put},
     Router,
 };
 
+use crate::session::renew_session_cookie;
+
 mod account;
+mod account_token;
+mod account_webhooks;
 mod api;
+mod api_token_delete;
+mod api_token_new;
 mod dayoff_delete;
 mod dayoff_new;
 mod daysoff;
+mod hooks_toggl;
 mod index;
 mod login;
+mod login_legacy;
+mod logout;
+mod notification_delete;
+mod notification_new;
+mod project_archive;
 mod project_delete;
 mod project_new;
+mod project_relink;
 mod projects;
+mod session_delete;
+mod sessions;
+mod sessions_delete;
 mod signup;
+mod signup_token;
+mod webhook_delete;
+mod webhook_new;
 
 pub fn router() -> Router {
     Router::new()
         .route("/", get(index::get))
         .route("/login/", get(login::get))
         .route("/login/", post(login::post))
+        .route("/login/legacy/", get(login_legacy::get))
+        .route("/login/legacy/", post(login_legacy::post))
+        .route("/logout/", post(logout::post))
+        .route("/signup/", get(signup::get))
         .route("/signup/", post(signup::post))
+        .route("/signup/token/", post(signup_token::post))
         .route("/projects/", get(projects::get))
         .route("/project/new/", get(project_new::get))
         .route("/project/new/", post(project_new::post))
         .route("/project/delete/", post(project_delete::post))
+        .route("/project/archive/", post(project_archive::post))
+        .route("/project/relink/", post(project_relink::post))
         .route("/account/", get(account::get))
         .route("/account/", post(account::post))
+        .route("/account/token/", post(account_token::post))
+        .route("/account/webhooks/", get(account_webhooks::get))
+        .route("/sessions/", get(sessions::get))
+        .route("/sessions/delete/", post(sessions_delete::post))
+        .route("/session/delete/", post(session_delete::post))
+        .route("/api-token/new/", post(api_token_new::post))
+        .route("/api-token/delete/", post(api_token_delete::post))
+        .route("/notification/new/", post(notification_new::post))
+        .route("/notification/delete/", post(notification_delete::post))
+        .route("/webhook/new/", post(webhook_new::post))
+        .route("/webhook/delete/", post(webhook_delete::post))
         .route("/daysoff/", get(daysoff::get))
         .route("/dayoff/new/", get(dayoff_new::get))
         .route("/dayoff/new/", post(dayoff_new::post))
         .route("/dayoff/delete/", post(dayoff_delete::post))
+        .route("/hooks/toggl/", post(hooks_toggl::post))
         .route("/api/v1/status/", get(api::v1::status::get))
+        .route("/api/v1/status/stream", get(api::v1::status_stream::get))
+        .route("/api/v1/daysoff/", post(api::v1::daysoff::post))
+        .route("/api/v1/plan/", get(api::v1::plan::get))
+        .route("/api/v2/openapi.json", get(api::v2::openapi::get))
+        .route("/api/v2/projects/", get(api::v2::projects::get))
+        .route("/api/v2/projects/", post(api::v2::projects::post))
+        .route(
+            "/api/v2/projects/{project_key}/",
+            get(api::v2::project::get),
+        )
+        .route(
+            "/api/v2/projects/{project_key}/",
+            put(api::v2::project::put),
+        )
+        .route(
+            "/api/v2/projects/{project_key}/",
+            delete(api::v2::project::delete),
+        )
+        .route("/api/v2/daysoff/", get(api::v2::daysoff::get))
+        .route("/api/v2/daysoff/", post(api::v2::daysoff::post))
+        .route("/api/v2/daysoff/{day_off_key}/", get(api::v2::dayoff::get))
+        .route("/api/v2/daysoff/{day_off_key}/", put(api::v2::dayoff::put))
+        .route(
+            "/api/v2/daysoff/{day_off_key}/",
+            delete(api::v2::dayoff::delete),
+        )
+        .route("/api/v2/account/", get(api::v2::account::get))
+        .route("/api/v2/account/", put(api::v2::account::put))
+        .layer(middleware::from_fn(renew_session_cookie))
+        .layer(Extension(api::v1::status_stream::StatusPolls::default()))
 }
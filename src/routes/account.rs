@@ -3,21 +3,41 @@ use std::str::FromStr;
 use askama::Template;
 use axum::{
     debug_handler,
-    extract::{Extension, Form},
-    http::StatusCode,
+    extract::Extension,
     response::{Html, IntoResponse, Redirect},
 };
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
 use chrono_tz::{Tz, TZ_VARIANTS};
+use reqwest::Client;
 use serde::Deserialize;
 use sqlx::PgPool;
 
 use crate::{
-    errors::{InternalError, InternalResult},
+    crypto::ServerKey,
+    csrf::{CsrfForm, CsrfToken},
+    errors::{AppError, AppResult},
     human_duration::hours_minutes_seconds,
-    session::UserKey,
+    notifications::{get_notifications, Notification},
+    session::{ApiScopes, UserKey},
+    toggl::{
+        get_all_toggl_projects_including_archived, get_user_toggl_api_token,
+        get_user_workspace_ids, get_workspaces, ProjectId, TogglProject, WorkspaceId,
+    },
+    validation::FieldErrors,
 };
 
+use super::notification_new::NotificationNewForm;
+
+mod tests;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+struct Workspace {
+    pub id: WorkspaceId,
+    pub name: String,
+    pub linked: bool,
+}
+
 #[derive(Template)]
 #[template(path = "account.html")]
 struct AccountTemplate<'a> {
@@ -26,37 +46,259 @@ struct AccountTemplate<'a> {
     daily_max_seconds: i64,
     user_timezone: &'a Tz,
     timezones: &'a [Tz],
+    workspaces: &'a [Workspace],
+    unavailable_links: &'a [UnavailableLink],
+    invalid_api_token: bool,
+    api_tokens: &'a [ApiToken],
+    new_api_token: Option<&'a str>,
+    notifications: &'a [Notification],
+    notification_form: &'a NotificationNewForm,
+    notification_errors: &'a FieldErrors,
+    csrf_token: &'a str,
+}
+
+/// What the user just did on the account page, which changes how it's shown
+#[derive(Default)]
+pub struct AccountForms<'a> {
+    pub invalid_api_token: bool,
+    /// A new API token, which is only ever shown once
+    pub new_api_token: Option<&'a str>,
+    pub notification_form: NotificationNewForm,
+    pub notification_errors: FieldErrors,
+}
+
+/// A personal API token, without the token itself
+struct ApiToken {
+    key: i64,
+    name: String,
+    scopes: ApiScopes,
+    created_at: String,
+    last_used_at: Option<String>,
+}
+
+/// A Toggl project counted by one of the user's projects that none of their linked workspaces have
+#[derive(Debug, PartialEq)]
+pub struct UnavailableLink {
+    pub project_name: String,
+    pub project_id: ProjectId,
 }
 
 #[debug_handler]
 pub async fn get(
     user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
     Extension(pool): Extension<PgPool>,
-) -> InternalResult<impl IntoResponse> {
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+) -> AppResult<impl IntoResponse> {
+    Ok(Html(
+        render(
+            user_key,
+            &pool,
+            client,
+            &server_key,
+            &csrf_token,
+            &AccountForms::default(),
+        )
+        .await?,
+    ))
+}
+
+pub async fn render(
+    user_key: UserKey,
+    pool: &PgPool,
+    client: Client,
+    server_key: &ServerKey,
+    csrf_token: &str,
+    forms: &AccountForms<'_>,
+) -> AppResult<String> {
+    let toggl_api_key = get_user_toggl_api_token(user_key, pool, server_key).await?;
+
     let record = sqlx::query!(
         "SELECT daily_max, timezone FROM users WHERE user_key = $1",
         user_key.0,
     )
-    .fetch_one(&pool)
+    .fetch_one(pool)
     .await?;
 
     let timezone: Tz = record
         .timezone
         .parse()
-        .map_err(InternalError::UnrecognizedTimezone)?;
+        .map_err(AppError::UnrecognizedTimezone)?;
 
     let (daily_max_hours, daily_max_minutes, daily_max_seconds) =
         hours_minutes_seconds(Duration::seconds(record.daily_max));
 
+    let linked_workspace_ids = get_user_workspace_ids(user_key, pool).await?;
+
+    let unavailable_links = get_unavailable_links(
+        user_key,
+        &toggl_api_key,
+        &linked_workspace_ids,
+        pool,
+        &client,
+    )
+    .await?;
+
+    let workspaces: Vec<Workspace> = get_workspaces(&toggl_api_key, client)
+        .await?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|workspace| Workspace {
+            linked: linked_workspace_ids.contains(&workspace.id),
+            id: workspace.id,
+            name: workspace.name,
+        })
+        .collect();
+
+    let api_tokens: Vec<ApiToken> = sqlx::query!(
+        "SELECT api_token_key, name, read_status, write_days_off, admin, created_at, last_used_at
+        FROM api_tokens
+        WHERE user_key = $1
+        ORDER BY created_at",
+        user_key.0,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|record| ApiToken {
+        key: record.api_token_key,
+        name: record.name,
+        scopes: ApiScopes {
+            read_status: record.read_status,
+            write_days_off: record.write_days_off,
+            admin: record.admin,
+        },
+        created_at: format_timestamp(record.created_at, &timezone),
+        last_used_at: record
+            .last_used_at
+            .map(|last_used_at| format_timestamp(last_used_at, &timezone)),
+    })
+    .collect();
+
+    let notifications = get_notifications(user_key, pool).await?;
+
     let template = AccountTemplate {
         daily_max_hours,
         daily_max_minutes,
         daily_max_seconds,
         user_timezone: &timezone,
         timezones: &TZ_VARIANTS,
+        workspaces: &workspaces,
+        unavailable_links: &unavailable_links,
+        invalid_api_token: forms.invalid_api_token,
+        api_tokens: &api_tokens,
+        new_api_token: forms.new_api_token,
+        notifications: &notifications,
+        notification_form: &forms.notification_form,
+        notification_errors: &forms.notification_errors,
+        csrf_token,
     };
 
-    Ok(Html(template.render()?))
+    Ok(template.render()?)
+}
+
+pub fn format_timestamp(timestamp: DateTime<Utc>, timezone: &Tz) -> String {
+    timestamp
+        .with_timezone(timezone)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+/// Check a daily limit, in seconds, the same way wherever it's set
+pub fn validate_daily_max(daily_max: i64) -> FieldErrors {
+    let mut errors = FieldErrors::new();
+
+    errors.require(
+        (1..=SECONDS_PER_DAY).contains(&daily_max),
+        "daily_max",
+        "The daily limit has to be more than zero and at most a day",
+    );
+
+    errors
+}
+
+/// Add up a daily limit's hours, minutes and seconds from a form, and check it the same way
+pub fn form_daily_max(hours: i64, minutes: i64, seconds: i64) -> Result<Duration, FieldErrors> {
+    let daily_max = Duration::try_hours(hours)
+        .zip(Duration::try_minutes(minutes))
+        .zip(Duration::try_seconds(seconds))
+        .and_then(|((hours, minutes), seconds)| hours.checked_add(&minutes)?.checked_add(&seconds));
+
+    let Some(daily_max) = daily_max else {
+        let mut errors = FieldErrors::new();
+        errors.add("daily_max", "That's too long");
+
+        return Err(errors);
+    };
+
+    let errors = validate_daily_max(daily_max.num_seconds());
+
+    if errors.is_empty() {
+        Ok(daily_max)
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn invalid_timezone(timezone: &str) -> AppError {
+    AppError::Validation(format!("{timezone} isn't a recognized timezone"))
+}
+
+/// Check the Toggl projects counted by the user's projects against their linked workspaces
+pub async fn get_unavailable_links(
+    user_key: UserKey,
+    toggl_api_token: &str,
+    workspace_ids: &[WorkspaceId],
+    pool: &PgPool,
+    client: &Client,
+) -> AppResult<Vec<UnavailableLink>> {
+    // Projects archived in Toggl are still in the workspace, and their entries still count
+    let toggl_projects =
+        get_all_toggl_projects_including_archived(toggl_api_token, workspace_ids, client).await?;
+
+    let records = sqlx::query!(
+        "SELECT projects.project_key, projects.name,
+            projects_to_toggl_projects.workspace_id, projects_to_toggl_projects.project_id
+        FROM projects
+        INNER JOIN projects_to_toggl_projects
+        ON projects.project_key = projects_to_toggl_projects.project_key
+        WHERE projects.user_key = $1
+        ORDER BY projects.project_key",
+        user_key.0,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let links = records
+        .into_iter()
+        .map(|record| UnavailableLink {
+            project_name: record
+                .name
+                .unwrap_or_else(|| format!("Project {}", record.project_key)),
+            project_id: ProjectId {
+                workspace_id: WorkspaceId(record.workspace_id),
+                project_id: record.project_id,
+            },
+        })
+        .collect();
+
+    Ok(filter_unavailable_links(links, &toggl_projects))
+}
+
+/// Keep the links to Toggl projects that aren't among the ones in the linked workspaces
+fn filter_unavailable_links(
+    links: Vec<UnavailableLink>,
+    toggl_projects: &[TogglProject],
+) -> Vec<UnavailableLink> {
+    links
+        .into_iter()
+        .filter(|link| {
+            !toggl_projects
+                .iter()
+                .any(|toggl_project| toggl_project.project_id() == link.project_id)
+        })
+        .collect()
 }
 
 #[derive(Debug, Deserialize)]
@@ -65,35 +307,110 @@ pub struct AccountForm {
     daily_max_minutes: i64,
     daily_max_seconds: i64,
     timezone: String,
+    #[serde(default)]
+    workspace_ids: Vec<WorkspaceId>,
+}
+
+impl AccountForm {
+    fn daily_max(&self) -> Result<Duration, FieldErrors> {
+        form_daily_max(
+            self.daily_max_hours,
+            self.daily_max_minutes,
+            self.daily_max_seconds,
+        )
+    }
 }
 
 #[debug_handler]
 pub async fn post(
     user_key: UserKey,
     Extension(pool): Extension<PgPool>,
-    Form(form): Form<AccountForm>,
-) -> InternalResult<impl IntoResponse> {
-    let daily_max = Duration::hours(form.daily_max_hours)
-        + Duration::minutes(form.daily_max_minutes)
-        + Duration::seconds(form.daily_max_seconds);
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    CsrfForm(form): CsrfForm<AccountForm>,
+) -> AppResult<impl IntoResponse> {
+    let daily_max = form.daily_max();
+
+    let toggl_api_key = get_user_toggl_api_token(user_key, &pool, &server_key).await?;
+
+    // Only link workspaces that the user's API token can actually access
+    let available_workspaces = get_workspaces(&toggl_api_key, client.clone())
+        .await?
+        .unwrap_or_default();
+    let valid_workspaces = !form.workspace_ids.is_empty()
+        && form.workspace_ids.iter().all(|workspace_id| {
+            available_workspaces
+                .iter()
+                .any(|workspace| workspace.id == *workspace_id)
+        });
 
-    let positive_daily_max = daily_max >= Duration::zero();
     let timezone: Result<Tz, <Tz as FromStr>::Err> = form.timezone.parse();
 
     Ok(
-        if let (true, Ok(timezone)) = (positive_daily_max, timezone) {
+        if let (Ok(daily_max), true, Ok(timezone)) = (&daily_max, valid_workspaces, timezone) {
+            let mut transaction = pool.begin().await?;
+
             sqlx::query!(
                 "UPDATE users SET daily_max = $1, timezone = $2 WHERE user_key = $3",
                 daily_max.num_seconds(),
                 timezone.to_string(),
                 user_key.0,
             )
-            .execute(&pool)
+            .execute(&mut *transaction)
+            .await?;
+
+            let workspace_ids: Vec<i64> = form
+                .workspace_ids
+                .iter()
+                .map(|workspace_id| workspace_id.0)
+                .collect();
+
+            sqlx::query!(
+                "DELETE FROM users_to_workspaces
+                WHERE user_key = $1
+                AND workspace_id <> ALL($2::BIGINT[])",
+                user_key.0,
+                &workspace_ids,
+            )
+            .execute(&mut *transaction)
+            .await?;
+
+            sqlx::query!(
+                "INSERT INTO users_to_workspaces(user_key, workspace_id)
+                SELECT $1, workspace_id FROM UNNEST($2::BIGINT[]) AS workspace_id
+                ON CONFLICT DO NOTHING",
+                user_key.0,
+                &workspace_ids,
+            )
+            .execute(&mut *transaction)
+            .await?;
+
+            transaction.commit().await?;
+
+            // Send the user back to their settings if their projects count Toggl projects that
+            // the newly linked workspaces don't have
+            let unavailable_links = get_unavailable_links(
+                user_key,
+                &toggl_api_key,
+                &form.workspace_ids,
+                &pool,
+                &client,
+            )
             .await?;
 
-            Redirect::to("/").into_response()
+            if unavailable_links.is_empty() {
+                Redirect::to("/").into_response()
+            } else {
+                Redirect::to("/account/").into_response()
+            }
+        } else if let Err(errors) = daily_max {
+            return Err(AppError::InvalidFields(errors));
+        } else if !valid_workspaces {
+            return Err(AppError::Validation(
+                "Choose at least one workspace that your Toggl API token can access".to_owned(),
+            ));
         } else {
-            StatusCode::BAD_REQUEST.into_response()
+            return Err(invalid_timezone(&form.timezone));
         },
     )
 }
@@ -0,0 +1,67 @@
+#![cfg(test)]
+
+use super::*;
+
+fn toggl_project(workspace_id: i64, id: i64, active: bool) -> TogglProject {
+    TogglProject {
+        active,
+        name: format!("Project {id}"),
+        id,
+        workspace_id: WorkspaceId(workspace_id),
+        client_id: None,
+        color: None,
+    }
+}
+
+fn link(workspace_id: i64, project_id: i64) -> UnavailableLink {
+    UnavailableLink {
+        project_name: "Thesis".to_owned(),
+        project_id: ProjectId {
+            workspace_id: WorkspaceId(workspace_id),
+            project_id,
+        },
+    }
+}
+
+#[test]
+fn test_filter_unavailable_links() {
+    let toggl_projects = [toggl_project(1, 10, true), toggl_project(1, 11, false)];
+
+    // An archived Toggl project is still available, unlike a deleted one or another workspace's
+    assert_eq!(
+        filter_unavailable_links(
+            Vec::from([link(1, 10), link(1, 11), link(1, 12), link(2, 10)]),
+            &toggl_projects,
+        ),
+        Vec::from([link(1, 12), link(2, 10)]),
+    );
+}
+
+#[test]
+fn test_validate_daily_max() {
+    assert!(validate_daily_max(8 * 60 * 60).is_empty());
+    assert!(validate_daily_max(SECONDS_PER_DAY).is_empty());
+
+    assert!(validate_daily_max(0).has("daily_max"));
+    assert!(validate_daily_max(-60).has("daily_max"));
+    assert!(validate_daily_max(SECONDS_PER_DAY + 1).has("daily_max"));
+}
+
+#[test]
+fn test_account_form_daily_max() {
+    let form = |daily_max_hours| AccountForm {
+        daily_max_hours,
+        daily_max_minutes: 30,
+        daily_max_seconds: 0,
+        timezone: "UTC".to_owned(),
+        workspace_ids: Vec::new(),
+    };
+
+    assert_eq!(form(8).daily_max().unwrap(), Duration::minutes(8 * 60 + 30));
+
+    // Too long to even be a duration, either way
+    assert!(form(i64::MAX).daily_max().unwrap_err().has("daily_max"));
+    assert!(form(i64::MIN).daily_max().unwrap_err().has("daily_max"));
+
+    assert!(form(24).daily_max().unwrap_err().has("daily_max"));
+}
@@ -0,0 +1,129 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect},
+};
+use reqwest::Client;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    crypto::ServerKey,
+    csrf::{CsrfForm, CsrfToken},
+    errors::AppResult,
+    session::UserKey,
+    toggl::{get_workspaces, WorkspaceId},
+};
+
+use super::account::{render, AccountForms};
+
+mod tests;
+
+#[derive(Deserialize)]
+pub struct AccountTokenForm {
+    toggl_api_key: String,
+}
+
+#[debug_handler]
+pub async fn post(
+    user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    CsrfForm(form): CsrfForm<AccountTokenForm>,
+) -> AppResult<impl IntoResponse> {
+    let toggl_api_key = form.toggl_api_key.trim();
+    let toggl_api_key_digest = server_key.digest(toggl_api_key);
+
+    let token_taken = sqlx::query!(
+        "SELECT user_key
+        FROM users
+        WHERE toggl_api_key_digest = $1
+        AND user_key <> $2",
+        toggl_api_key_digest,
+        user_key.0,
+    )
+    .fetch_optional(&pool)
+    .await?
+    .is_some();
+
+    let workspaces = if token_taken {
+        None
+    } else {
+        get_workspaces(toggl_api_key, client.clone()).await?
+    };
+
+    Ok(if let Some(workspaces) = workspaces {
+        let workspace_ids: Vec<WorkspaceId> =
+            workspaces.iter().map(|workspace| workspace.id).collect();
+
+        replace_toggl_api_token(user_key, toggl_api_key, &workspace_ids, &server_key, &pool)
+            .await?;
+
+        // Show the account settings again so the user can check their workspaces and projects
+        Redirect::to("/account/").into_response()
+    } else {
+        // The token doesn't work or belongs to someone else: Respond with an error message
+        (
+            StatusCode::BAD_REQUEST,
+            Html(
+                render(
+                    user_key,
+                    &pool,
+                    client,
+                    &server_key,
+                    &csrf_token,
+                    &AccountForms {
+                        invalid_api_token: true,
+                        ..AccountForms::default()
+                    },
+                )
+                .await?,
+            ),
+        )
+            .into_response()
+    })
+}
+
+/// Store the user's new Toggl API token, and unlink the workspaces that it can't access
+async fn replace_toggl_api_token(
+    user_key: UserKey,
+    toggl_api_key: &str,
+    workspace_ids: &[WorkspaceId],
+    server_key: &ServerKey,
+    pool: &PgPool,
+) -> AppResult<()> {
+    let workspace_ids: Vec<i64> = workspace_ids
+        .iter()
+        .map(|workspace_id| workspace_id.0)
+        .collect();
+
+    let mut transaction = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE users
+        SET toggl_api_key_digest = $1, toggl_api_key_ciphertext = $2, toggl_user_id = NULL
+        WHERE user_key = $3",
+        server_key.digest(toggl_api_key),
+        server_key.encrypt(toggl_api_key)?,
+        user_key.0,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM users_to_workspaces
+        WHERE user_key = $1
+        AND workspace_id <> ALL($2::BIGINT[])",
+        user_key.0,
+        &workspace_ids,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(())
+}
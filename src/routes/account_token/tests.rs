@@ -0,0 +1,75 @@
+#![cfg(test)]
+
+use super::*;
+
+fn example_server_key() -> ServerKey {
+    ServerKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap()
+}
+
+#[sqlx::test]
+async fn test_replace_toggl_api_token(pool: PgPool) -> sqlx::Result<()> {
+    let server_key = example_server_key();
+
+    let user_key = sqlx::query!(
+        "INSERT INTO users(username, toggl_api_key_digest, daily_max, timezone)
+        VALUES ('someone', $1, 3600, 'UTC')
+        RETURNING user_key",
+        server_key.digest("old token"),
+    )
+    .fetch_one(&pool)
+    .await?
+    .user_key;
+
+    for workspace_id in [1, 2] {
+        sqlx::query!(
+            "INSERT INTO users_to_workspaces(user_key, workspace_id) VALUES ($1, $2)",
+            user_key,
+            workspace_id,
+        )
+        .execute(&pool)
+        .await?;
+    }
+
+    replace_toggl_api_token(
+        UserKey(user_key),
+        "new token",
+        &[WorkspaceId(2), WorkspaceId(3)],
+        &server_key,
+        &pool,
+    )
+    .await
+    .unwrap();
+
+    let record = sqlx::query!(
+        "SELECT toggl_api_key_digest, toggl_api_key_ciphertext FROM users WHERE user_key = $1",
+        user_key,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    assert_eq!(
+        record.toggl_api_key_digest,
+        Some(server_key.digest("new token"))
+    );
+    assert_eq!(
+        server_key
+            .decrypt(&record.toggl_api_key_ciphertext.unwrap())
+            .unwrap(),
+        "new token",
+    );
+
+    // Only the workspaces that the new token can access stay linked, and none are added
+    let workspace_ids: Vec<i64> = sqlx::query!(
+        "SELECT workspace_id FROM users_to_workspaces WHERE user_key = $1",
+        user_key,
+    )
+    .fetch_all(&pool)
+    .await?
+    .into_iter()
+    .map(|record| record.workspace_id)
+    .collect();
+
+    assert_eq!(workspace_ids, [2]);
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use askama::Template;
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{Html, IntoResponse},
+};
+use chrono_tz::Tz;
+use sqlx::PgPool;
+
+use crate::{
+    crypto::ServerKey,
+    csrf::CsrfToken,
+    errors::{AppError, AppResult},
+    human_duration::HumanDuration,
+    session::UserKey,
+    validation::FieldErrors,
+    webhooks::{
+        get_deliveries, get_webhooks, take_new_webhook_secrets, Delivery, Webhook, MAX_ATTEMPTS,
+    },
+};
+
+use super::{account::format_timestamp, webhook_new::WebhookNewForm};
+
+/// How many of the most recent deliveries to show
+const DELIVERY_LOG_LENGTH: i64 = 50;
+
+#[derive(Template)]
+#[template(path = "account_webhooks.html")]
+struct AccountWebhooksTemplate<'a> {
+    webhooks: &'a [WebhookRow],
+    deliveries: &'a [DeliveryRow],
+    /// The URLs and secrets of webhooks that were just added
+    new_webhook_secrets: &'a [(String, String)],
+    form: &'a WebhookNewForm,
+    errors: &'a FieldErrors,
+    max_attempts: i32,
+    csrf_token: &'a str,
+}
+
+struct WebhookRow {
+    webhook: Webhook,
+    debt_threshold: HumanDuration,
+    created_at: String,
+}
+
+struct DeliveryRow {
+    delivery: Delivery,
+    next_attempt_at: Option<String>,
+    delivered_at: Option<String>,
+}
+
+#[debug_handler]
+pub async fn get(
+    user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
+    Extension(pool): Extension<PgPool>,
+    Extension(server_key): Extension<ServerKey>,
+) -> AppResult<impl IntoResponse> {
+    let new_webhook_secrets = take_new_webhook_secrets(user_key, &pool, &server_key).await?;
+
+    Ok(Html(
+        render(
+            user_key,
+            &pool,
+            &csrf_token,
+            &WebhookNewForm::default(),
+            &FieldErrors::new(),
+            &new_webhook_secrets,
+        )
+        .await?,
+    ))
+}
+
+pub async fn render(
+    user_key: UserKey,
+    pool: &PgPool,
+    csrf_token: &str,
+    form: &WebhookNewForm,
+    errors: &FieldErrors,
+    new_webhook_secrets: &[(String, String)],
+) -> AppResult<String> {
+    let record = sqlx::query!("SELECT timezone FROM users WHERE user_key = $1", user_key.0)
+        .fetch_one(pool)
+        .await?;
+
+    let timezone: Tz = record
+        .timezone
+        .parse()
+        .map_err(AppError::UnrecognizedTimezone)?;
+
+    let webhooks: Vec<WebhookRow> = get_webhooks(user_key, pool)
+        .await?
+        .into_iter()
+        .map(|webhook| WebhookRow {
+            debt_threshold: HumanDuration(webhook.subscriptions.debt_threshold),
+            created_at: format_timestamp(webhook.created_at, &timezone),
+            webhook,
+        })
+        .collect();
+
+    let deliveries: Vec<DeliveryRow> = get_deliveries(user_key, DELIVERY_LOG_LENGTH, pool)
+        .await?
+        .into_iter()
+        .map(|delivery| DeliveryRow {
+            next_attempt_at: delivery
+                .next_attempt_at
+                .map(|next_attempt_at| format_timestamp(next_attempt_at, &timezone)),
+            delivered_at: delivery
+                .delivered_at
+                .map(|delivered_at| format_timestamp(delivered_at, &timezone)),
+            delivery,
+        })
+        .collect();
+
+    let template = AccountWebhooksTemplate {
+        webhooks: &webhooks,
+        deliveries: &deliveries,
+        new_webhook_secrets,
+        form,
+        errors,
+        max_attempts: MAX_ATTEMPTS,
+        csrf_token,
+    };
+
+    Ok(template.render()?)
+}
@@ -1 +1,4 @@
+pub mod daysoff;
+pub mod plan;
 pub mod status;
+pub mod status_stream;
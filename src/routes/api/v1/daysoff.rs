@@ -0,0 +1,76 @@
+use axum::{debug_handler, extract::Extension, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::WithRejection;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    errors::{ApiError, ApiResult, AppError},
+    session::{ApiUserKey, WriteDaysOff},
+};
+
+#[derive(Deserialize)]
+pub struct RequestBody {
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub project_keys: Vec<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ResponseBody {
+    pub day_off_key: i64,
+}
+
+#[debug_handler]
+pub async fn post(
+    ApiUserKey(user_key, _): ApiUserKey<WriteDaysOff>,
+    Extension(pool): Extension<PgPool>,
+    WithRejection(Json(request_body), _): WithRejection<Json<RequestBody>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let mut project_keys = request_body.project_keys;
+    project_keys.sort_unstable();
+    project_keys.dedup();
+
+    // Make sure the projects belong to this user
+    let owned_projects = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!"
+        FROM projects
+        WHERE project_key = ANY($1::BIGINT[])
+        AND user_key = $2"#,
+        &project_keys,
+        user_key.0,
+    )
+    .fetch_one(&pool)
+    .await?
+    .count;
+
+    if owned_projects != project_keys.len() as i64 {
+        return Err(AppError::Validation("Unknown project key".to_owned()).into());
+    }
+
+    let mut transaction = pool.begin().await?;
+
+    let day_off_key = sqlx::query!(
+        "INSERT INTO days_off(user_key, day_off)
+        VALUES ($1, $2)
+        RETURNING day_off_key",
+        user_key.0,
+        request_body.date,
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .day_off_key;
+
+    sqlx::query!(
+        "INSERT INTO days_off_to_projects(project_key, day_off_key)
+        SELECT project_key, $1 FROM UNNEST($2::BIGINT[]) AS project_key",
+        day_off_key,
+        &project_keys,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(ResponseBody { day_off_key })).into_response())
+}
@@ -0,0 +1,98 @@
+use axum::{
+    debug_handler,
+    extract::{Extension, Query},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use chrono::{Duration, NaiveDate};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    crypto::ServerKey,
+    errors::{ApiError, ApiResult, AppError},
+    plan::{self, plan},
+    session::{ApiUserKey, ReadStatus},
+    toggl::{calculate_goals, get_user_today, ProjectKey},
+};
+
+#[derive(Deserialize)]
+pub struct PlanQuery {
+    /// How much time the user has left today, in seconds, which defaults to their daily max
+    pub available: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct Allocation {
+    pub project_key: ProjectKey,
+    pub name: String,
+    pub time: i64,
+}
+
+impl From<plan::Allocation> for Allocation {
+    fn from(other: plan::Allocation) -> Self {
+        Self {
+            project_key: other.project_key,
+            name: other.name,
+            time: other.time.num_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ResponseBody {
+    pub date: NaiveDate,
+    pub available: i64,
+    /// How much of the available time the plan uses
+    pub planned: i64,
+    /// What to work on, in the order to work on it
+    pub allocations: Vec<Allocation>,
+}
+
+#[debug_handler]
+pub async fn get(
+    ApiUserKey(user_key, _): ApiUserKey<ReadStatus>,
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    WithRejection(Query(query), _): WithRejection<Query<PlanQuery>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let record = sqlx::query!(
+        "SELECT daily_max FROM users WHERE user_key = $1",
+        user_key.0,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    let daily_max = Duration::seconds(record.daily_max);
+    let available = match query.available {
+        Some(available) => Duration::try_seconds(available)
+            .ok_or_else(|| AppError::Validation("available is too long".to_owned()))?,
+        None => daily_max,
+    };
+
+    if available < Duration::zero() {
+        return Err(AppError::Validation("available can't be negative".to_owned()).into());
+    }
+
+    let (date, allocations) =
+        match calculate_goals(user_key, pool.clone(), client, server_key).await? {
+            Some(status) => (status.today, plan(&status.goals, available, daily_max)),
+            None => (get_user_today(user_key, &pool).await?, Vec::new()),
+        };
+
+    let planned = allocations
+        .iter()
+        .fold(Duration::zero(), |total, allocation| {
+            total + allocation.time
+        });
+
+    Ok(Json(ResponseBody {
+        date,
+        available: available.num_seconds(),
+        planned: planned.num_seconds(),
+        allocations: allocations.into_iter().map(Allocation::from).collect(),
+    }))
+}
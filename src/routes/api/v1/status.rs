@@ -1,66 +1,163 @@
 use axum::{debug_handler, extract::Extension, response::IntoResponse, Json};
-use chrono::Duration;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use reqwest::Client;
 use serde::Serialize;
 use sqlx::PgPool;
 
 use crate::{
-    errors::InternalResult,
-    session::UserKey,
-    toggl::{self, calculate_goals},
+    crypto::ServerKey,
+    errors::{ApiResult, AppResult, TogglOutage},
+    last_status::calculate_goals_or_last,
+    session::{ApiUserKey, ReadStatus, UserKey},
+    toggl::{self, get_user_today, LimitPeriod, LimitUsage, ProjectId, ProjectKey},
 };
 
-#[derive(Serialize)]
+/// Where the user stands on one project, with every duration in seconds
+#[derive(Serialize, PartialEq)]
 pub struct Goal {
+    pub project_key: ProjectKey,
     pub name: String,
+    /// The Toggl projects counted by the project, as `<workspace_id>:<project_id>`
+    pub toggl_project_ids: Vec<ProjectId>,
+    /// The debt, which is negative if the user is ahead on the project
     pub time: i64,
+    pub debt: i64,
+    pub surplus: i64,
+    pub goal_today: i64,
+    pub logged_today: i64,
+    pub day_off_today: bool,
+    pub priority: i32,
+    pub weight: i32,
+    /// How much of the day's total debt is this project's to work off
+    pub share_today: i64,
+    /// For a project with a total target rather than a daily goal: When it's due
+    pub deadline: Option<NaiveDate>,
+    pub target_total: Option<i64>,
+    /// When the target will be reached at the pace kept so far, if it will be
+    pub projected_finish: Option<NaiveDate>,
+    /// For a project with a limit rather than a goal: How much of it is used up
+    pub limit: Option<Limit>,
+}
+
+/// How much of a limit is used up in the current day or week, with every duration in seconds
+#[derive(Serialize, PartialEq)]
+pub struct Limit {
+    pub period: LimitPeriod,
+    pub time: i64,
+    pub used: i64,
+    /// Negative once the limit is exceeded
+    pub remaining: i64,
+    pub exceeded: bool,
+}
+
+impl From<LimitUsage> for Limit {
+    fn from(other: LimitUsage) -> Self {
+        Self {
+            period: other.limit.period,
+            time: other.limit.time.num_seconds(),
+            used: other.used.num_seconds(),
+            remaining: other.remaining().num_seconds(),
+            exceeded: other.exceeded(),
+        }
+    }
 }
 
 impl From<toggl::Goal> for Goal {
     fn from(other: toggl::Goal) -> Self {
         Self {
+            project_key: other.project_key,
             name: other.name,
+            toggl_project_ids: other.project_ids,
             time: other.time.num_seconds(),
+            debt: other.time.num_seconds().max(0),
+            surplus: (-other.time.num_seconds()).max(0),
+            goal_today: other.goal_today.num_seconds(),
+            logged_today: other.logged_today.num_seconds(),
+            day_off_today: other.day_off_today,
+            priority: other.priority,
+            weight: other.weight,
+            share_today: other.share_today.num_seconds(),
+            deadline: other.deadline.map(|deadline| deadline.date),
+            target_total: other.deadline.map(|deadline| deadline.total.num_seconds()),
+            projected_finish: other.projected_finish,
+            limit: other.limit.map(Limit::from),
         }
     }
 }
 
-#[derive(Serialize)]
+/// Set when Toggl can't be reached, so the status is the last one calculated
+#[derive(Serialize, PartialEq)]
+pub struct Stale {
+    /// When the status was calculated
+    pub as_of: DateTime<Utc>,
+    pub toggl_outage: TogglOutage,
+}
+
+#[derive(Serialize, PartialEq)]
 pub struct ResponseBody {
     pub total_debt: i64,
     pub daily_max: i64,
+    /// The user's date, which is when today's goals and logged time apply
+    pub date: NaiveDate,
+    pub timezone: String,
     pub goals: Vec<Goal>,
+    pub stale: Option<Stale>,
 }
 
-#[debug_handler]
-pub async fn get(
+/// Where the user stands, as reported by the status route and its stream
+pub async fn status_body(
     user_key: UserKey,
-    Extension(pool): Extension<PgPool>,
-    Extension(client): Extension<Client>,
-) -> InternalResult<impl IntoResponse> {
+    pool: &PgPool,
+    client: Client,
+    server_key: ServerKey,
+) -> AppResult<ResponseBody> {
     let record = sqlx::query!(
-        "SELECT daily_max FROM users WHERE user_key = $1",
+        "SELECT daily_max, timezone FROM users WHERE user_key = $1",
         user_key.0,
     )
-    .fetch_one(&pool)
+    .fetch_one(pool)
     .await?;
 
     let daily_max = Duration::seconds(record.daily_max);
 
-    let response_body =
-        if let Some((goals, total_debt)) = calculate_goals(user_key, pool, client).await? {
-            ResponseBody {
-                total_debt: total_debt.num_seconds(),
-                daily_max: daily_max.num_seconds(),
-                goals: goals.into_iter().map(Goal::from).collect(),
-            }
-        } else {
-            ResponseBody {
-                total_debt: 0,
-                daily_max: 0,
-                goals: Vec::from([]),
-            }
-        };
+    let (status, staleness) = calculate_goals_or_last(user_key, pool, client, server_key).await?;
+
+    let stale = staleness.map(|staleness| Stale {
+        as_of: staleness.as_of,
+        toggl_outage: staleness.outage,
+    });
+
+    let response_body = if let Some(status) = status {
+        ResponseBody {
+            total_debt: status.total_debt.num_seconds(),
+            daily_max: daily_max.num_seconds(),
+            date: status.today,
+            timezone: record.timezone,
+            goals: status.goals.into_iter().map(Goal::from).collect(),
+            stale,
+        }
+    } else {
+        ResponseBody {
+            total_debt: 0,
+            daily_max: 0,
+            date: get_user_today(user_key, pool).await?,
+            timezone: record.timezone,
+            goals: Vec::from([]),
+            stale,
+        }
+    };
+
+    Ok(response_body)
+}
+
+#[debug_handler]
+pub async fn get(
+    ApiUserKey(user_key, _): ApiUserKey<ReadStatus>,
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+) -> ApiResult<impl IntoResponse> {
+    let response_body = status_body(user_key, &pool, client, server_key).await?;
 
     Ok(Json(response_body).into_response())
 }
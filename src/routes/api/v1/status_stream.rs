@@ -0,0 +1,186 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+};
+use futures::stream;
+use reqwest::Client;
+use sqlx::PgPool;
+use tokio::{sync::watch, time::MissedTickBehavior};
+use tracing::error;
+
+use crate::{
+    crypto::ServerKey,
+    errors::ProblemDocument,
+    routes::api::v1::status::{status_body, ResponseBody},
+    session::{ApiUserKey, ReadStatus, UserKey},
+};
+
+mod tests;
+
+/// How often the user's status is recalculated while a stream is open
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The last status sent down a stream, so that only changes are sent
+#[derive(Default)]
+pub struct Changes {
+    last: Option<ResponseBody>,
+}
+
+impl Changes {
+    /// Whether `body` is different from the last status sent, which it becomes if so
+    pub fn update(&mut self, body: ResponseBody) -> Option<&ResponseBody> {
+        if self.last.as_ref() == Some(&body) {
+            None
+        } else {
+            Some(self.last.insert(body))
+        }
+    }
+
+    /// Make sure the next status is sent even if it hasn't changed, such as after an error
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// One poll per user, shared by all of their open streams, so that a user with several tabs open
+/// doesn't have their status calculated once for each of them
+#[derive(Clone, Default)]
+pub struct StatusPolls(Arc<Mutex<HashMap<UserKey, watch::Receiver<Option<Event>>>>>);
+
+impl StatusPolls {
+    /// Follow the user's poll, starting it if it isn't running
+    fn subscribe(
+        &self,
+        user_key: UserKey,
+        pool: PgPool,
+        client: Client,
+        server_key: ServerKey,
+    ) -> watch::Receiver<Option<Event>> {
+        let mut polls = self.0.lock().unwrap();
+
+        if let Some(receiver) = polls.get(&user_key) {
+            return receiver.clone();
+        }
+
+        let (sender, receiver) = watch::channel(None);
+        polls.insert(user_key, receiver.clone());
+
+        tokio::spawn(poll(
+            user_key,
+            sender,
+            self.clone(),
+            pool,
+            client,
+            server_key,
+        ));
+
+        receiver
+    }
+
+    /// Whether anyone besides the poll itself is following it, which stops it if not
+    fn is_followed(&self, user_key: UserKey, sender: &watch::Sender<Option<Event>>) -> bool {
+        let mut polls = self.0.lock().unwrap();
+
+        // One receiver is kept here for new streams to follow
+        if sender.receiver_count() > 1 {
+            true
+        } else {
+            polls.remove(&user_key);
+            false
+        }
+    }
+}
+
+/// Recalculate the user's status every so often for as long as any of their streams are open, and
+/// send it on whenever it changes
+async fn poll(
+    user_key: UserKey,
+    sender: watch::Sender<Option<Event>>,
+    polls: StatusPolls,
+    pool: PgPool,
+    client: Client,
+    server_key: ServerKey,
+) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let mut changes = Changes::default();
+
+    loop {
+        interval.tick().await;
+
+        if !polls.is_followed(user_key, &sender) {
+            return;
+        }
+
+        let body = status_body(user_key, &pool, client.clone(), server_key.clone()).await;
+
+        let event = match body {
+            Ok(body) => match changes.update(body) {
+                Some(body) => Event::default().event("status").json_data(body),
+                None => continue,
+            },
+            Err(error) => {
+                error.log();
+                changes.reset();
+
+                Event::default()
+                    .event("problem")
+                    .json_data(ProblemDocument::from(&error))
+            }
+        };
+
+        match event {
+            Ok(event) => {
+                sender.send_replace(Some(event));
+            }
+            Err(error) => error!("Could not serialize status event: {error}"),
+        }
+    }
+}
+
+/// Server-sent events with the same body as `/api/v1/status/`, sent as soon as the stream opens
+/// and then whenever it changes
+///
+/// A `status` event carries the status, and a `problem` event carries a problem document when it
+/// couldn't be calculated.
+#[debug_handler]
+pub async fn get(
+    ApiUserKey(user_key, _): ApiUserKey<ReadStatus>,
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    Extension(polls): Extension<StatusPolls>,
+) -> impl IntoResponse {
+    let receiver = polls.subscribe(user_key, pool, client, server_key);
+
+    // The latest event is sent right away, unless the first poll hasn't finished yet
+    let events = stream::unfold((receiver, true), |(mut receiver, first)| async move {
+        if !first {
+            receiver.changed().await.ok()?;
+        }
+
+        loop {
+            let event = receiver.borrow_and_update().clone();
+
+            if let Some(event) = event {
+                return Some((Ok::<_, Infallible>(event), (receiver, false)));
+            }
+
+            receiver.changed().await.ok()?;
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,48 @@
+#![cfg(test)]
+
+use chrono::NaiveDate;
+
+use super::*;
+
+fn body(total_debt: i64) -> ResponseBody {
+    ResponseBody {
+        total_debt,
+        daily_max: 4 * 60 * 60,
+        date: NaiveDate::from_ymd_opt(2023, 3, 6).unwrap(),
+        timezone: "America/Toronto".to_owned(),
+        goals: Vec::from([]),
+        stale: None,
+    }
+}
+
+#[test]
+fn test_changes() {
+    let mut changes = Changes::default();
+
+    assert!(changes.update(body(60)).is_some());
+    assert!(changes.update(body(60)).is_none());
+
+    let changed = changes.update(body(30)).unwrap();
+    assert_eq!(changed.total_debt, 30);
+    assert!(changes.update(body(30)).is_none());
+
+    changes.reset();
+    assert!(changes.update(body(30)).is_some());
+}
+
+#[test]
+fn test_status_polls() {
+    let polls = StatusPolls::default();
+    let user_key = UserKey(1);
+
+    let (sender, receiver) = watch::channel(None);
+    polls.0.lock().unwrap().insert(user_key, receiver.clone());
+
+    // A stream is still open
+    assert!(polls.is_followed(user_key, &sender));
+
+    // Once every stream is closed the poll stops, and the next stream starts a new one
+    drop(receiver);
+    assert!(!polls.is_followed(user_key, &sender));
+    assert!(polls.0.lock().unwrap().is_empty());
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::errors::{AppError, AppResult};
+
+pub mod account;
+pub mod dayoff;
+pub mod daysoff;
+pub mod openapi;
+pub mod project;
+pub mod projects;
+
+mod tests;
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 100;
+
+/// Which page of a list to return
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Pagination {
+    /// Which page to return, starting from 1
+    #[serde(default = "first_page")]
+    #[param(minimum = 1, default = 1)]
+    pub page: i64,
+    /// How many items to put on each page
+    #[serde(default = "default_per_page")]
+    #[param(minimum = 1, maximum = 100, default = 50)]
+    pub per_page: i64,
+}
+
+fn first_page() -> i64 {
+    1
+}
+
+fn default_per_page() -> i64 {
+    DEFAULT_PER_PAGE
+}
+
+impl Pagination {
+    /// The `LIMIT` and `OFFSET` that select the page
+    pub fn limit_offset(&self) -> AppResult<(i64, i64)> {
+        if self.page < 1 {
+            return Err(AppError::Validation("page has to be at least 1".to_owned()));
+        }
+
+        if !(1..=MAX_PER_PAGE).contains(&self.per_page) {
+            return Err(AppError::Validation(format!(
+                "per_page has to be between 1 and {MAX_PER_PAGE}"
+            )));
+        }
+
+        let offset = (self.page - 1)
+            .checked_mul(self.per_page)
+            .ok_or_else(|| AppError::Validation("page is too far along".to_owned()))?;
+
+        Ok((self.per_page, offset))
+    }
+
+    pub fn page<T>(&self, items: Vec<T>, total: i64) -> Page<T> {
+        Page {
+            items,
+            page: self.page,
+            per_page: self.per_page,
+            total,
+        }
+    }
+}
+
+/// One page of a list, along with how many items there are in total
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
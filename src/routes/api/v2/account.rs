@@ -0,0 +1,101 @@
+use std::str::FromStr;
+
+use axum::{debug_handler, extract::Extension, response::IntoResponse, Json};
+use axum_extra::extract::WithRejection;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::{
+    errors::{ApiError, ApiResult, AppError, ProblemDocument},
+    routes::account::validate_daily_max,
+    session::{Admin, ApiUserKey, ReadStatus},
+};
+
+/// The user's account settings
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Account {
+    /// The most time to plan for in a day, in seconds
+    #[schema(minimum = 1, maximum = 86400)]
+    pub daily_max: i64,
+    /// An IANA timezone name, which decides when the user's days start and end
+    #[schema(example = "Europe/Amsterdam")]
+    pub timezone: String,
+}
+
+/// Get the user's account settings
+#[utoipa::path(
+    get,
+    path = "/api/v2/account/",
+    tag = "account",
+    responses(
+        (status = 200, body = Account),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn get(
+    ApiUserKey(user_key, _): ApiUserKey<ReadStatus>,
+    Extension(pool): Extension<PgPool>,
+) -> ApiResult<impl IntoResponse> {
+    let record = sqlx::query!(
+        "SELECT daily_max, timezone FROM users WHERE user_key = $1",
+        user_key.0,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(Account {
+        daily_max: record.daily_max,
+        timezone: record.timezone,
+    }))
+}
+
+/// Change the user's account settings
+///
+/// Linked Toggl workspaces and the Toggl API token can only be changed on the account page.
+#[utoipa::path(
+    put,
+    path = "/api/v2/account/",
+    tag = "account",
+    request_body = Account,
+    responses(
+        (status = 200, body = Account),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn put(
+    ApiUserKey(user_key, _): ApiUserKey<Admin>,
+    Extension(pool): Extension<PgPool>,
+    WithRejection(Json(account), _): WithRejection<Json<Account>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let timezone = Tz::from_str(&account.timezone);
+
+    let mut errors = validate_daily_max(account.daily_max);
+    errors.require(
+        timezone.is_ok(),
+        "timezone",
+        format!("{} isn't a recognized timezone", account.timezone),
+    );
+
+    let timezone = match timezone {
+        Ok(timezone) if errors.is_empty() => timezone,
+        _ => return Err(AppError::InvalidFields(errors).into()),
+    };
+
+    sqlx::query!(
+        "UPDATE users SET daily_max = $1, timezone = $2 WHERE user_key = $3",
+        account.daily_max,
+        timezone.to_string(),
+        user_key.0,
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(Json(Account {
+        daily_max: account.daily_max,
+        timezone: timezone.to_string(),
+    }))
+}
@@ -0,0 +1,129 @@
+use axum::{
+    debug_handler,
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use sqlx::PgPool;
+
+use super::daysoff::{get_days_off, set_day_off_projects, DayOff, DayOffFields};
+use crate::{
+    errors::{ApiError, ApiResult, AppError, ProblemDocument},
+    session::{ApiUserKey, ReadStatus, WriteDaysOff},
+};
+
+/// Get one of the user's days off
+#[utoipa::path(
+    get,
+    path = "/api/v2/daysoff/{day_off_key}/",
+    tag = "days off",
+    params(("day_off_key" = i64, Path)),
+    responses(
+        (status = 200, body = DayOff),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn get(
+    ApiUserKey(user_key, _): ApiUserKey<ReadStatus>,
+    Extension(pool): Extension<PgPool>,
+    WithRejection(Path(day_off_key), _): WithRejection<Path<i64>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let day_off = get_days_off(user_key, Some(day_off_key), None, 0, &pool)
+        .await?
+        .pop()
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(day_off))
+}
+
+/// Move one of the user's days off, or change which projects take it
+#[utoipa::path(
+    put,
+    path = "/api/v2/daysoff/{day_off_key}/",
+    tag = "days off",
+    params(("day_off_key" = i64, Path)),
+    request_body = DayOffFields,
+    responses(
+        (status = 200, body = DayOff),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn put(
+    ApiUserKey(user_key, _): ApiUserKey<WriteDaysOff>,
+    Extension(pool): Extension<PgPool>,
+    WithRejection(Path(day_off_key), _): WithRejection<Path<i64>, ApiError>,
+    WithRejection(Json(fields), _): WithRejection<Json<DayOffFields>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    fields.validate(user_key, Some(day_off_key), &pool).await?;
+
+    let mut transaction = pool.begin().await?;
+
+    let updated = sqlx::query!(
+        "UPDATE days_off
+        SET day_off = $3
+        WHERE day_off_key = $1
+        AND user_key = $2",
+        day_off_key,
+        user_key.0,
+        fields.date,
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if !updated {
+        return Err(AppError::NotFound.into());
+    }
+
+    set_day_off_projects(day_off_key, &fields.project_keys, &mut transaction).await?;
+
+    transaction.commit().await?;
+
+    let day_off = get_days_off(user_key, Some(day_off_key), None, 0, &pool)
+        .await?
+        .pop()
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(day_off))
+}
+
+/// Delete one of the user's days off
+#[utoipa::path(
+    delete,
+    path = "/api/v2/daysoff/{day_off_key}/",
+    tag = "days off",
+    params(("day_off_key" = i64, Path)),
+    responses(
+        (status = 204),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn delete(
+    ApiUserKey(user_key, _): ApiUserKey<WriteDaysOff>,
+    Extension(pool): Extension<PgPool>,
+    WithRejection(Path(day_off_key), _): WithRejection<Path<i64>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let deleted = sqlx::query!(
+        "DELETE FROM days_off
+        WHERE day_off_key = $1
+        AND user_key = $2",
+        day_off_key,
+        user_key.0,
+    )
+    .execute(&pool)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound.into())
+    }
+}
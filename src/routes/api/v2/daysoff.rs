@@ -0,0 +1,245 @@
+use axum::{
+    debug_handler,
+    extract::{Extension, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use utoipa::ToSchema;
+
+use super::{Page, Pagination};
+use crate::{
+    errors::{ApiError, ApiResult, AppError, AppResult, ProblemDocument},
+    session::{ApiUserKey, ReadStatus, UserKey, WriteDaysOff},
+    validation::FieldErrors,
+};
+
+/// A day off, as it's read and written through the API
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DayOffFields {
+    pub date: NaiveDate,
+    /// The projects that take the day off
+    pub project_keys: Vec<i64>,
+}
+
+impl DayOffFields {
+    /// Check the day off the way the new day off form does
+    ///
+    /// `day_off_key` is the day off being replaced, if any, so that it can keep its date.
+    pub async fn validate(
+        &self,
+        user_key: UserKey,
+        day_off_key: Option<i64>,
+        pool: &PgPool,
+    ) -> AppResult<()> {
+        let mut project_keys = self.project_keys.clone();
+        project_keys.sort_unstable();
+        project_keys.dedup();
+
+        let owned_projects = sqlx::query!(
+            r#"SELECT COUNT(*) AS "count!"
+            FROM projects
+            WHERE project_key = ANY($1::BIGINT[])
+            AND user_key = $2"#,
+            &project_keys,
+            user_key.0,
+        )
+        .fetch_one(pool)
+        .await?
+        .count;
+
+        let date_taken = sqlx::query!(
+            "SELECT day_off_key FROM days_off
+            WHERE user_key = $1
+            AND day_off = $2
+            AND day_off_key IS DISTINCT FROM $3",
+            user_key.0,
+            self.date,
+            day_off_key,
+        )
+        .fetch_optional(pool)
+        .await?
+        .is_some();
+
+        let mut errors = FieldErrors::new();
+        errors.require(
+            !date_taken,
+            "date",
+            "You already have a day off on this date",
+        );
+        errors.require(
+            !project_keys.is_empty(),
+            "project_keys",
+            "Choose at least one project to take the day off from",
+        );
+        errors.require(
+            owned_projects == project_keys.len() as i64,
+            "project_keys",
+            "Some of these projects don't exist",
+        );
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::InvalidFields(errors))
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DayOff {
+    pub day_off_key: i64,
+    #[serde(flatten)]
+    pub fields: DayOffFields,
+}
+
+/// Some of the user's days off, ordered by date, or just the one with `day_off_key`
+pub async fn get_days_off(
+    user_key: UserKey,
+    day_off_key: Option<i64>,
+    limit: Option<i64>,
+    offset: i64,
+    pool: &PgPool,
+) -> AppResult<Vec<DayOff>> {
+    let records = sqlx::query!(
+        r#"SELECT day_off_key, day_off,
+            ARRAY(
+                SELECT project_key
+                FROM days_off_to_projects
+                WHERE days_off_to_projects.day_off_key = days_off.day_off_key
+                ORDER BY project_key
+            ) AS "project_keys!"
+        FROM days_off
+        WHERE user_key = $1
+        AND ($2::BIGINT IS NULL OR day_off_key = $2)
+        ORDER BY day_off
+        LIMIT $3
+        OFFSET $4"#,
+        user_key.0,
+        day_off_key,
+        limit,
+        offset,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| DayOff {
+            day_off_key: record.day_off_key,
+            fields: DayOffFields {
+                date: record.day_off,
+                project_keys: record.project_keys,
+            },
+        })
+        .collect())
+}
+
+/// Link a day off to exactly these projects
+pub async fn set_day_off_projects(
+    day_off_key: i64,
+    project_keys: &[i64],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> AppResult<()> {
+    sqlx::query!(
+        "DELETE FROM days_off_to_projects
+        WHERE day_off_key = $1
+        AND project_key <> ALL($2::BIGINT[])",
+        day_off_key,
+        project_keys,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO days_off_to_projects(project_key, day_off_key)
+        SELECT project_key, $1 FROM UNNEST($2::BIGINT[]) AS project_key
+        ON CONFLICT DO NOTHING",
+        day_off_key,
+        project_keys,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// List the user's days off
+#[utoipa::path(
+    get,
+    path = "/api/v2/daysoff/",
+    tag = "days off",
+    params(Pagination),
+    responses(
+        (status = 200, body = Page<DayOff>),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn get(
+    ApiUserKey(user_key, _): ApiUserKey<ReadStatus>,
+    Extension(pool): Extension<PgPool>,
+    WithRejection(Query(pagination), _): WithRejection<Query<Pagination>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let (limit, offset) = pagination.limit_offset()?;
+
+    let days_off = get_days_off(user_key, None, Some(limit), offset, &pool).await?;
+
+    let total = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM days_off WHERE user_key = $1"#,
+        user_key.0,
+    )
+    .fetch_one(&pool)
+    .await?
+    .count;
+
+    Ok(Json(pagination.page(days_off, total)))
+}
+
+/// Take a day off from some of the user's projects
+#[utoipa::path(
+    post,
+    path = "/api/v2/daysoff/",
+    tag = "days off",
+    request_body = DayOffFields,
+    responses(
+        (status = 201, body = DayOff),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn post(
+    ApiUserKey(user_key, _): ApiUserKey<WriteDaysOff>,
+    Extension(pool): Extension<PgPool>,
+    WithRejection(Json(fields), _): WithRejection<Json<DayOffFields>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    fields.validate(user_key, None, &pool).await?;
+
+    let mut transaction = pool.begin().await?;
+
+    let day_off_key = sqlx::query!(
+        "INSERT INTO days_off(user_key, day_off)
+        VALUES ($1, $2)
+        RETURNING day_off_key",
+        user_key.0,
+        fields.date,
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .day_off_key;
+
+    set_day_off_projects(day_off_key, &fields.project_keys, &mut transaction).await?;
+
+    transaction.commit().await?;
+
+    let day_off = get_days_off(user_key, Some(day_off_key), None, 0, &pool)
+        .await?
+        .pop()
+        .ok_or(AppError::NotFound)?;
+
+    Ok((StatusCode::CREATED, Json(day_off)))
+}
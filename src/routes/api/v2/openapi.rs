@@ -0,0 +1,54 @@
+use axum::{debug_handler, response::IntoResponse, Json};
+use utoipa::{
+    openapi::security::{Http, HttpAuthScheme, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use super::{account, dayoff, daysoff, project, projects};
+
+/// The OpenAPI document for the API, generated from the handlers and the types they use
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Yottaclock API",
+        version = "2",
+        description = "Authenticate with a personal API token from the account page. Reading \
+            needs the read_status scope, changing days off needs write_days_off, and changing \
+            projects or account settings needs admin. Errors are problem documents (RFC 9457).",
+    ),
+    paths(
+        projects::get,
+        projects::post,
+        project::get,
+        project::put,
+        project::delete,
+        daysoff::get,
+        daysoff::post,
+        dayoff::get,
+        dayoff::put,
+        dayoff::delete,
+        account::get,
+        account::put,
+    ),
+    modifiers(&ApiTokenSecurity),
+    security(("api_token" = [])),
+)]
+pub struct ApiDoc;
+
+struct ApiTokenSecurity;
+
+impl Modify for ApiTokenSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
+
+#[debug_handler]
+pub async fn get() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,119 @@
+use axum::{
+    debug_handler,
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use reqwest::Client;
+use sqlx::PgPool;
+
+use super::projects::{Project, ProjectFields};
+use crate::{
+    crypto::ServerKey,
+    errors::{ApiError, ApiResult, AppError, ProblemDocument},
+    project_settings::{get_project_settings, update_project},
+    session::{Admin, ApiUserKey, ReadStatus},
+    toggl::ProjectKey,
+};
+
+/// Get one of the user's projects
+#[utoipa::path(
+    get,
+    path = "/api/v2/projects/{project_key}/",
+    tag = "projects",
+    params(("project_key" = i64, Path)),
+    responses(
+        (status = 200, body = Project),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn get(
+    ApiUserKey(user_key, _): ApiUserKey<ReadStatus>,
+    Extension(pool): Extension<PgPool>,
+    WithRejection(Path(project_key), _): WithRejection<Path<ProjectKey>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let settings = get_project_settings(user_key, project_key, &pool)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(Project::new(project_key, settings)))
+}
+
+/// Replace the settings of one of the user's projects, keeping its days off
+#[utoipa::path(
+    put,
+    path = "/api/v2/projects/{project_key}/",
+    tag = "projects",
+    params(("project_key" = i64, Path)),
+    request_body = ProjectFields,
+    responses(
+        (status = 200, body = Project),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn put(
+    ApiUserKey(user_key, _): ApiUserKey<Admin>,
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    WithRejection(Path(project_key), _): WithRejection<Path<ProjectKey>, ApiError>,
+    WithRejection(Json(fields), _): WithRejection<Json<ProjectFields>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let settings = fields
+        .validate(user_key, Some(project_key), &pool, &client, &server_key)
+        .await?;
+
+    let mut transaction = pool.begin().await?;
+
+    if !update_project(user_key, project_key, &settings, &mut transaction).await? {
+        return Err(AppError::NotFound.into());
+    }
+
+    transaction.commit().await?;
+
+    let settings = get_project_settings(user_key, project_key, &pool)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(Project::new(project_key, settings)))
+}
+
+/// Delete one of the user's projects
+#[utoipa::path(
+    delete,
+    path = "/api/v2/projects/{project_key}/",
+    tag = "projects",
+    params(("project_key" = i64, Path)),
+    responses(
+        (status = 204),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn delete(
+    ApiUserKey(user_key, _): ApiUserKey<Admin>,
+    Extension(pool): Extension<PgPool>,
+    WithRejection(Path(project_key), _): WithRejection<Path<ProjectKey>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let deleted = sqlx::query!(
+        "DELETE FROM projects
+        WHERE project_key = $1
+        AND user_key = $2",
+        project_key.0,
+        user_key.0,
+    )
+    .execute(&pool)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound.into())
+    }
+}
@@ -0,0 +1,378 @@
+use axum::{
+    debug_handler,
+    extract::{Extension, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use chrono::{Duration, NaiveDate};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use super::{Page, Pagination};
+use crate::{
+    crypto::ServerKey,
+    errors::{ApiError, ApiResult, AppError, AppResult, ProblemDocument},
+    project_settings::{
+        allowed_project_ids, count_projects, get_project_settings, get_project_settings_page,
+        insert_project, ProjectSettings,
+    },
+    session::{Admin, ApiUserKey, ReadStatus, UserKey},
+    toggl::{
+        get_all_toggl_projects_including_archived, get_user_today, get_user_toggl_api_token,
+        get_user_workspace_ids, Deadline, Limit, LimitPeriod, ProjectId, ProjectKey, WhichWeekdays,
+    },
+    validation::FieldErrors,
+};
+
+mod tests;
+
+/// A project, as it's read and written through the API
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProjectFields {
+    /// What to call the project, or `null` to name it after what it counts
+    #[serde(default)]
+    pub name: Option<String>,
+    pub filter: Filter,
+    pub schedule: Schedule,
+    /// Projects with a higher priority get their share of the day before any others do
+    #[serde(default)]
+    pub priority: i32,
+    /// How big a share of the day the project gets compared to others with the same priority
+    #[serde(default = "default_weight")]
+    #[schema(minimum = 1, maximum = 100, default = 1)]
+    pub weight: i32,
+}
+
+fn default_weight() -> i32 {
+    1
+}
+
+/// Which Toggl entries count towards the project
+///
+/// An entry counts if it matches any of `project_ids`, `clients`, `tags`, `no_project`,
+/// `description_substrings` or `description_regexes`, unless it's left out by `billable_only`,
+/// `required_tags` or `excluded_tags`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Filter {
+    /// Toggl projects, written as `<workspace_id>:<project_id>`
+    #[serde(default)]
+    #[schema(value_type = Vec<String>, example = json!(["1234567:7654321"]))]
+    pub project_ids: Vec<ProjectId>,
+    #[serde(default)]
+    pub clients: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub no_project: bool,
+    #[serde(default)]
+    pub description_substrings: Vec<String>,
+    #[serde(default)]
+    pub description_regexes: Vec<String>,
+    #[serde(default)]
+    pub billable_only: bool,
+    #[serde(default)]
+    pub required_tags: Vec<String>,
+    #[serde(default)]
+    pub excluded_tags: Vec<String>,
+}
+
+/// When the project's goal applies
+///
+/// A project either has a `daily_goal`, or a `deadline` and a `target_total` to reach by then, in
+/// which case its daily goal is worked out each day from the time that's left, or a `limit` on how
+/// much time to spend on it rather than a goal.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Schedule {
+    pub starting_date: NaiveDate,
+    /// How much time to spend on the project each day, in seconds
+    #[serde(default)]
+    #[schema(maximum = 86400)]
+    pub daily_goal: i64,
+    pub weekdays: Vec<Weekday>,
+    #[serde(default)]
+    pub deadline: Option<NaiveDate>,
+    /// How much time to spend on the project by the deadline, in seconds
+    #[serde(default)]
+    #[schema(maximum = 3_600_000_000)]
+    pub target_total: Option<i64>,
+    #[serde(default)]
+    pub limit: Option<ScheduleLimit>,
+}
+
+/// The most time to spend on the project in each day or week, which starts on Monday, and which
+/// can't be more than the whole day or week
+///
+/// Limits count every day from the starting date, whatever the project's weekdays and days off.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ScheduleLimit {
+    /// In seconds
+    pub time: i64,
+    pub period: LimitPeriod,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+/// Turn a number of seconds into a duration, or record an error if it's too long to be one
+fn seconds(seconds: i64, errors: &mut FieldErrors, field: &'static str) -> Duration {
+    Duration::try_seconds(seconds).unwrap_or_else(|| {
+        errors.add(field, "This is too long");
+        Duration::zero()
+    })
+}
+
+impl ProjectFields {
+    fn settings(&self) -> Result<ProjectSettings, FieldErrors> {
+        let has_weekday = |weekday| self.schedule.weekdays.contains(&weekday);
+        let mut errors = FieldErrors::new();
+
+        let settings = ProjectSettings {
+            name: self
+                .name
+                .as_deref()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(String::from),
+            project_ids: self.filter.project_ids.clone(),
+            clients: self.filter.clients.clone(),
+            tags: self.filter.tags.clone(),
+            no_project: self.filter.no_project,
+            description_substrings: self.filter.description_substrings.clone(),
+            description_regexes: self.filter.description_regexes.clone(),
+            billable_only: self.filter.billable_only,
+            required_tags: self.filter.required_tags.clone(),
+            excluded_tags: self.filter.excluded_tags.clone(),
+            starting_date: self.schedule.starting_date,
+            daily_goal: seconds(self.schedule.daily_goal, &mut errors, "daily_goal"),
+            weekdays: WhichWeekdays {
+                monday: has_weekday(Weekday::Monday),
+                tuesday: has_weekday(Weekday::Tuesday),
+                wednesday: has_weekday(Weekday::Wednesday),
+                thursday: has_weekday(Weekday::Thursday),
+                friday: has_weekday(Weekday::Friday),
+                saturday: has_weekday(Weekday::Saturday),
+                sunday: has_weekday(Weekday::Sunday),
+            },
+            priority: self.priority,
+            weight: self.weight,
+            deadline: self.schedule.deadline.zip(self.schedule.target_total).map(
+                |(date, target_total)| Deadline {
+                    date,
+                    total: seconds(target_total, &mut errors, "target_total"),
+                },
+            ),
+            limit: self.schedule.limit.as_ref().map(|limit| Limit {
+                time: seconds(limit.time, &mut errors, "limit"),
+                period: limit.period,
+            }),
+        };
+
+        if errors.is_empty() {
+            Ok(settings)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Check the project the way the new project form does, and turn it into settings to store
+    ///
+    /// `project_key` is the project being changed, if it's not a new one.
+    pub async fn validate(
+        &self,
+        user_key: UserKey,
+        project_key: Option<ProjectKey>,
+        pool: &PgPool,
+        client: &Client,
+        server_key: &ServerKey,
+    ) -> AppResult<ProjectSettings> {
+        if self.schedule.deadline.is_some() != self.schedule.target_total.is_some() {
+            let mut errors = FieldErrors::new();
+            errors.add(
+                "target_total",
+                "A deadline and a total target have to be given together",
+            );
+
+            return Err(AppError::InvalidFields(errors));
+        }
+
+        let settings = self.settings().map_err(AppError::InvalidFields)?;
+
+        // Only ask Toggl which projects are available if there are any to check
+        let available_project_ids: Vec<ProjectId> = if settings.project_ids.is_empty() {
+            Vec::new()
+        } else {
+            let toggl_api_key = get_user_toggl_api_token(user_key, pool, server_key).await?;
+            let workspace_ids = get_user_workspace_ids(user_key, pool).await?;
+
+            let existing_project_ids = match project_key {
+                Some(project_key) => get_project_settings(user_key, project_key, pool)
+                    .await?
+                    .map(|settings| settings.project_ids)
+                    .unwrap_or_default(),
+                None => Vec::new(),
+            };
+
+            let toggl_projects =
+                get_all_toggl_projects_including_archived(&toggl_api_key, &workspace_ids, client)
+                    .await?;
+
+            allowed_project_ids(&toggl_projects, &existing_project_ids)
+        };
+
+        let today = get_user_today(user_key, pool).await?;
+
+        let errors = settings.validate(&available_project_ids, today);
+
+        if errors.is_empty() {
+            Ok(settings)
+        } else {
+            Err(AppError::InvalidFields(errors))
+        }
+    }
+}
+
+impl From<ProjectSettings> for ProjectFields {
+    fn from(settings: ProjectSettings) -> Self {
+        let weekdays = [
+            (settings.weekdays.monday, Weekday::Monday),
+            (settings.weekdays.tuesday, Weekday::Tuesday),
+            (settings.weekdays.wednesday, Weekday::Wednesday),
+            (settings.weekdays.thursday, Weekday::Thursday),
+            (settings.weekdays.friday, Weekday::Friday),
+            (settings.weekdays.saturday, Weekday::Saturday),
+            (settings.weekdays.sunday, Weekday::Sunday),
+        ]
+        .into_iter()
+        .filter_map(|(included, weekday)| included.then_some(weekday))
+        .collect();
+
+        Self {
+            name: settings.name,
+            filter: Filter {
+                project_ids: settings.project_ids,
+                clients: settings.clients,
+                tags: settings.tags,
+                no_project: settings.no_project,
+                description_substrings: settings.description_substrings,
+                description_regexes: settings.description_regexes,
+                billable_only: settings.billable_only,
+                required_tags: settings.required_tags,
+                excluded_tags: settings.excluded_tags,
+            },
+            schedule: Schedule {
+                starting_date: settings.starting_date,
+                daily_goal: settings.daily_goal.num_seconds(),
+                weekdays,
+                deadline: settings.deadline.map(|deadline| deadline.date),
+                target_total: settings
+                    .deadline
+                    .map(|deadline| deadline.total.num_seconds()),
+                limit: settings.limit.map(|limit| ScheduleLimit {
+                    time: limit.time.num_seconds(),
+                    period: limit.period,
+                }),
+            },
+            priority: settings.priority,
+            weight: settings.weight,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Project {
+    pub project_key: i64,
+    #[serde(flatten)]
+    pub fields: ProjectFields,
+}
+
+impl Project {
+    pub fn new(project_key: ProjectKey, settings: ProjectSettings) -> Self {
+        Self {
+            project_key: project_key.0,
+            fields: settings.into(),
+        }
+    }
+}
+
+/// List the user's projects
+#[utoipa::path(
+    get,
+    path = "/api/v2/projects/",
+    tag = "projects",
+    params(Pagination),
+    responses(
+        (status = 200, body = Page<Project>),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn get(
+    ApiUserKey(user_key, _): ApiUserKey<ReadStatus>,
+    Extension(pool): Extension<PgPool>,
+    WithRejection(Query(pagination), _): WithRejection<Query<Pagination>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let (limit, offset) = pagination.limit_offset()?;
+
+    let projects = get_project_settings_page(user_key, limit, offset, &pool)
+        .await?
+        .into_iter()
+        .map(|(project_key, settings)| Project::new(project_key, settings))
+        .collect();
+
+    let total = count_projects(user_key, &pool).await?;
+
+    Ok(Json(pagination.page(projects, total)))
+}
+
+/// Create a project
+#[utoipa::path(
+    post,
+    path = "/api/v2/projects/",
+    tag = "projects",
+    request_body = ProjectFields,
+    responses(
+        (status = 201, body = Project),
+        (status = "4XX", body = ProblemDocument, content_type = "application/problem+json"),
+    ),
+)]
+#[debug_handler]
+pub async fn post(
+    ApiUserKey(user_key, _): ApiUserKey<Admin>,
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    WithRejection(Json(fields), _): WithRejection<Json<ProjectFields>, ApiError>,
+) -> ApiResult<impl IntoResponse> {
+    let settings = fields
+        .validate(user_key, None, &pool, &client, &server_key)
+        .await?;
+
+    let mut transaction = pool.begin().await?;
+
+    let project_key = insert_project(user_key, &settings, &mut transaction).await?;
+
+    transaction.commit().await?;
+
+    // Read the project back so that it's returned the way it was stored
+    let settings = get_project_settings(user_key, project_key, &pool)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Project::new(project_key, settings)),
+    ))
+}
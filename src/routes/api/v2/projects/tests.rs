@@ -0,0 +1,65 @@
+#![cfg(test)]
+
+use super::*;
+
+fn example_fields(daily_goal: i64) -> ProjectFields {
+    ProjectFields {
+        name: Some("Thesis".to_owned()),
+        filter: Filter {
+            project_ids: Vec::new(),
+            clients: Vec::new(),
+            tags: Vec::from(["thesis".to_owned()]),
+            no_project: false,
+            description_substrings: Vec::new(),
+            description_regexes: Vec::new(),
+            billable_only: false,
+            required_tags: Vec::new(),
+            excluded_tags: Vec::new(),
+        },
+        schedule: Schedule {
+            starting_date: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            daily_goal,
+            weekdays: Vec::from([Weekday::Monday, Weekday::Wednesday, Weekday::Friday]),
+            deadline: None,
+            target_total: None,
+            limit: None,
+        },
+        priority: 0,
+        weight: 1,
+    }
+}
+
+#[sqlx::test]
+async fn test_validate_oversized_project(pool: PgPool) -> AppResult<()> {
+    let user_key = UserKey(
+        sqlx::query!(
+            "INSERT INTO users(username, daily_max, timezone)
+            VALUES ('ada', 3600, 'UTC')
+            RETURNING user_key",
+        )
+        .fetch_one(&pool)
+        .await?
+        .user_key,
+    );
+
+    let client = Client::new();
+    let server_key =
+        ServerKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+
+    let settings = example_fields(60 * 60)
+        .validate(user_key, None, &pool, &client, &server_key)
+        .await?;
+    assert_eq!(settings.daily_goal, Duration::hours(1));
+
+    // A daily goal this big would overflow the debts it's added to
+    let error = example_fields(1_000_000_000_000_000)
+        .validate(user_key, None, &pool, &client, &server_key)
+        .await
+        .unwrap_err();
+    assert!(matches!(&error, AppError::InvalidFields(errors) if errors.has("daily_goal")));
+
+    let response = ApiError(error).into_response();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+#![cfg(test)]
+
+use utoipa::OpenApi;
+
+use super::*;
+
+#[test]
+fn test_pagination() {
+    let pagination = Pagination {
+        page: 3,
+        per_page: 20,
+    };
+    assert_eq!(pagination.limit_offset().unwrap(), (20, 40));
+
+    let page = pagination.page(Vec::from(["a", "b"]), 42);
+    assert_eq!(page.total, 42);
+    assert_eq!(page.page, 3);
+
+    let before_first = Pagination {
+        page: 0,
+        per_page: 20,
+    };
+    assert!(matches!(
+        before_first.limit_offset(),
+        Err(AppError::Validation(_))
+    ));
+
+    let too_long = Pagination {
+        page: 1,
+        per_page: MAX_PER_PAGE + 1,
+    };
+    assert!(matches!(
+        too_long.limit_offset(),
+        Err(AppError::Validation(_))
+    ));
+
+    let too_far = Pagination {
+        page: i64::MAX,
+        per_page: 20,
+    };
+    assert!(matches!(
+        too_far.limit_offset(),
+        Err(AppError::Validation(_))
+    ));
+}
+
+#[test]
+fn test_openapi_document() {
+    let document = serde_json::to_value(openapi::ApiDoc::openapi()).unwrap();
+
+    for path in [
+        "/api/v2/projects/",
+        "/api/v2/projects/{project_key}/",
+        "/api/v2/daysoff/",
+        "/api/v2/daysoff/{day_off_key}/",
+        "/api/v2/account/",
+    ] {
+        assert!(document["paths"][path].is_object(), "{path} is missing");
+    }
+
+    for schema in [
+        "Project",
+        "ProjectFields",
+        "DayOff",
+        "Account",
+        "ProblemDocument",
+    ] {
+        assert!(
+            document["components"]["schemas"][schema].is_object(),
+            "{schema} is missing"
+        );
+    }
+}
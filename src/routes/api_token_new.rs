@@ -0,0 +1,78 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{Html, IntoResponse},
+};
+use reqwest::Client;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    crypto::{generate_api_token, hash_api_token, ServerKey},
+    csrf::{CsrfForm, CsrfToken},
+    errors::{AppError, AppResult},
+    session::UserKey,
+};
+
+use super::account::{render, AccountForms};
+
+#[derive(Deserialize)]
+pub struct ApiTokenNewForm {
+    name: String,
+    read_status: Option<String>,
+    write_days_off: Option<String>,
+    admin: Option<String>,
+}
+
+#[debug_handler]
+pub async fn post(
+    user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    CsrfForm(form): CsrfForm<ApiTokenNewForm>,
+) -> AppResult<impl IntoResponse> {
+    let name = form.name.trim();
+    let read_status = form.read_status.is_some();
+    let write_days_off = form.write_days_off.is_some();
+    let admin = form.admin.is_some();
+
+    if name.is_empty() || !(read_status || write_days_off || admin) {
+        return Err(AppError::Validation(
+            "API tokens need a name and at least one scope".to_owned(),
+        ));
+    }
+
+    let api_token = generate_api_token();
+
+    sqlx::query!(
+        "INSERT INTO api_tokens(user_key, name, digest, read_status, write_days_off, admin)
+        VALUES ($1, $2, $3, $4, $5, $6)",
+        user_key.0,
+        name,
+        hash_api_token(&api_token),
+        read_status,
+        write_days_off,
+        admin,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Only the hash is stored, so this is the one chance to see the token
+    Ok(Html(
+        render(
+            user_key,
+            &pool,
+            client,
+            &server_key,
+            &csrf_token,
+            &AccountForms {
+                new_api_token: Some(&api_token),
+                ..AccountForms::default()
+            },
+        )
+        .await?,
+    )
+    .into_response())
+}
@@ -1,12 +1,12 @@
 use axum::{
     debug_handler,
-    extract::{Extension, Form},
+    extract::Extension,
     response::{IntoResponse, Redirect},
 };
 use serde::Deserialize;
 use sqlx::PgPool;
 
-use crate::{errors::InternalResult, session::UserKey};
+use crate::{csrf::CsrfForm, errors::AppResult, session::UserKey};
 
 use super::daysoff::DayOffKey;
 
@@ -19,8 +19,8 @@ pub struct DayOffDeleteForm {
 pub async fn post(
     user_key: UserKey,
     Extension(pool): Extension<PgPool>,
-    Form(form): Form<DayOffDeleteForm>,
-) -> InternalResult<impl IntoResponse> {
+    CsrfForm(form): CsrfForm<DayOffDeleteForm>,
+) -> AppResult<impl IntoResponse> {
     sqlx::query!(
         "DELETE FROM days_off
         WHERE day_off_key = $1
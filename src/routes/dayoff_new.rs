@@ -1,25 +1,29 @@
-use std::collections::HashMap;
-
 use askama::Template;
 use axum::{
     debug_handler,
-    extract::{Extension, Form},
+    extract::Extension,
+    http::StatusCode,
     response::{Html, IntoResponse, Redirect},
 };
 use chrono::NaiveDate;
-use futures::future;
 use reqwest::Client;
 use serde::Deserialize;
 use sqlx::PgPool;
 
 use crate::{
-    errors::InternalResult,
+    crypto::ServerKey,
+    csrf::{CsrfForm, CsrfToken},
+    errors::AppResult,
     session::UserKey,
-    toggl::{get_user_projects, ProjectId, WorkspaceId},
+    toggl::{
+        get_user_projects, get_user_today, get_user_toggl_api_token, get_user_workspace_ids,
+        ProjectKey,
+    },
+    validation::FieldErrors,
 };
 
 struct Project {
-    pub id: ProjectId,
+    pub key: ProjectKey,
     pub name: String,
 }
 
@@ -27,61 +31,136 @@ struct Project {
 #[template(path = "dayoff_new.html")]
 pub struct NewDayOffTemplate<'a> {
     projects: &'a [Project],
+    form: &'a NewDayOffForm,
+    errors: &'a FieldErrors,
+    csrf_token: &'a str,
 }
 
 #[debug_handler]
 pub async fn get(
     user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
     Extension(pool): Extension<PgPool>,
     Extension(client): Extension<Client>,
-) -> InternalResult<impl IntoResponse> {
-    let record = sqlx::query!(
-        "SELECT toggl_api_key, workspace_id
-        FROM users
-        WHERE user_key = $1",
-        user_key.0,
-    )
-    .fetch_one(&pool)
-    .await?;
-
-    let mut projects: Vec<Project> = get_user_projects(
-        &record.toggl_api_key,
-        WorkspaceId(record.workspace_id),
-        &client,
-        user_key,
-        &pool,
-    )
-    .await?
-    .into_iter()
-    .map(|(project_id, project)| Project {
-        id: project_id,
-        name: project.name,
-    })
-    .collect();
-    projects.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+    Extension(server_key): Extension<ServerKey>,
+) -> AppResult<impl IntoResponse> {
+    let projects = get_projects(user_key, &pool, &client, &server_key).await?;
+
+    // Every project is off by default
+    let form = NewDayOffForm {
+        date: get_user_today(user_key, &pool).await?,
+        project_keys: projects.iter().map(|project| project.key).collect(),
+    };
 
     let template = NewDayOffTemplate {
         projects: &projects,
+        form: &form,
+        errors: &FieldErrors::new(),
+        csrf_token: &csrf_token,
     };
 
     Ok(Html(template.render()?))
 }
 
+async fn get_projects(
+    user_key: UserKey,
+    pool: &PgPool,
+    client: &Client,
+    server_key: &ServerKey,
+) -> AppResult<Vec<Project>> {
+    let toggl_api_key = get_user_toggl_api_token(user_key, pool, server_key).await?;
+
+    let workspace_ids = get_user_workspace_ids(user_key, pool).await?;
+
+    let mut projects: Vec<Project> =
+        get_user_projects(&toggl_api_key, &workspace_ids, client, user_key, pool)
+            .await?
+            .into_iter()
+            .map(|(project_key, project)| Project {
+                key: project_key,
+                name: project.name,
+            })
+            .collect();
+    projects.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+
+    Ok(projects)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct NewDayOffForm {
     date: NaiveDate,
+    #[serde(default)]
+    project_keys: Vec<ProjectKey>,
+}
 
-    #[serde(flatten)]
-    project_ids: HashMap<String, String>,
+impl NewDayOffForm {
+    fn has_project_key(&self, project_key: &ProjectKey) -> bool {
+        self.project_keys.contains(project_key)
+    }
 }
 
 #[debug_handler]
 pub async fn post(
     user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
     Extension(pool): Extension<PgPool>,
-    Form(form): Form<NewDayOffForm>,
-) -> InternalResult<impl IntoResponse> {
-    let pool = &pool;
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    CsrfForm(form): CsrfForm<NewDayOffForm>,
+) -> AppResult<impl IntoResponse> {
+    let user_project_keys: Vec<ProjectKey> = sqlx::query!(
+        "SELECT project_key FROM projects WHERE user_key = $1",
+        user_key.0,
+    )
+    .fetch_all(&pool)
+    .await?
+    .into_iter()
+    .map(|record| ProjectKey(record.project_key))
+    .collect();
+
+    let date_taken = sqlx::query!(
+        "SELECT day_off_key FROM days_off WHERE user_key = $1 AND day_off = $2",
+        user_key.0,
+        form.date,
+    )
+    .fetch_optional(&pool)
+    .await?
+    .is_some();
+
+    let mut errors = FieldErrors::new();
+    errors.require(
+        !date_taken,
+        "date",
+        "You already have a day off on this date",
+    );
+    errors.require(
+        !form.project_keys.is_empty(),
+        "project_keys",
+        "Choose at least one project to take the day off from",
+    );
+    errors.require(
+        form.project_keys
+            .iter()
+            .all(|project_key| user_project_keys.contains(project_key)),
+        "project_keys",
+        "Some of these projects don't exist anymore",
+    );
+
+    if !errors.is_empty() {
+        // Show the form again with the user's input and what's wrong with it
+        let projects = get_projects(user_key, &pool, &client, &server_key).await?;
+
+        let template = NewDayOffTemplate {
+            projects: &projects,
+            form: &form,
+            errors: &errors,
+            csrf_token: &csrf_token,
+        };
+
+        return Ok((StatusCode::BAD_REQUEST, Html(template.render()?)).into_response());
+    }
+
+    let mut transaction = pool.begin().await?;
 
     let day_off_key = sqlx::query!(
         "INSERT INTO days_off(user_key, day_off)
@@ -90,41 +169,27 @@ pub async fn post(
         user_key.0,
         form.date,
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *transaction)
     .await?
     .day_off_key;
 
-    let futures = form.project_ids.keys().map(|project_id| async move {
-        let project_id = ProjectId(project_id.parse()?);
-
-        let project_key = sqlx::query!(
-            "SELECT project_key
-            FROM projects
-            WHERE project_id = $1
-            AND user_key = $2",
-            project_id.0,
-            user_key.0,
-        )
-        .fetch_one(pool)
-        .await?
-        .project_key;
-
-        sqlx::query!(
-            "INSERT INTO days_off_to_projects(project_key, day_off_key)
-            VALUES ($1, $2)",
-            project_key,
-            day_off_key,
-        )
-        .execute(pool)
-        .await?;
-
-        Ok(())
-    });
-
-    let future_results: Vec<InternalResult<()>> = future::join_all(futures).await;
-    for future_result in future_results {
-        future_result?;
-    }
+    let project_keys: Vec<i64> = form
+        .project_keys
+        .iter()
+        .map(|project_key| project_key.0)
+        .collect();
+
+    sqlx::query!(
+        "INSERT INTO days_off_to_projects(project_key, day_off_key)
+        SELECT project_key, $1 FROM UNNEST($2::BIGINT[]) AS project_key
+        ON CONFLICT DO NOTHING",
+        day_off_key,
+        &project_keys,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
 
-    Ok(Redirect::to("/"))
+    Ok(Redirect::to("/").into_response())
 }
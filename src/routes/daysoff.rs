@@ -12,9 +12,13 @@ use serde::Deserialize;
 use sqlx::PgPool;
 
 use crate::{
-    errors::InternalResult,
+    crypto::ServerKey,
+    csrf::CsrfToken,
+    errors::AppResult,
     session::UserKey,
-    toggl::{get_user_projects, Project, ProjectId, WorkspaceId},
+    toggl::{
+        get_user_projects, get_user_toggl_api_token, get_user_workspace_ids, Project, ProjectKey,
+    },
 };
 
 #[derive(Deserialize)]
@@ -37,22 +41,22 @@ struct DayOff<'a> {
 #[template(path = "daysoff.html")]
 pub struct DaysOffTemplate<'a> {
     days_off: &'a [DayOff<'a>],
+    csrf_token: &'a str,
 }
 
 #[debug_handler]
 pub async fn get(
     user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
     Extension(pool): Extension<PgPool>,
     Extension(client): Extension<Client>,
-) -> InternalResult<impl IntoResponse> {
+    Extension(server_key): Extension<ServerKey>,
+) -> AppResult<impl IntoResponse> {
     // TODO: Parallelize the await points in this function
 
-    let user_record = sqlx::query!(
-        "SELECT toggl_api_key, workspace_id FROM users WHERE user_key = $1",
-        user_key.0,
-    )
-    .fetch_one(&pool)
-    .await?;
+    let toggl_api_key = get_user_toggl_api_token(user_key, &pool, &server_key).await?;
+
+    let workspace_ids = get_user_workspace_ids(user_key, &pool).await?;
 
     let day_off_records = sqlx::query!(
         "SELECT day_off_key, day_off
@@ -63,24 +67,16 @@ pub async fn get(
     .fetch_all(&pool)
     .await?;
 
-    let project_ids_to_projects = get_user_projects(
-        &user_record.toggl_api_key,
-        WorkspaceId(user_record.workspace_id),
-        &client,
-        user_key,
-        &pool,
-    )
-    .await?;
+    let project_keys_to_projects =
+        get_user_projects(&toggl_api_key, &workspace_ids, &client, user_key, &pool).await?;
 
     let mut days_off = Vec::new();
     for day_off_record in day_off_records {
         let day_off_key = DayOffKey(day_off_record.day_off_key);
 
         let project_records = sqlx::query!(
-            "SELECT project_id
-            FROM projects
-            INNER JOIN days_off_to_projects
-            ON projects.project_key = days_off_to_projects.project_key
+            "SELECT project_key
+            FROM days_off_to_projects
             WHERE day_off_key = $1",
             day_off_key.0,
         )
@@ -89,9 +85,9 @@ pub async fn get(
 
         let mut projects = Vec::new();
         for project_record in project_records {
-            let project_id = ProjectId(project_record.project_id);
+            let project_key = ProjectKey(project_record.project_key);
 
-            if let Some(project) = project_ids_to_projects.get(&project_id) {
+            if let Some(project) = project_keys_to_projects.get(&project_key) {
                 projects.push(project);
             }
         }
@@ -107,6 +103,7 @@ pub async fn get(
 
     let template = DaysOffTemplate {
         days_off: &days_off,
+        csrf_token: &csrf_token,
     };
 
     Ok(Html(template.render()?))
@@ -0,0 +1,62 @@
+use axum::{
+    body::Bytes,
+    debug_handler,
+    extract::Extension,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::{
+    crypto::verify_webhook_signature,
+    errors::{ApiResult, AppError},
+    toggl_hooks::{apply_change, parse_event, Change, TogglHookSecret},
+};
+
+/// The header Toggl puts an event's signature in
+const SIGNATURE_HEADER: &str = "X-Webhook-Signature-256";
+
+#[derive(Serialize)]
+pub struct Validation {
+    pub validation_code: String,
+}
+
+/// Receive an event from a Toggl webhook subscription, which must be signed with the secret in
+/// `TOGGL_WEBHOOK_SECRET`
+#[debug_handler]
+pub async fn post(
+    Extension(pool): Extension<PgPool>,
+    Extension(secret): Extension<Option<TogglHookSecret>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ApiResult<Response> {
+    // Without a secret there's no telling Toggl's events apart from anyone else's
+    let Some(secret) = secret else {
+        return Err(AppError::NotFound.into());
+    };
+
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|signature| signature.to_str().ok())
+        .ok_or(AppError::Unauthenticated)?;
+
+    if !verify_webhook_signature(&secret.0, &body, signature) {
+        return Err(AppError::Unauthenticated.into());
+    }
+
+    let event = parse_event(&body).map_err(|error| AppError::Validation(error.to_string()))?;
+
+    if let Change::Ping(validation_code) = &event.change {
+        let validation_code = validation_code.clone();
+        return Ok(Json(Validation { validation_code }).into_response());
+    }
+
+    // What's stored is kept for each Toggl user, so there's nowhere to put an event from no one
+    if let Some(subscriber) = event.subscriber {
+        apply_change(subscriber, &event.change, &pool).await?;
+    }
+
+    Ok(StatusCode::OK.into_response())
+}
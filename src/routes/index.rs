@@ -1,27 +1,87 @@
 use crate::{
-    errors::InternalResult,
+    crypto::ServerKey,
+    csrf::CsrfToken,
+    errors::{AppError, AppResult, TogglOutage},
+    last_status::calculate_goals_or_last,
+    plan::{self, plan},
     session::UserKey,
-    toggl::{self, calculate_goals},
+    toggl::{self, get_toggl_project_colors, LimitPeriod, LimitUsage, ProjectKey},
 };
 use askama::Template;
 use axum::{
     debug_handler,
-    extract::Extension,
+    extract::{Extension, Query},
     response::{Html, IntoResponse, Redirect},
 };
-use chrono::Duration;
+use axum_extra::extract::WithRejection;
+use chrono::{Duration, NaiveDate};
+use chrono_tz::Tz;
 use reqwest::Client;
+use serde::Deserialize;
 use sqlx::PgPool;
 
-use crate::human_duration::HumanDuration;
+use crate::human_duration::{hours_minutes_seconds, HumanDuration};
 
 pub struct Goal {
+    pub project_key: ProjectKey,
     pub name: String,
     pub time: HumanDuration,
+    pub share_today: HumanDuration,
+    pub deadline: Option<NaiveDate>,
+    pub projected_finish: Option<NaiveDate>,
+    pub limit: Option<Limit>,
+    /// The color of the first Toggl project it counts, which its card is marked with
+    pub color: Option<String>,
+}
+
+/// How much of a limit is left, or how far over it the user is
+pub struct Limit {
+    pub time: HumanDuration,
+    pub period: LimitPeriod,
+    pub remaining: HumanDuration,
+    pub exceeded: bool,
+}
+
+impl From<LimitUsage> for Limit {
+    fn from(other: LimitUsage) -> Self {
+        Self {
+            time: HumanDuration(other.limit.time),
+            period: other.limit.period,
+            remaining: HumanDuration(other.remaining().abs()),
+            exceeded: other.exceeded(),
+        }
+    }
 }
 
 impl From<toggl::Goal> for Goal {
     fn from(other: toggl::Goal) -> Self {
+        Self {
+            project_key: other.project_key,
+            name: other.name,
+            time: HumanDuration(other.time),
+            share_today: HumanDuration(other.share_today),
+            deadline: other.deadline.map(|deadline| deadline.date),
+            projected_finish: other.projected_finish,
+            limit: other.limit.map(Limit::from),
+            color: None,
+        }
+    }
+}
+
+/// Shown in place of a current status when Toggl can't be reached
+pub struct Stale {
+    /// When the status shown was calculated, in the user's timezone
+    pub as_of: String,
+    pub outage: TogglOutage,
+}
+
+pub struct Allocation {
+    pub name: String,
+    pub time: HumanDuration,
+}
+
+impl From<plan::Allocation> for Allocation {
+    fn from(other: plan::Allocation) -> Self {
         Self {
             name: other.name,
             time: HumanDuration(other.time),
@@ -36,16 +96,31 @@ pub struct Index {
     pub daily_max: HumanDuration,
     pub percentage: i64,
     pub goals: Vec<Goal>,
+    pub allocations: Vec<Allocation>,
+    pub stale: Option<Stale>,
+    pub available_hours: i64,
+    pub available_minutes: i64,
+    pub csrf_token: String,
+}
+
+/// How much time the user has left today, which defaults to their daily max
+#[derive(Deserialize)]
+pub struct PlanQuery {
+    available_hours: Option<i64>,
+    available_minutes: Option<i64>,
 }
 
 #[debug_handler]
 pub async fn get(
     user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
     Extension(pool): Extension<PgPool>,
     Extension(client): Extension<Client>,
-) -> InternalResult<impl IntoResponse> {
+    Extension(server_key): Extension<ServerKey>,
+    WithRejection(Query(query), _): WithRejection<Query<PlanQuery>, AppError>,
+) -> AppResult<impl IntoResponse> {
     let record = sqlx::query!(
-        "SELECT daily_max FROM users WHERE user_key = $1",
+        "SELECT daily_max, timezone FROM users WHERE user_key = $1",
         user_key.0
     )
     .fetch_one(&pool)
@@ -53,12 +128,71 @@ pub async fn get(
 
     let daily_max = Duration::seconds(record.daily_max);
 
-    if let Some((goals, total_debt)) = calculate_goals(user_key, pool, client).await? {
+    let available = match (query.available_hours, query.available_minutes) {
+        (None, None) => daily_max,
+        (hours, minutes) => Duration::try_hours(hours.unwrap_or(0))
+            .zip(Duration::try_minutes(minutes.unwrap_or(0)))
+            .and_then(|(hours, minutes)| hours.checked_add(&minutes))
+            .ok_or_else(|| AppError::Validation("The time you have left is too long".to_owned()))?,
+    };
+
+    if available < Duration::zero() {
+        return Err(AppError::Validation(
+            "The time you have left can't be negative".to_owned(),
+        ));
+    }
+
+    let (status, staleness) = calculate_goals_or_last(user_key, &pool, client, server_key).await?;
+
+    if let Some(status) = status {
+        let tz: Tz = record
+            .timezone
+            .parse()
+            .map_err(AppError::UnrecognizedTimezone)?;
+
+        let colors = get_toggl_project_colors(user_key, &pool).await?;
+
+        let (available_hours, available_minutes, _) = hours_minutes_seconds(available);
+
         let template = Index {
-            total_debt: HumanDuration(total_debt),
+            total_debt: HumanDuration(status.total_debt),
             daily_max: HumanDuration(daily_max),
-            percentage: 100 - (total_debt.num_seconds() * 100 / daily_max.num_seconds()),
-            goals: goals.into_iter().map(|g| g.into()).collect(),
+            // The account page allows a daily limit of zero, which leaves no debt to show
+            percentage: 100
+                - (status.total_debt.num_seconds() * 100)
+                    .checked_div(daily_max.num_seconds())
+                    .unwrap_or(0),
+            allocations: plan(&status.goals, available, daily_max)
+                .into_iter()
+                .map(Allocation::from)
+                .collect(),
+            stale: staleness.map(|staleness| Stale {
+                as_of: staleness
+                    .as_of
+                    .with_timezone(&tz)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                outage: staleness.outage,
+            }),
+            available_hours,
+            available_minutes,
+            goals: status
+                .goals
+                .into_iter()
+                .map(|goal| {
+                    let color = goal
+                        .project_ids
+                        .first()
+                        .and_then(|project_id| colors.get(project_id))
+                        .cloned();
+
+                    Goal {
+                        color,
+                        ..goal.into()
+                    }
+                })
+                .collect(),
+            csrf_token,
         };
 
         Ok(Html(template.render()?).into_response())
@@ -6,29 +6,25 @@ use axum::{
     response::{Html, IntoResponse, Redirect},
     Extension,
 };
-use chrono_tz::TZ_VARIANTS;
-use reqwest::Client;
 use serde::Deserialize;
 use sqlx::PgPool;
 
 use crate::{
-    errors::InternalResult,
-    session::{new_session_cookie_header, UserKey},
-    toggl::get_workspaces,
+    crypto::verify_password,
+    errors::AppResult,
+    session::{new_session_cookie_header, ClientInfo, UserKey},
 };
 
-use super::signup::SignupTemplate;
-
 #[derive(Template)]
 #[template(path = "login.html")]
 pub struct LoginTemplate {
-    unrecognized_api_token: bool,
+    unrecognized_credentials: bool,
 }
 
 #[debug_handler]
-pub async fn get() -> InternalResult<impl IntoResponse> {
+pub async fn get() -> AppResult<impl IntoResponse> {
     let template = LoginTemplate {
-        unrecognized_api_token: false,
+        unrecognized_credentials: false,
     };
 
     Ok(Html(template.render()?))
@@ -36,46 +32,63 @@ pub async fn get() -> InternalResult<impl IntoResponse> {
 
 #[derive(Deserialize)]
 pub struct LoginForm {
-    toggl_api_key: String,
+    username: String,
+    password: String,
 }
 
 #[debug_handler]
 pub async fn post(
+    client_info: ClientInfo,
     Extension(pool): Extension<PgPool>,
-    Extension(client): Extension<Client>,
     Form(form): Form<LoginForm>,
-) -> InternalResult<impl IntoResponse> {
-    let user = sqlx::query!(
-        "SELECT user_key
-        FROM users
-        WHERE toggl_api_key = $1",
-        form.toggl_api_key
-    )
-    .fetch_optional(&pool)
-    .await?;
+) -> AppResult<impl IntoResponse> {
+    let user_key = find_user(&form.username, &form.password, &pool).await?;
 
-    Ok(if let Some(user) = user {
-        // User already exists: Log them in
+    Ok(if let Some(user_key) = user_key {
+        // The credentials are correct: Log them in
         (
-            [new_session_cookie_header(UserKey(user.user_key), &pool).await?],
+            [new_session_cookie_header(user_key, &client_info, &pool).await?],
             Redirect::to("/"),
         )
             .into_response()
-    } else if let Some(workspaces) = get_workspaces(&form.toggl_api_key, client).await? {
-        // User doesn't exist, but the API token works: Sign them up
-        let template = SignupTemplate {
-            toggl_api_key: &form.toggl_api_key,
-            workspaces: &workspaces,
-            timezones: &TZ_VARIANTS,
-        };
-
-        Html(template.render()?).into_response()
     } else {
-        // User doesn't exist and the API token works: Respond with an error message
+        // The credentials are wrong: Respond with an error message
         let template = LoginTemplate {
-            unrecognized_api_token: true,
+            unrecognized_credentials: true,
         };
 
         (StatusCode::BAD_REQUEST, Html(template.render()?)).into_response()
     })
 }
+
+/// Look up the user with the given username and password
+pub async fn find_user(
+    username: &str,
+    password: &str,
+    pool: &PgPool,
+) -> AppResult<Option<UserKey>> {
+    let user = sqlx::query!(
+        r#"SELECT user_key, password_hash AS "password_hash!"
+        FROM users
+        WHERE username = $1
+        AND password_hash IS NOT NULL"#,
+        normalize_username(username),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user
+        .filter(|user| verify_password(password, &user.password_hash))
+        .map(|user| UserKey(user.user_key)))
+}
+
+pub fn normalize_username(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+/// Usernames can't be blank, and passwords have to be at least 8 characters long
+pub fn valid_credentials(username: &str, password: &str) -> bool {
+    let username = normalize_username(username);
+
+    !username.is_empty() && username.len() <= 254 && password.chars().count() >= 8
+}
@@ -0,0 +1,102 @@
+use askama::Template;
+use axum::{
+    debug_handler,
+    extract::Form,
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect},
+    Extension,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    crypto::{hash_password, ServerKey},
+    errors::AppResult,
+    session::{new_session_cookie_header, ClientInfo, UserKey},
+};
+
+use super::login::{normalize_username, valid_credentials};
+
+#[derive(Template)]
+#[template(path = "login_legacy.html")]
+pub struct LegacyLoginTemplate {
+    unrecognized_api_token: bool,
+    invalid_credentials: bool,
+    username_taken: bool,
+}
+
+#[debug_handler]
+pub async fn get() -> AppResult<impl IntoResponse> {
+    let template = LegacyLoginTemplate {
+        unrecognized_api_token: false,
+        invalid_credentials: false,
+        username_taken: false,
+    };
+
+    Ok(Html(template.render()?))
+}
+
+#[derive(Deserialize)]
+pub struct LegacyLoginForm {
+    toggl_api_key: String,
+    username: String,
+    password: String,
+}
+
+/// Let a user who signed up with only their Toggl API token claim their account by choosing a
+/// username and password
+#[debug_handler]
+pub async fn post(
+    client_info: ClientInfo,
+    Extension(pool): Extension<PgPool>,
+    Extension(server_key): Extension<ServerKey>,
+    Form(form): Form<LegacyLoginForm>,
+) -> AppResult<impl IntoResponse> {
+    let user = sqlx::query!(
+        "SELECT user_key
+        FROM users
+        WHERE toggl_api_key_digest = $1
+        AND password_hash IS NULL",
+        server_key.digest(form.toggl_api_key.trim()),
+    )
+    .fetch_optional(&pool)
+    .await?;
+
+    let username = normalize_username(&form.username);
+    let valid_credentials = valid_credentials(&form.username, &form.password);
+
+    let username_taken = sqlx::query!("SELECT user_key FROM users WHERE username = $1", username)
+        .fetch_optional(&pool)
+        .await?
+        .is_some();
+
+    Ok(match user {
+        Some(user) if valid_credentials && !username_taken => {
+            let user_key = UserKey(user.user_key);
+
+            sqlx::query!(
+                "UPDATE users SET username = $1, password_hash = $2 WHERE user_key = $3",
+                username,
+                hash_password(&form.password)?,
+                user_key.0,
+            )
+            .execute(&pool)
+            .await?;
+
+            (
+                [new_session_cookie_header(user_key, &client_info, &pool).await?],
+                Redirect::to("/"),
+            )
+                .into_response()
+        }
+        user => {
+            let template = LegacyLoginTemplate {
+                unrecognized_api_token: user.is_none(),
+                invalid_credentials: !valid_credentials,
+                username_taken,
+            };
+
+            (StatusCode::BAD_REQUEST, Html(template.render()?)).into_response()
+        }
+    })
+}
@@ -0,0 +1,31 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{IntoResponse, Redirect},
+};
+use axum_extra::extract::CookieJar;
+use sqlx::PgPool;
+
+use crate::{
+    csrf::{CsrfForm, NoFields},
+    errors::AppResult,
+    session::{session_cookie_header, SESSION_COOKIE_NAME},
+};
+
+#[debug_handler]
+pub async fn post(
+    cookie_jar: CookieJar,
+    Extension(pool): Extension<PgPool>,
+    CsrfForm(NoFields {}): CsrfForm<NoFields>,
+) -> AppResult<impl IntoResponse> {
+    if let Some(cookie) = cookie_jar.get(SESSION_COOKIE_NAME) {
+        sqlx::query!(
+            "DELETE FROM session_tokens WHERE token = $1",
+            cookie.value(),
+        )
+        .execute(&pool)
+        .await?;
+    }
+
+    Ok(([session_cookie_header("", 0)?], Redirect::to("/login/")))
+}
@@ -0,0 +1,33 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{IntoResponse, Redirect},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{csrf::CsrfForm, errors::AppResult, session::UserKey};
+
+#[derive(Deserialize)]
+pub struct NotificationDeleteForm {
+    notification_key: i64,
+}
+
+#[debug_handler]
+pub async fn post(
+    user_key: UserKey,
+    Extension(pool): Extension<PgPool>,
+    CsrfForm(form): CsrfForm<NotificationDeleteForm>,
+) -> AppResult<impl IntoResponse> {
+    sqlx::query!(
+        "DELETE FROM notifications
+        WHERE notification_key = $1
+        AND user_key = $2",
+        form.notification_key,
+        user_key.0,
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(Redirect::to("/account/"))
+}
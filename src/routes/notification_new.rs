@@ -0,0 +1,121 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect},
+};
+use chrono::{NaiveTime, Utc};
+use chrono_tz::Tz;
+use reqwest::Client;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    crypto::ServerKey,
+    csrf::{CsrfForm, CsrfToken},
+    errors::{AppError, AppResult},
+    notifications::{is_due, validate_destination, Channel, Mailer},
+    session::UserKey,
+    validation::FieldErrors,
+};
+
+use super::account::{render, AccountForms};
+
+mod tests;
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationNewForm {
+    pub local_time: NaiveTime,
+    pub channel: String,
+    pub destination: String,
+}
+
+/// An empty form, which sends an email first thing in the morning
+impl Default for NotificationNewForm {
+    fn default() -> Self {
+        Self {
+            local_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            channel: Channel::Email.to_string(),
+            destination: String::new(),
+        }
+    }
+}
+
+fn validate(form: &NotificationNewForm, can_send_email: bool) -> FieldErrors {
+    let mut errors = FieldErrors::new();
+
+    match form.channel.parse::<Channel>() {
+        Ok(channel) => {
+            errors.require(
+                channel != Channel::Email || can_send_email,
+                "channel",
+                "This server isn't set up to send email",
+            );
+            errors.merge(validate_destination(channel, form.destination.trim()));
+        }
+        Err(_) => errors.add("channel", "Choose email or a webhook"),
+    }
+
+    errors
+}
+
+#[debug_handler]
+pub async fn post(
+    user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    Extension(mailer): Extension<Option<Mailer>>,
+    CsrfForm(form): CsrfForm<NotificationNewForm>,
+) -> AppResult<impl IntoResponse> {
+    let errors = validate(&form, mailer.is_some());
+
+    let channel: Channel = match form.channel.parse() {
+        Ok(channel) if errors.is_empty() => channel,
+        _ => {
+            // Show the account page again with the user's input and what's wrong with it
+            let body = render(
+                user_key,
+                &pool,
+                client,
+                &server_key,
+                &csrf_token,
+                &AccountForms {
+                    notification_form: form,
+                    notification_errors: errors,
+                    ..AccountForms::default()
+                },
+            )
+            .await?;
+
+            return Ok((StatusCode::BAD_REQUEST, Html(body)).into_response());
+        }
+    };
+
+    let record = sqlx::query!("SELECT timezone FROM users WHERE user_key = $1", user_key.0)
+        .fetch_one(&pool)
+        .await?;
+    let timezone: Tz = record
+        .timezone
+        .parse()
+        .map_err(AppError::UnrecognizedTimezone)?;
+    let now = Utc::now().with_timezone(&timezone).naive_local();
+
+    // A time that has already passed today starts tomorrow, rather than going out right away
+    let last_sent = is_due(form.local_time, None, now).then_some(now.date());
+
+    sqlx::query!(
+        "INSERT INTO notifications(user_key, local_time, channel, destination, last_sent)
+        VALUES ($1, $2, $3, $4, $5)",
+        user_key.0,
+        form.local_time,
+        channel.to_string(),
+        form.destination.trim(),
+        last_sent,
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(Redirect::to("/account/").into_response())
+}
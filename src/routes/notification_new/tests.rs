@@ -0,0 +1,31 @@
+#![cfg(test)]
+
+use super::*;
+
+fn example_form() -> NotificationNewForm {
+    NotificationNewForm {
+        destination: " you@example.com ".to_owned(),
+        ..NotificationNewForm::default()
+    }
+}
+
+#[test]
+fn test_validate_new_notification() {
+    assert!(validate(&example_form(), true).is_empty());
+
+    // Email can only be chosen if the server can send it
+    assert!(validate(&example_form(), false).has("channel"));
+
+    let unknown_channel = NotificationNewForm {
+        channel: "carrier pigeon".to_owned(),
+        ..example_form()
+    };
+    assert!(validate(&unknown_channel, true).has("channel"));
+
+    let private_webhook = NotificationNewForm {
+        channel: Channel::Webhook.to_string(),
+        destination: "http://10.0.0.1/hook".to_owned(),
+        ..example_form()
+    };
+    assert!(validate(&private_webhook, true).has("destination"));
+}
@@ -0,0 +1,61 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{IntoResponse, Redirect},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    csrf::CsrfForm,
+    errors::{AppError, AppResult},
+    session::UserKey,
+    toggl::ProjectKey,
+};
+
+mod tests;
+
+#[derive(Deserialize)]
+pub struct ProjectArchiveForm {
+    project_key: ProjectKey,
+    /// False to bring an archived project back
+    archived: bool,
+}
+
+#[debug_handler]
+pub async fn post(
+    user_key: UserKey,
+    Extension(pool): Extension<PgPool>,
+    CsrfForm(form): CsrfForm<ProjectArchiveForm>,
+) -> AppResult<impl IntoResponse> {
+    set_project_archived(user_key, form.project_key, form.archived, &pool).await?;
+
+    Ok(Redirect::to("/projects/"))
+}
+
+/// Archive one of the user's projects, or bring it back
+async fn set_project_archived(
+    user_key: UserKey,
+    project_key: ProjectKey,
+    archived: bool,
+    pool: &PgPool,
+) -> AppResult<()> {
+    let updated = sqlx::query!(
+        "UPDATE projects
+        SET archived = $3
+        WHERE project_key = $1
+        AND user_key = $2",
+        project_key.0,
+        user_key.0,
+        archived,
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    if updated == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+#![cfg(test)]
+
+use super::*;
+
+async fn insert_user_with_project(username: &str, pool: &PgPool) -> (UserKey, ProjectKey) {
+    let user_key = sqlx::query!(
+        "INSERT INTO users(username, daily_max, timezone)
+        VALUES ($1, 0, 'UTC')
+        RETURNING user_key",
+        username,
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+    .user_key;
+
+    let project_key = sqlx::query!(
+        "INSERT INTO projects(user_key, starting_date, daily_goal,
+            monday, tuesday, wednesday, thursday, friday, saturday, sunday)
+        VALUES ($1, '2026-10-18', 3600, TRUE, TRUE, TRUE, TRUE, TRUE, FALSE, FALSE)
+        RETURNING project_key",
+        user_key,
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+    .project_key;
+
+    (UserKey(user_key), ProjectKey(project_key))
+}
+
+async fn is_archived(project_key: ProjectKey, pool: &PgPool) -> bool {
+    sqlx::query!(
+        "SELECT archived FROM projects WHERE project_key = $1",
+        project_key.0,
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+    .archived
+}
+
+#[sqlx::test]
+async fn test_set_project_archived(pool: PgPool) {
+    let (user_key, project_key) = insert_user_with_project("someone", &pool).await;
+    let (_, other_project_key) = insert_user_with_project("someone else", &pool).await;
+
+    set_project_archived(user_key, project_key, true, &pool)
+        .await
+        .unwrap();
+    assert!(is_archived(project_key, &pool).await);
+
+    set_project_archived(user_key, project_key, false, &pool)
+        .await
+        .unwrap();
+    assert!(!is_archived(project_key, &pool).await);
+
+    // Someone else's project is left alone, as if it didn't exist
+    assert!(matches!(
+        set_project_archived(user_key, other_project_key, true, &pool).await,
+        Err(AppError::NotFound)
+    ));
+    assert!(!is_archived(other_project_key, &pool).await);
+}
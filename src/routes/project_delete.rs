@@ -1,29 +1,29 @@
 use axum::{
     debug_handler,
-    extract::{Extension, Form},
+    extract::Extension,
     response::{IntoResponse, Redirect},
 };
 use serde::Deserialize;
 use sqlx::PgPool;
 
-use crate::{errors::InternalResult, session::UserKey, toggl::ProjectId};
+use crate::{csrf::CsrfForm, errors::AppResult, session::UserKey, toggl::ProjectKey};
 
 #[derive(Deserialize)]
 pub struct ProjectDeleteForm {
-    project_id: ProjectId,
+    project_key: ProjectKey,
 }
 
 #[debug_handler]
 pub async fn post(
     user_key: UserKey,
     Extension(pool): Extension<PgPool>,
-    Form(form): Form<ProjectDeleteForm>,
-) -> InternalResult<impl IntoResponse> {
+    CsrfForm(form): CsrfForm<ProjectDeleteForm>,
+) -> AppResult<impl IntoResponse> {
     sqlx::query!(
         "DELETE FROM projects
-        WHERE project_id = $1
+        WHERE project_key = $1
         AND user_key = $2",
-        form.project_id.0,
+        form.project_key.0,
         user_key.0,
     )
     .execute(&pool)
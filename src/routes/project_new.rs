@@ -1,74 +1,156 @@
 use askama::Template;
 use axum::{
     debug_handler,
-    extract::{Extension, Form},
+    extract::Extension,
+    http::StatusCode,
     response::{Html, IntoResponse, Redirect},
 };
 use chrono::{Duration, NaiveDate};
+use futures::future;
 use reqwest::Client;
 use serde::Deserialize;
 use sqlx::PgPool;
 
 use crate::{
-    errors::InternalResult,
+    crypto::ServerKey,
+    csrf::{CsrfForm, CsrfToken},
+    errors::{AppError, AppResult},
+    project_settings::{allowed_project_ids, insert_project, ProjectSettings},
     session::UserKey,
-    toggl::{get_toggl_projects, ProjectId, TogglProject, WorkspaceId},
+    toggl::{
+        get_all_toggl_projects_including_archived, get_toggl_clients, get_toggl_projects,
+        get_toggl_tags, get_user_today, get_user_toggl_api_token, get_user_workspace_ids,
+        get_workspaces, Deadline, Limit, LimitPeriod, ProjectId, TogglProject, WhichWeekdays,
+        WorkspaceId,
+    },
+    validation::FieldErrors,
 };
 
+mod tests;
+
+/// The most hours, minutes or seconds a form field takes, so that adding them up can't overflow
+const MAX_FIELD_VALUE: i64 = 1_000_000;
+
+struct Workspace {
+    pub name: String,
+    pub projects: Vec<TogglProject>,
+}
+
 #[derive(Template)]
 #[template(path = "project_new.html")]
 pub struct NewProjectTemplate<'a> {
-    projects: &'a [TogglProject],
+    workspaces: &'a [Workspace],
+    clients: &'a [String],
+    tags: &'a [String],
+    form: &'a NewProjectForm,
+    errors: &'a FieldErrors,
+    csrf_token: &'a str,
 }
 
 #[debug_handler]
 pub async fn get(
     user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
     Extension(pool): Extension<PgPool>,
     Extension(client): Extension<Client>,
-) -> InternalResult<impl IntoResponse> {
-    let record = sqlx::query!(
-        "SELECT toggl_api_key, workspace_id FROM users WHERE user_key = $1",
-        user_key.0,
-    )
-    .fetch_one(&pool)
-    .await?;
-
-    let toggl_projects = get_toggl_projects(
-        &record.toggl_api_key,
-        WorkspaceId(record.workspace_id),
-        &client,
-    )
-    .await?;
-
-    let records = sqlx::query!(
-        "SELECT project_id FROM projects WHERE user_key = $1",
-        user_key.0,
-    )
-    .fetch_all(&pool)
-    .await?;
-
-    // Remove Toggl projects that the user has already created projects for
-    let toggl_projects_not_yet_created: Vec<TogglProject> = toggl_projects
-        .into_iter()
-        .filter(|toggl_project| {
-            (&records)
-                .into_iter()
-                .find(|user_project| toggl_project.id == ProjectId(user_project.project_id))
-                .is_none()
-        })
-        .collect();
+    Extension(server_key): Extension<ServerKey>,
+) -> AppResult<impl IntoResponse> {
+    let toggl_api_key = get_user_toggl_api_token(user_key, &pool, &server_key).await?;
+    let workspace_ids = get_user_workspace_ids(user_key, &pool).await?;
+    let today = get_user_today(user_key, &pool).await?;
+
+    Ok(Html(
+        render(
+            &toggl_api_key,
+            &workspace_ids,
+            &client,
+            &NewProjectForm::new(today),
+            &FieldErrors::new(),
+            &csrf_token,
+        )
+        .await?,
+    ))
+}
+
+async fn render(
+    toggl_api_key: &str,
+    workspace_ids: &[WorkspaceId],
+    client: &Client,
+    form: &NewProjectForm,
+    errors: &FieldErrors,
+    csrf_token: &str,
+) -> AppResult<String> {
+    let (all_workspaces, workspace_data) = futures::try_join!(
+        get_workspaces(toggl_api_key, client.clone()),
+        future::try_join_all(workspace_ids.iter().map(|&workspace_id| async move {
+            futures::try_join!(
+                get_toggl_projects(toggl_api_key, workspace_id, client),
+                get_toggl_clients(toggl_api_key, workspace_id, client),
+                get_toggl_tags(toggl_api_key, workspace_id, client),
+            )
+        })),
+    )?;
+
+    let all_workspaces = all_workspaces.unwrap_or_default();
+
+    let mut workspaces = Vec::new();
+    let mut clients = Vec::new();
+    let mut tags = Vec::new();
+    for (&workspace_id, (toggl_projects, toggl_clients, toggl_tags)) in
+        workspace_ids.iter().zip(workspace_data)
+    {
+        let name = all_workspaces
+            .iter()
+            .find(|workspace| workspace.id == workspace_id)
+            .map(|workspace| workspace.name.clone())
+            .unwrap_or_else(|| format!("Workspace {}", workspace_id));
+
+        workspaces.push(Workspace {
+            name,
+            projects: toggl_projects,
+        });
+        clients.extend(toggl_clients.into_iter().map(|client| client.name));
+        tags.extend(toggl_tags.into_iter().map(|tag| tag.name));
+    }
+
+    // Clients and tags are matched by name, so the same name in two workspaces is one option
+    clients.sort();
+    clients.dedup();
+    tags.sort();
+    tags.dedup();
 
     let template = NewProjectTemplate {
-        projects: &toggl_projects_not_yet_created,
+        workspaces: &workspaces,
+        clients: &clients,
+        tags: &tags,
+        form,
+        errors,
+        csrf_token,
     };
 
-    Ok(Html(template.render()?))
+    Ok(template.render()?)
 }
 
 #[derive(Debug, Deserialize)]
 pub struct NewProjectForm {
-    project_id: ProjectId,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    project_ids: Vec<ProjectId>,
+    #[serde(default)]
+    clients: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    no_project: Option<String>,
+    #[serde(default)]
+    description_substrings: String,
+    #[serde(default)]
+    description_regexes: String,
+    billable_only: Option<String>,
+    #[serde(default)]
+    required_tags: Vec<String>,
+    #[serde(default)]
+    excluded_tags: Vec<String>,
     starting_date: NaiveDate,
     daily_goal_hours: i64,
     daily_goal_minutes: i64,
@@ -80,36 +162,279 @@ pub struct NewProjectForm {
     friday: Option<String>,
     saturday: Option<String>,
     sunday: Option<String>,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default = "default_weight")]
+    weight: i32,
+    #[serde(default)]
+    goal_type: GoalType,
+    deadline: Option<NaiveDate>,
+    #[serde(default)]
+    target_total_hours: i64,
+    #[serde(default)]
+    limit_hours: i64,
+    #[serde(default)]
+    limit_minutes: i64,
+    limit_period: Option<LimitPeriod>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum GoalType {
+    #[default]
+    Daily,
+    Deadline,
+    Limit,
+}
+
+fn default_weight() -> i32 {
+    1
+}
+
+impl NewProjectForm {
+    /// The form as it's first shown: Starting today, every day of the week
+    fn new(today: NaiveDate) -> Self {
+        let checked = || Some(String::new());
+
+        Self {
+            name: String::new(),
+            project_ids: Vec::new(),
+            clients: Vec::new(),
+            tags: Vec::new(),
+            no_project: None,
+            description_substrings: String::new(),
+            description_regexes: String::new(),
+            billable_only: None,
+            required_tags: Vec::new(),
+            excluded_tags: Vec::new(),
+            starting_date: today,
+            daily_goal_hours: 0,
+            daily_goal_minutes: 0,
+            daily_goal_seconds: 0,
+            monday: checked(),
+            tuesday: checked(),
+            wednesday: checked(),
+            thursday: checked(),
+            friday: checked(),
+            saturday: checked(),
+            sunday: checked(),
+            priority: 0,
+            weight: default_weight(),
+            goal_type: GoalType::Daily,
+            deadline: None,
+            target_total_hours: 0,
+            limit_hours: 0,
+            limit_minutes: 0,
+            limit_period: None,
+        }
+    }
+
+    fn has_project_id(&self, project_id: ProjectId) -> bool {
+        self.project_ids.contains(&project_id)
+    }
+
+    fn has_deadline(&self) -> bool {
+        self.goal_type == GoalType::Deadline
+    }
+
+    fn has_limit(&self) -> bool {
+        self.goal_type == GoalType::Limit
+    }
+
+    fn has_weekly_limit(&self) -> bool {
+        self.limit_period == Some(LimitPeriod::Week)
+    }
+
+    /// Turn the form into settings, or record an error for each duration that's too long to be one
+    fn settings(&self) -> Result<ProjectSettings, FieldErrors> {
+        let mut errors = FieldErrors::new();
+
+        let settings = ProjectSettings {
+            name: Some(self.name.trim())
+                .filter(|name| !name.is_empty())
+                .map(String::from),
+            project_ids: self.project_ids.clone(),
+            clients: self.clients.clone(),
+            tags: self.tags.clone(),
+            no_project: self.no_project.is_some(),
+            description_substrings: non_empty_lines(&self.description_substrings),
+            description_regexes: non_empty_lines(&self.description_regexes),
+            billable_only: self.billable_only.is_some(),
+            required_tags: self.required_tags.clone(),
+            excluded_tags: self.excluded_tags.clone(),
+            starting_date: self.starting_date,
+            daily_goal: duration(
+                self.daily_goal_hours,
+                self.daily_goal_minutes,
+                self.daily_goal_seconds,
+                &mut errors,
+                "daily_goal",
+            ),
+            weekdays: WhichWeekdays {
+                monday: self.monday.is_some(),
+                tuesday: self.tuesday.is_some(),
+                wednesday: self.wednesday.is_some(),
+                thursday: self.thursday.is_some(),
+                friday: self.friday.is_some(),
+                saturday: self.saturday.is_some(),
+                sunday: self.sunday.is_some(),
+            },
+            priority: self.priority,
+            weight: self.weight,
+            // A missing deadline is reported by the form's own check
+            deadline: self.has_deadline().then(|| Deadline {
+                date: self.deadline.unwrap_or(self.starting_date),
+                total: duration(self.target_total_hours, 0, 0, &mut errors, "target_total"),
+            }),
+            limit: self.has_limit().then(|| Limit {
+                time: duration(
+                    self.limit_hours,
+                    self.limit_minutes,
+                    0,
+                    &mut errors,
+                    "limit",
+                ),
+                period: self.limit_period.unwrap_or(LimitPeriod::Day),
+            }),
+        };
+
+        if errors.is_empty() {
+            Ok(settings)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Add up hours, minutes and seconds, or record an error if they're too long to be a duration
+fn duration(
+    hours: i64,
+    minutes: i64,
+    seconds: i64,
+    errors: &mut FieldErrors,
+    field: &'static str,
+) -> Duration {
+    Duration::try_hours(hours)
+        .zip(Duration::try_minutes(minutes))
+        .zip(Duration::try_seconds(seconds))
+        .and_then(|((hours, minutes), seconds)| hours.checked_add(&minutes)?.checked_add(&seconds))
+        .unwrap_or_else(|| {
+            errors.add(field, "That's too long");
+            Duration::zero()
+        })
+}
+
+/// Check a new project against the Toggl projects the user can choose from and today's date
+fn validate(
+    form: &NewProjectForm,
+    available_project_ids: &[ProjectId],
+    today: NaiveDate,
+) -> FieldErrors {
+    let mut errors = FieldErrors::new();
+
+    let daily_goal = [
+        form.daily_goal_hours,
+        form.daily_goal_minutes,
+        form.daily_goal_seconds,
+    ];
+    let target_total = [form.target_total_hours];
+    let limit = [form.limit_hours, form.limit_minutes];
+
+    let non_negative = |values: &[i64]| values.iter().all(|&value| value >= 0);
+    let small_enough = |values: &[i64]| values.iter().all(|&value| value <= MAX_FIELD_VALUE);
+
+    errors.require(
+        non_negative(&daily_goal),
+        "daily_goal",
+        "Hours, minutes and seconds can't be negative",
+    );
+    errors.require(
+        non_negative(&target_total),
+        "target_total",
+        "Hours can't be negative",
+    );
+    errors.require(
+        non_negative(&limit),
+        "limit",
+        "Hours and minutes can't be negative",
+    );
+
+    errors.require(small_enough(&daily_goal), "daily_goal", "That's too long");
+    errors.require(
+        small_enough(&target_total),
+        "target_total",
+        "That's too long",
+    );
+    errors.require(small_enough(&limit), "limit", "That's too long");
+
+    errors.require(
+        !form.has_deadline() || form.deadline.is_some(),
+        "deadline",
+        "Choose a date to reach the target by",
+    );
+
+    // The settings are only worth checking once every duration is in range
+    let in_range = |values: &[i64]| non_negative(values) && small_enough(values);
+    if in_range(&daily_goal) && in_range(&target_total) && in_range(&limit) {
+        match form.settings() {
+            Ok(settings) => errors.merge(settings.validate(available_project_ids, today)),
+            Err(settings_errors) => errors.merge(settings_errors),
+        }
+    }
+
+    errors
 }
 
 #[debug_handler]
 pub async fn post(
     user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
     Extension(pool): Extension<PgPool>,
-    Form(form): Form<NewProjectForm>,
-) -> InternalResult<impl IntoResponse> {
-    let daily_goal = Duration::hours(form.daily_goal_hours)
-        + Duration::minutes(form.daily_goal_minutes)
-        + Duration::seconds(form.daily_goal_seconds);
-
-    sqlx::query!(
-        "INSERT INTO projects(user_key, project_id, starting_date, daily_goal,
-            monday, tuesday, wednesday, thursday, friday, saturday, sunday)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
-        user_key.0,
-        form.project_id.0,
-        form.starting_date,
-        daily_goal.num_seconds(),
-        form.monday.is_some(),
-        form.tuesday.is_some(),
-        form.wednesday.is_some(),
-        form.thursday.is_some(),
-        form.friday.is_some(),
-        form.saturday.is_some(),
-        form.sunday.is_some(),
-    )
-    .execute(&pool)
-    .await?;
-
-    Ok(Redirect::to("/"))
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    CsrfForm(form): CsrfForm<NewProjectForm>,
+) -> AppResult<impl IntoResponse> {
+    let toggl_api_key = get_user_toggl_api_token(user_key, &pool, &server_key).await?;
+    let workspace_ids = get_user_workspace_ids(user_key, &pool).await?;
+    let today = get_user_today(user_key, &pool).await?;
+
+    // A new project doesn't count any Toggl projects yet, so only active ones can be chosen
+    let toggl_projects =
+        get_all_toggl_projects_including_archived(&toggl_api_key, &workspace_ids, &client).await?;
+    let available_project_ids = allowed_project_ids(&toggl_projects, &[]);
+
+    let errors = validate(&form, &available_project_ids, today);
+
+    if !errors.is_empty() {
+        // Show the form again with the user's input and what's wrong with it
+        let body = render(
+            &toggl_api_key,
+            &workspace_ids,
+            &client,
+            &form,
+            &errors,
+            &csrf_token,
+        )
+        .await?;
+
+        return Ok((StatusCode::BAD_REQUEST, Html(body)).into_response());
+    }
+
+    let settings = form.settings().map_err(AppError::InvalidFields)?;
+
+    let mut transaction = pool.begin().await?;
+
+    insert_project(user_key, &settings, &mut transaction).await?;
+
+    transaction.commit().await?;
+
+    Ok(Redirect::to("/").into_response())
+}
+
+fn non_empty_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
 }
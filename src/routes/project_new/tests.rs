@@ -0,0 +1,141 @@
+#![cfg(test)]
+
+use super::*;
+use crate::toggl::WorkspaceId;
+
+fn example_project_id() -> ProjectId {
+    ProjectId {
+        workspace_id: WorkspaceId(1234567),
+        project_id: 7654321,
+    }
+}
+
+fn example_form(today: NaiveDate) -> NewProjectForm {
+    NewProjectForm {
+        project_ids: Vec::from([example_project_id()]),
+        daily_goal_hours: 1,
+        ..NewProjectForm::new(today)
+    }
+}
+
+#[test]
+fn test_validate_new_project() {
+    let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+    let available_project_ids = [example_project_id()];
+
+    assert!(validate(&example_form(today), &available_project_ids, today).is_empty());
+
+    let nothing_counted = NewProjectForm {
+        project_ids: Vec::new(),
+        ..example_form(today)
+    };
+    assert!(validate(&nothing_counted, &available_project_ids, today).has("filters"));
+
+    let unavailable_project = NewProjectForm {
+        project_ids: Vec::from([ProjectId {
+            workspace_id: WorkspaceId(1),
+            project_id: 2,
+        }]),
+        ..example_form(today)
+    };
+    assert!(validate(&unavailable_project, &available_project_ids, today).has("project_ids"));
+
+    let zero_goal = NewProjectForm {
+        daily_goal_hours: 0,
+        ..example_form(today)
+    };
+    assert!(validate(&zero_goal, &available_project_ids, today).has("daily_goal"));
+
+    let negative_goal = NewProjectForm {
+        daily_goal_hours: 2,
+        daily_goal_minutes: -30,
+        ..example_form(today)
+    };
+    assert!(validate(&negative_goal, &available_project_ids, today).has("daily_goal"));
+
+    let oversized_goal = NewProjectForm {
+        daily_goal_hours: i64::MAX,
+        ..example_form(today)
+    };
+    assert!(validate(&oversized_goal, &available_project_ids, today).has("daily_goal"));
+
+    let oversized_target = NewProjectForm {
+        goal_type: GoalType::Deadline,
+        deadline: Some(today + Duration::days(30)),
+        target_total_hours: i64::MAX,
+        ..example_form(today)
+    };
+    assert!(validate(&oversized_target, &available_project_ids, today).has("target_total"));
+
+    let huge_negative_goal = NewProjectForm {
+        daily_goal_hours: -1_000_000_000_000_000_000,
+        ..example_form(today)
+    };
+    assert!(validate(&huge_negative_goal, &available_project_ids, today).has("daily_goal"));
+
+    let huge_negative_target = NewProjectForm {
+        goal_type: GoalType::Deadline,
+        deadline: Some(today + Duration::days(30)),
+        target_total_hours: -1_000_000_000_000_000_000,
+        ..example_form(today)
+    };
+    assert!(validate(&huge_negative_target, &available_project_ids, today).has("target_total"));
+
+    let huge_negative_limit = NewProjectForm {
+        goal_type: GoalType::Limit,
+        daily_goal_hours: 0,
+        limit_hours: -1_000_000_000_000_000_000,
+        ..example_form(today)
+    };
+    assert!(validate(&huge_negative_limit, &available_project_ids, today).has("limit"));
+
+    let far_future = NewProjectForm {
+        starting_date: NaiveDate::from_ymd_opt(2062, 10, 18).unwrap(),
+        ..example_form(today)
+    };
+    assert!(validate(&far_future, &available_project_ids, today).has("starting_date"));
+
+    let invalid_regex = NewProjectForm {
+        description_regexes: "thesis\n(unclosed".to_owned(),
+        ..example_form(today)
+    };
+    let errors = validate(&invalid_regex, &available_project_ids, today);
+    assert_eq!(
+        errors.get("description_regexes"),
+        Some("/(unclosed/ isn't a valid regular expression"),
+    );
+
+    let no_weekdays = NewProjectForm {
+        monday: None,
+        tuesday: None,
+        wednesday: None,
+        thursday: None,
+        friday: None,
+        saturday: None,
+        sunday: None,
+        ..example_form(today)
+    };
+    assert!(validate(&no_weekdays, &available_project_ids, today).has("weekdays"));
+
+    let weekly_limit = NewProjectForm {
+        goal_type: GoalType::Limit,
+        daily_goal_hours: 0,
+        limit_hours: 5,
+        limit_period: Some(LimitPeriod::Week),
+        ..example_form(today)
+    };
+    assert!(validate(&weekly_limit, &available_project_ids, today).is_empty());
+    assert_eq!(
+        weekly_limit.settings().unwrap().limit,
+        Some(Limit {
+            time: Duration::hours(5),
+            period: LimitPeriod::Week,
+        }),
+    );
+
+    let empty_limit = NewProjectForm {
+        limit_hours: 0,
+        ..weekly_limit
+    };
+    assert!(validate(&empty_limit, &available_project_ids, today).has("limit"));
+}
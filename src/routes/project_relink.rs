@@ -0,0 +1,123 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{IntoResponse, Redirect},
+};
+use reqwest::Client;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    crypto::ServerKey,
+    csrf::CsrfForm,
+    errors::{AppError, AppResult},
+    session::UserKey,
+    toggl::{
+        get_all_toggl_projects, get_user_toggl_api_token, get_user_workspace_ids, ProjectId,
+        ProjectKey, TogglProject,
+    },
+};
+
+mod tests;
+
+#[derive(Deserialize)]
+pub struct ProjectRelinkForm {
+    project_key: ProjectKey,
+    /// The Toggl project to stop counting, which is usually archived or deleted
+    old_project_id: ProjectId,
+    /// The Toggl project to count instead
+    new_project_id: ProjectId,
+}
+
+/// Make a project count a different Toggl project in place of one it counts now
+#[debug_handler]
+pub async fn post(
+    user_key: UserKey,
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    CsrfForm(form): CsrfForm<ProjectRelinkForm>,
+) -> AppResult<impl IntoResponse> {
+    let toggl_api_key = get_user_toggl_api_token(user_key, &pool, &server_key).await?;
+
+    let workspace_ids = get_user_workspace_ids(user_key, &pool).await?;
+
+    let toggl_projects = get_all_toggl_projects(&toggl_api_key, &workspace_ids, &client).await?;
+    let new_project = find_active_toggl_project(&toggl_projects, form.new_project_id)?;
+
+    relink_toggl_project(
+        user_key,
+        form.project_key,
+        form.old_project_id,
+        new_project,
+        &pool,
+    )
+    .await?;
+
+    Ok(Redirect::to("/projects/"))
+}
+
+/// The Toggl project to count instead, which has to be active in one of the linked workspaces
+fn find_active_toggl_project(
+    toggl_projects: &[TogglProject],
+    project_id: ProjectId,
+) -> AppResult<&TogglProject> {
+    toggl_projects
+        .iter()
+        .find(|toggl_project| toggl_project.active && toggl_project.project_id() == project_id)
+        .ok_or_else(|| {
+            AppError::Validation(
+                "Choose an active Toggl project in one of your linked workspaces".to_owned(),
+            )
+        })
+}
+
+/// Replace a Toggl project that one of the user's projects counts with another one
+async fn relink_toggl_project(
+    user_key: UserKey,
+    project_key: ProjectKey,
+    old_project_id: ProjectId,
+    new_project: &TogglProject,
+    pool: &PgPool,
+) -> AppResult<()> {
+    let mut transaction = pool.begin().await?;
+
+    let unlinked = sqlx::query!(
+        "DELETE FROM projects_to_toggl_projects
+        WHERE project_key = (
+            SELECT project_key FROM projects WHERE project_key = $1 AND user_key = $2
+        )
+        AND workspace_id = $3
+        AND project_id = $4",
+        project_key.0,
+        user_key.0,
+        old_project_id.workspace_id.0,
+        old_project_id.project_id,
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    if unlinked == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    sqlx::query!(
+        "INSERT INTO projects_to_toggl_projects(project_key, workspace_id, project_id,
+            last_known_name, last_known_color, last_known_active)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT DO NOTHING",
+        project_key.0,
+        new_project.workspace_id.0,
+        new_project.id,
+        new_project.name,
+        new_project.color,
+        new_project.active,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+#![cfg(test)]
+
+use super::*;
+use crate::toggl::WorkspaceId;
+
+fn toggl_project(project_id: i64, active: bool) -> TogglProject {
+    TogglProject {
+        active,
+        name: format!("Toggl project {project_id}"),
+        id: project_id,
+        workspace_id: WorkspaceId(1234567),
+        client_id: None,
+        color: None,
+    }
+}
+
+async fn insert_user_with_project(username: &str, pool: &PgPool) -> (UserKey, ProjectKey) {
+    let user_key = sqlx::query!(
+        "INSERT INTO users(username, daily_max, timezone)
+        VALUES ($1, 0, 'UTC')
+        RETURNING user_key",
+        username,
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+    .user_key;
+
+    let project_key = sqlx::query!(
+        "INSERT INTO projects(user_key, starting_date, daily_goal,
+            monday, tuesday, wednesday, thursday, friday, saturday, sunday)
+        VALUES ($1, '2026-10-18', 3600, TRUE, TRUE, TRUE, TRUE, TRUE, FALSE, FALSE)
+        RETURNING project_key",
+        user_key,
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+    .project_key;
+
+    sqlx::query!(
+        "INSERT INTO projects_to_toggl_projects(project_key, workspace_id, project_id)
+        VALUES ($1, 1234567, 1)",
+        project_key,
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+
+    (UserKey(user_key), ProjectKey(project_key))
+}
+
+async fn get_links(project_key: ProjectKey, pool: &PgPool) -> Vec<(i64, Option<String>)> {
+    sqlx::query!(
+        "SELECT project_id, last_known_name
+        FROM projects_to_toggl_projects
+        WHERE project_key = $1
+        ORDER BY project_id",
+        project_key.0,
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|record| (record.project_id, record.last_known_name))
+    .collect()
+}
+
+#[test]
+fn test_find_active_toggl_project() {
+    let toggl_projects = [toggl_project(1, false), toggl_project(2, true)];
+
+    assert_eq!(
+        find_active_toggl_project(&toggl_projects, toggl_project(2, true).project_id())
+            .unwrap()
+            .id,
+        2,
+    );
+
+    // An archived Toggl project can't replace another one, and neither can one that's missing
+    for project_id in [1, 3] {
+        assert!(matches!(
+            find_active_toggl_project(
+                &toggl_projects,
+                toggl_project(project_id, true).project_id()
+            ),
+            Err(AppError::Validation(_))
+        ));
+    }
+}
+
+#[sqlx::test]
+async fn test_relink_toggl_project(pool: PgPool) {
+    let (user_key, project_key) = insert_user_with_project("someone", &pool).await;
+    let (_, other_project_key) = insert_user_with_project("someone else", &pool).await;
+
+    let old_project_id = toggl_project(1, false).project_id();
+    let new_project = toggl_project(2, true);
+
+    relink_toggl_project(user_key, project_key, old_project_id, &new_project, &pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        get_links(project_key, &pool).await,
+        [(2, Some("Toggl project 2".to_owned()))],
+    );
+
+    // The project doesn't count the old Toggl project any more
+    assert!(matches!(
+        relink_toggl_project(user_key, project_key, old_project_id, &new_project, &pool).await,
+        Err(AppError::NotFound)
+    ));
+
+    // Someone else's project is left alone, as if it didn't exist
+    assert!(matches!(
+        relink_toggl_project(
+            user_key,
+            other_project_key,
+            old_project_id,
+            &new_project,
+            &pool,
+        )
+        .await,
+        Err(AppError::NotFound)
+    ));
+    assert_eq!(get_links(other_project_key, &pool).await, [(1, None)]);
+}
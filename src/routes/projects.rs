@@ -1,77 +1,254 @@
+use std::collections::{HashMap, HashSet};
+
 use askama::Template;
 use axum::{
-    debug_handler, extract::Extension, response::{Html, IntoResponse}
+    debug_handler,
+    extract::Extension,
+    response::{Html, IntoResponse},
 };
 use chrono::{Duration, NaiveDate};
 use reqwest::Client;
 use sqlx::PgPool;
 
 use crate::{
-    errors::InternalResult,
+    crypto::ServerKey,
+    csrf::CsrfToken,
+    errors::AppResult,
     human_duration::HumanDuration,
     session::UserKey,
-    toggl::{get_user_projects, ProjectId, WhichWeekdays, WorkspaceId},
+    toggl::{
+        get_last_known_toggl_project_names, get_toggl_projects_or_stored,
+        get_user_projects_from_toggl_projects, get_user_toggl_api_token, get_user_workspace_ids,
+        GoalFilter, LimitPeriod, ProjectId, ProjectKey, TogglProject, WhichWeekdays,
+    },
 };
 
+mod tests;
+
 struct Project {
-    pub id: ProjectId,
+    pub key: ProjectKey,
     pub name: String,
+    pub filter: FilterDescription,
     pub starting_date: NaiveDate,
     pub daily_goal: HumanDuration,
     pub weekly_goal: HumanDuration,
     pub weekdays: WhichWeekdays,
+    pub priority: i32,
+    pub weight: i32,
+    pub deadline: Option<(NaiveDate, HumanDuration)>,
+    pub limit: Option<(HumanDuration, LimitPeriod)>,
+    pub archived: bool,
+    pub inactive_toggl_projects: Vec<InactiveTogglProject>,
+}
+
+/// A Toggl project counted by a project that isn't active in Toggl any more
+#[derive(Debug, PartialEq)]
+struct InactiveTogglProject {
+    pub project_id: ProjectId,
+    pub name: String,
+    /// Whether it's archived in Toggl, rather than deleted or moved out of the linked workspaces
+    pub archived: bool,
 }
 
 #[derive(Template)]
 #[template(path = "projects.html")]
 pub struct ProjectsTemplate<'a> {
     projects: &'a [Project],
+    /// The active Toggl projects that an inactive one can be replaced with
+    toggl_projects: &'a [(ProjectId, &'a str)],
     total_weekly_goal: HumanDuration,
     average_daily_goal: HumanDuration,
+    csrf_token: &'a str,
 }
 
 #[debug_handler]
 pub async fn get(
     user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
     Extension(pool): Extension<PgPool>,
     Extension(client): Extension<Client>,
-) -> InternalResult<impl IntoResponse> {
-    let record = sqlx::query!(
-        "SELECT toggl_api_key, workspace_id FROM users WHERE user_key = $1",
-        user_key.0,
-    )
-    .fetch_one(&pool)
-    .await?;
-
-    let mut projects: Vec<Project> = get_user_projects(
-        &record.toggl_api_key,
-        WorkspaceId(record.workspace_id),
-        &client,
-        user_key,
-        &pool,
-    )
-    .await?
-    .into_iter()
-    .map(|(project_id, project)| Project {
-        id: project_id,
-        name: project.name,
-        starting_date: project.starting_date,
-        daily_goal: HumanDuration(project.daily_goal),
-        weekly_goal: HumanDuration(project.daily_goal * project.weekdays.num_days()),
-        weekdays: project.weekdays,
-    })
-    .collect();
-    projects.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+    Extension(server_key): Extension<ServerKey>,
+) -> AppResult<impl IntoResponse> {
+    let toggl_api_key = get_user_toggl_api_token(user_key, &pool, &server_key).await?;
+
+    let workspace_ids = get_user_workspace_ids(user_key, &pool).await?;
+
+    let toggl_projects =
+        get_toggl_projects_or_stored(&toggl_api_key, &workspace_ids, &client, user_key, &pool)
+            .await?;
+
+    let user_projects =
+        get_user_projects_from_toggl_projects(&toggl_projects, user_key, &pool).await?;
+
+    let last_known_names = get_last_known_toggl_project_names(user_key, &pool).await?;
+    let project_id_to_name: HashMap<ProjectId, &str> = last_known_names
+        .iter()
+        .map(|(project_id, name)| (*project_id, name.as_str()))
+        .collect();
+
+    let mut active_toggl_projects: Vec<(ProjectId, &str)> = toggl_projects
+        .iter()
+        .filter(|project| project.active)
+        .map(|project| (project.project_id(), project.name.as_str()))
+        .collect();
+    active_toggl_projects.sort_by(|lhs, rhs| lhs.1.cmp(rhs.1));
+
+    let mut projects: Vec<Project> = user_projects
+        .into_iter()
+        .map(|(project_key, project)| {
+            let filter = describe_filter(&project.filter, &project_id_to_name);
+
+            let inactive_toggl_projects = find_inactive_toggl_projects(
+                &project.filter.project_ids,
+                &toggl_projects,
+                &project_id_to_name,
+            );
+
+            Project {
+                key: project_key,
+                name: project.name,
+                filter,
+                starting_date: project.starting_date,
+                daily_goal: HumanDuration(project.daily_goal),
+                weekly_goal: HumanDuration(project.daily_goal * project.weekdays.num_days()),
+                weekdays: project.weekdays,
+                priority: project.priority,
+                weight: project.weight,
+                deadline: project
+                    .deadline
+                    .map(|deadline| (deadline.date, HumanDuration(deadline.total))),
+                limit: project
+                    .limit
+                    .map(|limit| (HumanDuration(limit.time), limit.period)),
+                archived: project.archived,
+                inactive_toggl_projects,
+            }
+        })
+        .collect();
+    // Archived projects go last
+    projects.sort_by(|lhs, rhs| (lhs.archived, &lhs.name).cmp(&(rhs.archived, &rhs.name)));
 
     let total_weekly_goal = projects
         .iter()
+        .filter(|project| !project.archived)
         .fold(Duration::zero(), |acc, project| acc + project.weekly_goal.0);
 
     let template = ProjectsTemplate {
         projects: &projects,
+        toggl_projects: &active_toggl_projects,
         total_weekly_goal: HumanDuration(total_weekly_goal),
         average_daily_goal: HumanDuration(total_weekly_goal / 7),
+        csrf_token: &csrf_token,
     };
 
     Ok(Html(template.render()?))
 }
+
+/// The Toggl projects counted by a project that aren't active in Toggl, sorted by name
+fn find_inactive_toggl_projects(
+    project_ids: &HashSet<ProjectId>,
+    toggl_projects: &[TogglProject],
+    project_id_to_name: &HashMap<ProjectId, &str>,
+) -> Vec<InactiveTogglProject> {
+    let find = |project_id| {
+        toggl_projects
+            .iter()
+            .find(|toggl_project| toggl_project.project_id() == project_id)
+    };
+
+    let mut inactive_toggl_projects: Vec<InactiveTogglProject> = project_ids
+        .iter()
+        .filter(|&&project_id| !find(project_id).is_some_and(|toggl_project| toggl_project.active))
+        .map(|&project_id| InactiveTogglProject {
+            project_id,
+            name: toggl_project_name(project_id, project_id_to_name),
+            archived: find(project_id).is_some(),
+        })
+        .collect();
+    inactive_toggl_projects.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+
+    inactive_toggl_projects
+}
+
+/// Sorted, human readable lists of what a project's filter matches
+struct FilterDescription {
+    pub toggl_projects: Vec<String>,
+    pub clients: Vec<String>,
+    pub tags: Vec<String>,
+    pub descriptions: Vec<String>,
+    pub restrictions: Vec<String>,
+}
+
+fn describe_filter(
+    filter: &GoalFilter,
+    project_id_to_name: &HashMap<ProjectId, &str>,
+) -> FilterDescription {
+    let mut toggl_projects: Vec<String> = filter
+        .project_ids
+        .iter()
+        .map(|&project_id| toggl_project_name(project_id, project_id_to_name))
+        .collect();
+    toggl_projects.sort();
+
+    if filter.no_project {
+        toggl_projects.push(String::from("No project"));
+    }
+
+    let mut clients: Vec<String> = filter.clients.iter().cloned().collect();
+    clients.sort();
+
+    let mut tags: Vec<String> = filter.tags.iter().cloned().collect();
+    tags.sort();
+
+    let mut descriptions: Vec<String> = filter
+        .description_substrings
+        .iter()
+        .map(|substring| format!("\"{}\"", substring))
+        .chain(
+            filter
+                .description_regexes
+                .iter()
+                .map(|regex| format!("/{}/", regex)),
+        )
+        .collect();
+    descriptions.sort();
+
+    let mut restrictions = Vec::new();
+    if filter.billable_only {
+        restrictions.push(String::from("Billable only"));
+    }
+
+    let mut required_tags: Vec<String> = filter
+        .required_tags
+        .iter()
+        .map(|tag| format!("Only #{}", tag))
+        .collect();
+    required_tags.sort();
+    restrictions.extend(required_tags);
+
+    let mut excluded_tags: Vec<String> = filter
+        .excluded_tags
+        .iter()
+        .map(|tag| format!("Not #{}", tag))
+        .collect();
+    excluded_tags.sort();
+    restrictions.extend(excluded_tags);
+
+    FilterDescription {
+        toggl_projects,
+        clients,
+        tags,
+        descriptions,
+        restrictions,
+    }
+}
+
+fn toggl_project_name(
+    project_id: ProjectId,
+    project_id_to_name: &HashMap<ProjectId, &str>,
+) -> String {
+    match project_id_to_name.get(&project_id) {
+        Some(name) => name.to_string(),
+        None => format!("Unknown project {}", project_id),
+    }
+}
@@ -0,0 +1,63 @@
+#![cfg(test)]
+
+use super::*;
+use crate::toggl::WorkspaceId;
+
+fn toggl_project_id(project_id: i64) -> ProjectId {
+    ProjectId {
+        workspace_id: WorkspaceId(1234567),
+        project_id,
+    }
+}
+
+#[test]
+fn test_find_inactive_toggl_projects() {
+    let toggl_project = |project_id, active| TogglProject {
+        active,
+        name: format!("Toggl project {project_id}"),
+        id: project_id,
+        workspace_id: WorkspaceId(1234567),
+        client_id: None,
+        color: None,
+    };
+    let toggl_projects = [toggl_project(1, true), toggl_project(2, false)];
+
+    let project_id_to_name = HashMap::from([
+        (toggl_project_id(1), "Thesis"),
+        (toggl_project_id(2), "Reading"),
+        (toggl_project_id(3), "Chores"),
+    ]);
+
+    // Only the archived and deleted Toggl projects are warned about, and only archived ones are
+    // still in Toggl
+    assert_eq!(
+        find_inactive_toggl_projects(
+            &HashSet::from([
+                toggl_project_id(1),
+                toggl_project_id(2),
+                toggl_project_id(3)
+            ]),
+            &toggl_projects,
+            &project_id_to_name,
+        ),
+        Vec::from([
+            InactiveTogglProject {
+                project_id: toggl_project_id(3),
+                name: "Chores".to_owned(),
+                archived: false,
+            },
+            InactiveTogglProject {
+                project_id: toggl_project_id(2),
+                name: "Reading".to_owned(),
+                archived: true,
+            },
+        ]),
+    );
+
+    assert!(find_inactive_toggl_projects(
+        &HashSet::from([toggl_project_id(1)]),
+        &toggl_projects,
+        &project_id_to_name,
+    )
+    .is_empty());
+}
@@ -0,0 +1,33 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{IntoResponse, Redirect},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{csrf::CsrfForm, errors::AppResult, session::UserKey};
+
+#[derive(Deserialize)]
+pub struct SessionDeleteForm {
+    session_key: i64,
+}
+
+#[debug_handler]
+pub async fn post(
+    user_key: UserKey,
+    Extension(pool): Extension<PgPool>,
+    CsrfForm(form): CsrfForm<SessionDeleteForm>,
+) -> AppResult<impl IntoResponse> {
+    sqlx::query!(
+        "DELETE FROM session_tokens
+        WHERE session_key = $1
+        AND user_key = $2",
+        form.session_key,
+        user_key.0,
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(Redirect::to("/sessions/"))
+}
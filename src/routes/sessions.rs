@@ -0,0 +1,79 @@
+use askama::Template;
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{Html, IntoResponse},
+};
+use axum_extra::extract::CookieJar;
+use chrono_tz::Tz;
+use sqlx::PgPool;
+
+use crate::{
+    csrf::CsrfToken,
+    errors::{AppError, AppResult},
+    session::{UserKey, SESSION_COOKIE_NAME},
+};
+
+use super::account::format_timestamp;
+
+struct Session {
+    key: i64,
+    current: bool,
+    created_at: String,
+    last_seen_at: String,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "sessions.html")]
+struct SessionsTemplate<'a> {
+    sessions: &'a [Session],
+    csrf_token: &'a str,
+}
+
+#[debug_handler]
+pub async fn get(
+    user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
+    cookie_jar: CookieJar,
+    Extension(pool): Extension<PgPool>,
+) -> AppResult<impl IntoResponse> {
+    let current_token = cookie_jar
+        .get(SESSION_COOKIE_NAME)
+        .map(|cookie| cookie.value());
+
+    let timezone: Tz = sqlx::query!("SELECT timezone FROM users WHERE user_key = $1", user_key.0,)
+        .fetch_one(&pool)
+        .await?
+        .timezone
+        .parse()
+        .map_err(AppError::UnrecognizedTimezone)?;
+
+    let sessions: Vec<Session> = sqlx::query!(
+        "SELECT session_key, token, created_at, last_seen_at, user_agent, ip_address
+        FROM session_tokens
+        WHERE user_key = $1
+        ORDER BY last_seen_at DESC",
+        user_key.0,
+    )
+    .fetch_all(&pool)
+    .await?
+    .into_iter()
+    .map(|record| Session {
+        key: record.session_key,
+        current: Some(record.token.as_str()) == current_token,
+        created_at: format_timestamp(record.created_at, &timezone),
+        last_seen_at: format_timestamp(record.last_seen_at, &timezone),
+        user_agent: record.user_agent,
+        ip_address: record.ip_address,
+    })
+    .collect();
+
+    let template = SessionsTemplate {
+        sessions: &sessions,
+        csrf_token: &csrf_token,
+    };
+
+    Ok(Html(template.render()?))
+}
@@ -0,0 +1,26 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{IntoResponse, Redirect},
+};
+use sqlx::PgPool;
+
+use crate::{
+    csrf::{CsrfForm, NoFields},
+    errors::AppResult,
+    session::{session_cookie_header, UserKey},
+};
+
+/// Sign out everywhere, including this session
+#[debug_handler]
+pub async fn post(
+    user_key: UserKey,
+    Extension(pool): Extension<PgPool>,
+    CsrfForm(NoFields {}): CsrfForm<NoFields>,
+) -> AppResult<impl IntoResponse> {
+    sqlx::query!("DELETE FROM session_tokens WHERE user_key = $1", user_key.0,)
+        .execute(&pool)
+        .await?;
+
+    Ok(([session_cookie_header("", 0)?], Redirect::to("/login/")))
+}
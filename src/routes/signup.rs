@@ -3,80 +3,201 @@ use std::str::FromStr;
 use askama::Template;
 use axum::{
     debug_handler,
-    extract::Form,
     http::StatusCode,
-    response::{IntoResponse, Redirect},
+    response::{Html, IntoResponse, Redirect},
     Extension,
 };
+use axum_extra::extract::Form;
 use chrono::Duration;
-use chrono_tz::Tz;
+use chrono_tz::{Tz, TZ_VARIANTS};
+use futures::future;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
 use crate::{
-    errors::InternalResult,
-    session::{new_session_cookie_header, UserKey},
-    toggl::{get_workspace_details, Workspace, WorkspaceId},
+    crypto::{hash_password, ServerKey},
+    errors::{AppError, AppResult},
+    session::{new_session_cookie_header, ClientInfo, UserKey},
+    toggl::{get_workspace_details, get_workspaces, Workspace, WorkspaceId},
+    validation::FieldErrors,
 };
 
+use super::{
+    account::{form_daily_max, invalid_timezone},
+    login::{normalize_username, valid_credentials},
+};
+
+mod tests;
+
 #[derive(Template)]
 #[template(path = "signup.html")]
 pub struct SignupTemplate<'a> {
     pub toggl_api_key: &'a str,
     pub workspaces: &'a [Workspace],
     pub timezones: &'a [Tz],
+    pub username_taken: bool,
+    pub invalid_credentials: bool,
+}
+
+#[derive(Template)]
+#[template(path = "signup_token.html")]
+pub struct SignupTokenTemplate {
+    pub unrecognized_api_token: bool,
+    pub already_registered: bool,
+}
+
+#[debug_handler]
+pub async fn get() -> AppResult<impl IntoResponse> {
+    let template = SignupTokenTemplate {
+        unrecognized_api_token: false,
+        already_registered: false,
+    };
+
+    Ok(Html(template.render()?))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SignupForm {
     toggl_api_key: String,
-    workspace_id: WorkspaceId,
+    username: String,
+    password: String,
+    #[serde(default)]
+    workspace_ids: Vec<WorkspaceId>,
     daily_max_hours: i64,
     daily_max_minutes: i64,
     daily_max_seconds: i64,
     timezone: String,
 }
 
+impl SignupForm {
+    fn daily_max(&self) -> Result<Duration, FieldErrors> {
+        form_daily_max(
+            self.daily_max_hours,
+            self.daily_max_minutes,
+            self.daily_max_seconds,
+        )
+    }
+}
+
 #[debug_handler]
 pub async fn post(
+    client_info: ClientInfo,
     Extension(client): Extension<Client>,
     Extension(pool): Extension<PgPool>,
+    Extension(server_key): Extension<ServerKey>,
     Form(form): Form<SignupForm>,
-) -> InternalResult<impl IntoResponse> {
-    let daily_max = Duration::hours(form.daily_max_hours)
-        + Duration::minutes(form.daily_max_minutes)
-        + Duration::seconds(form.daily_max_seconds);
+) -> AppResult<impl IntoResponse> {
+    let toggl_api_key = form.toggl_api_key.trim();
+
+    // Someone may have signed up with the token since it was checked
+    let already_registered = sqlx::query!(
+        "SELECT user_key
+        FROM users
+        WHERE toggl_api_key_digest = $1",
+        server_key.digest(toggl_api_key),
+    )
+    .fetch_optional(&pool)
+    .await?
+    .is_some();
+
+    if already_registered {
+        let template = SignupTokenTemplate {
+            unrecognized_api_token: false,
+            already_registered,
+        };
+
+        return Ok((StatusCode::BAD_REQUEST, Html(template.render()?)).into_response());
+    }
+
+    let daily_max = form.daily_max();
 
     // Check if the data from the form is valid
-    let workspace = get_workspace_details(&form.toggl_api_key, form.workspace_id, client).await?;
-    let positive_daily_max = daily_max >= Duration::zero();
+    let workspaces: Option<Vec<Workspace>> =
+        future::try_join_all(form.workspace_ids.iter().map(|&workspace_id| {
+            get_workspace_details(toggl_api_key, workspace_id, client.clone())
+        }))
+        .await?
+        .into_iter()
+        .collect();
+    let workspaces = workspaces.filter(|workspaces| !workspaces.is_empty());
+    let valid_workspaces = workspaces.is_some();
+    let username = normalize_username(&form.username);
+    let valid_credentials = valid_credentials(&form.username, &form.password);
+    let username_taken = sqlx::query!("SELECT user_key FROM users WHERE username = $1", username)
+        .fetch_optional(&pool)
+        .await?
+        .is_some();
     let timezone: Result<Tz, <Tz as FromStr>::Err> = form.timezone.parse();
 
+    if !valid_credentials || username_taken {
+        // Let the user pick different credentials without starting over
+        let all_workspaces = get_workspaces(toggl_api_key, client)
+            .await?
+            .unwrap_or_default();
+
+        let template = SignupTemplate {
+            toggl_api_key,
+            workspaces: &all_workspaces,
+            timezones: &TZ_VARIANTS,
+            username_taken,
+            invalid_credentials: !valid_credentials,
+        };
+
+        return Ok((StatusCode::BAD_REQUEST, Html(template.render()?)).into_response());
+    }
+
     Ok(
-        if let (Some(workspace), true, Ok(timezone)) = (workspace, positive_daily_max, timezone) {
+        if let (Some(workspaces), Ok(daily_max), Ok(timezone)) = (workspaces, &daily_max, timezone)
+        {
+            let mut transaction = pool.begin().await?;
+
             let user_key = UserKey(
                 sqlx::query!(
-                    "INSERT INTO users(toggl_api_key, workspace_id, daily_max, timezone)
-                    VALUES ($1, $2, $3, $4)
+                    "INSERT INTO users(username, password_hash,
+                        toggl_api_key_digest, toggl_api_key_ciphertext, daily_max, timezone)
+                    VALUES ($1, $2, $3, $4, $5, $6)
                     RETURNING user_key",
-                    form.toggl_api_key,
-                    workspace.id.0,
+                    username,
+                    hash_password(&form.password)?,
+                    server_key.digest(toggl_api_key),
+                    server_key.encrypt(toggl_api_key)?,
                     daily_max.num_seconds(),
                     timezone.to_string(),
                 )
-                .fetch_one(&pool)
+                .fetch_one(&mut *transaction)
                 .await?
                 .user_key,
             );
 
+            let workspace_ids: Vec<i64> =
+                workspaces.iter().map(|workspace| workspace.id.0).collect();
+
+            sqlx::query!(
+                "INSERT INTO users_to_workspaces(user_key, workspace_id)
+                SELECT $1, workspace_id FROM UNNEST($2::BIGINT[]) AS workspace_id
+                ON CONFLICT DO NOTHING",
+                user_key.0,
+                &workspace_ids,
+            )
+            .execute(&mut *transaction)
+            .await?;
+
+            transaction.commit().await?;
+
             (
-                [new_session_cookie_header(user_key, &pool).await?],
+                [new_session_cookie_header(user_key, &client_info, &pool).await?],
                 Redirect::to("/"),
             )
                 .into_response()
+        } else if !valid_workspaces {
+            return Err(AppError::Validation(
+                "Choose at least one workspace that your Toggl API token can access".to_owned(),
+            ));
+        } else if let Err(errors) = daily_max {
+            return Err(AppError::InvalidFields(errors));
         } else {
-            StatusCode::BAD_REQUEST.into_response()
+            return Err(invalid_timezone(&form.timezone));
         },
     )
 }
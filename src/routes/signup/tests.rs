@@ -0,0 +1,25 @@
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn test_signup_form_daily_max() {
+    let form = |daily_max_hours| SignupForm {
+        toggl_api_key: "1971800d4d82861d8f2c1651fea4d212".to_owned(),
+        username: "ada".to_owned(),
+        password: "correct horse battery staple".to_owned(),
+        workspace_ids: Vec::from([WorkspaceId(1234567)]),
+        daily_max_hours,
+        daily_max_minutes: 0,
+        daily_max_seconds: 0,
+        timezone: "UTC".to_owned(),
+    };
+
+    assert_eq!(form(8).daily_max().unwrap(), Duration::hours(8));
+
+    // Too long to even be a duration, either way
+    assert!(form(i64::MAX).daily_max().unwrap_err().has("daily_max"));
+    assert!(form(i64::MIN).daily_max().unwrap_err().has("daily_max"));
+
+    assert!(form(0).daily_max().unwrap_err().has("daily_max"));
+}
@@ -0,0 +1,68 @@
+use askama::Template;
+use axum::{
+    debug_handler,
+    extract::Form,
+    http::StatusCode,
+    response::{Html, IntoResponse},
+    Extension,
+};
+use chrono_tz::TZ_VARIANTS;
+use reqwest::Client;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{crypto::ServerKey, errors::AppResult, toggl::get_workspaces};
+
+use super::signup::{SignupTemplate, SignupTokenTemplate};
+
+#[derive(Deserialize)]
+pub struct SignupTokenForm {
+    toggl_api_key: String,
+}
+
+#[debug_handler]
+pub async fn post(
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+    Extension(server_key): Extension<ServerKey>,
+    Form(form): Form<SignupTokenForm>,
+) -> AppResult<impl IntoResponse> {
+    let toggl_api_key = form.toggl_api_key.trim();
+
+    let already_registered = sqlx::query!(
+        "SELECT user_key
+        FROM users
+        WHERE toggl_api_key_digest = $1",
+        server_key.digest(toggl_api_key),
+    )
+    .fetch_optional(&pool)
+    .await?
+    .is_some();
+
+    let workspaces = if already_registered {
+        None
+    } else {
+        get_workspaces(toggl_api_key, client).await?
+    };
+
+    Ok(if let Some(workspaces) = workspaces {
+        // The API token works: Let them finish signing up
+        let template = SignupTemplate {
+            toggl_api_key,
+            workspaces: &workspaces,
+            timezones: &TZ_VARIANTS,
+            username_taken: false,
+            invalid_credentials: false,
+        };
+
+        Html(template.render()?).into_response()
+    } else {
+        // The API token doesn't work or already has an account: Respond with an error message
+        let template = SignupTokenTemplate {
+            unrecognized_api_token: !already_registered,
+            already_registered,
+        };
+
+        (StatusCode::BAD_REQUEST, Html(template.render()?)).into_response()
+    })
+}
@@ -0,0 +1,33 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    response::{IntoResponse, Redirect},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{csrf::CsrfForm, errors::AppResult, session::UserKey};
+
+#[derive(Deserialize)]
+pub struct WebhookDeleteForm {
+    webhook_key: i64,
+}
+
+#[debug_handler]
+pub async fn post(
+    user_key: UserKey,
+    Extension(pool): Extension<PgPool>,
+    CsrfForm(form): CsrfForm<WebhookDeleteForm>,
+) -> AppResult<impl IntoResponse> {
+    sqlx::query!(
+        "DELETE FROM webhooks
+        WHERE webhook_key = $1
+        AND user_key = $2",
+        form.webhook_key,
+        user_key.0,
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(Redirect::to("/account/webhooks/"))
+}
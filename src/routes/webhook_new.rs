@@ -0,0 +1,130 @@
+use axum::{
+    debug_handler,
+    extract::Extension,
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect},
+};
+use chrono::Duration;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    crypto::{generate_webhook_secret, ServerKey},
+    csrf::{CsrfForm, CsrfToken},
+    errors::AppResult,
+    notifications::is_webhook_url,
+    session::UserKey,
+    validation::FieldErrors,
+};
+
+use super::account_webhooks::render;
+
+mod tests;
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookNewForm {
+    #[serde(default)]
+    pub url: String,
+    pub goal_met: Option<String>,
+    pub debt_cleared: Option<String>,
+    pub debt_exceeded: Option<String>,
+    pub day_started: Option<String>,
+    #[serde(default)]
+    pub debt_threshold_hours: i64,
+    #[serde(default)]
+    pub debt_threshold_minutes: i64,
+}
+
+/// An empty form, which sends the event that most people want
+impl Default for WebhookNewForm {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            goal_met: Some(String::new()),
+            debt_cleared: None,
+            debt_exceeded: None,
+            day_started: None,
+            debt_threshold_hours: 0,
+            debt_threshold_minutes: 0,
+        }
+    }
+}
+
+impl WebhookNewForm {
+    fn debt_threshold(&self) -> Option<Duration> {
+        Duration::try_hours(self.debt_threshold_hours)
+            .zip(Duration::try_minutes(self.debt_threshold_minutes))
+            .and_then(|(hours, minutes)| hours.checked_add(&minutes))
+    }
+}
+
+fn validate(form: &WebhookNewForm) -> FieldErrors {
+    let mut errors = FieldErrors::new();
+
+    let url = form.url.trim();
+    errors.require(
+        is_webhook_url(url),
+        "url",
+        format!("{url} isn't a public http or https URL"),
+    );
+
+    errors.require(
+        form.goal_met.is_some()
+            || form.debt_cleared.is_some()
+            || form.debt_exceeded.is_some()
+            || form.day_started.is_some(),
+        "events",
+        "Choose at least one event to send to the webhook",
+    );
+
+    match form.debt_threshold() {
+        Some(debt_threshold) => errors.require(
+            debt_threshold >= Duration::zero(),
+            "debt_threshold",
+            "The debt threshold can't be negative",
+        ),
+        None => errors.add("debt_threshold", "The debt threshold is too long"),
+    }
+
+    errors
+}
+
+#[debug_handler]
+pub async fn post(
+    user_key: UserKey,
+    CsrfToken(csrf_token): CsrfToken,
+    Extension(pool): Extension<PgPool>,
+    Extension(server_key): Extension<ServerKey>,
+    CsrfForm(form): CsrfForm<WebhookNewForm>,
+) -> AppResult<impl IntoResponse> {
+    let errors = validate(&form);
+
+    let debt_threshold = match form.debt_threshold() {
+        Some(debt_threshold) if errors.is_empty() => debt_threshold,
+        _ => {
+            // Show the form again with the user's input and what's wrong with it
+            let body = render(user_key, &pool, &csrf_token, &form, &errors, &[]).await?;
+
+            return Ok((StatusCode::BAD_REQUEST, Html(body)).into_response());
+        }
+    };
+
+    // The secret is shown the next time the webhooks page is loaded, and never again
+    sqlx::query!(
+        "INSERT INTO webhooks(user_key, url, secret_ciphertext, goal_met, debt_cleared,
+            debt_exceeded, day_started, debt_threshold, secret_shown)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, FALSE)",
+        user_key.0,
+        form.url.trim(),
+        server_key.encrypt(&generate_webhook_secret())?,
+        form.goal_met.is_some(),
+        form.debt_cleared.is_some(),
+        form.debt_exceeded.is_some(),
+        form.day_started.is_some(),
+        debt_threshold.num_seconds(),
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(Redirect::to("/account/webhooks/").into_response())
+}
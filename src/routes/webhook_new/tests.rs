@@ -0,0 +1,41 @@
+#![cfg(test)]
+
+use super::*;
+
+fn example_form() -> WebhookNewForm {
+    WebhookNewForm {
+        url: " https://example.com/hook ".to_owned(),
+        ..WebhookNewForm::default()
+    }
+}
+
+#[test]
+fn test_validate_new_webhook() {
+    assert!(validate(&example_form()).is_empty());
+
+    let private_url = WebhookNewForm {
+        url: "http://169.254.169.254/latest/meta-data/".to_owned(),
+        ..example_form()
+    };
+    assert!(validate(&private_url).has("url"));
+
+    let no_events = WebhookNewForm {
+        goal_met: None,
+        ..example_form()
+    };
+    assert!(validate(&no_events).has("events"));
+
+    let negative_threshold = WebhookNewForm {
+        debt_exceeded: Some(String::new()),
+        debt_threshold_hours: 1,
+        debt_threshold_minutes: -90,
+        ..example_form()
+    };
+    assert!(validate(&negative_threshold).has("debt_threshold"));
+
+    let oversized_threshold = WebhookNewForm {
+        debt_threshold_hours: i64::MAX,
+        ..example_form()
+    };
+    assert!(validate(&oversized_threshold).has("debt_threshold"));
+}
@@ -1,26 +1,46 @@
-use crate::errors::InternalResult;
+use crate::{
+    crypto::hash_api_token,
+    errors::{ApiError, AppError, AppResult},
+};
 use axum::{
-    extract::{Extension, FromRequestParts},
-    http::{header::SET_COOKIE, request::Parts, HeaderName, HeaderValue, StatusCode},
+    extract::{ConnectInfo, Extension, FromRequestParts, Request},
+    http::{
+        header::{SET_COOKIE, USER_AGENT},
+        request::Parts,
+        HeaderName, HeaderValue,
+    },
+    middleware::Next,
     response::{IntoResponse, Redirect, Response},
 };
 use axum_extra::{
     extract::CookieJar,
-    headers::{authorization::Basic, Authorization},
+    headers::{
+        authorization::{Basic, Bearer},
+        Authorization,
+    },
     TypedHeader,
 };
+use chrono::{DateTime, Duration, Utc};
 use rand::distr::{Alphanumeric, SampleString};
 use sqlx::PgPool;
-use std::env;
+use std::{
+    convert::Infallible,
+    env,
+    marker::PhantomData,
+    net::{AddrParseError, IpAddr, SocketAddr},
+};
+
+mod tests;
 
 pub static BASIC_AUTH_USERNAME: &str = "api_token";
 pub static SESSION_COOKIE_NAME: &str = "session";
+pub static TRUSTED_PROXIES_VARIABLE: &str = "YOTTACLOCK_TRUSTED_PROXIES";
 
-pub struct UserKey(pub i64);
+/// Sessions expire after this long without being used
+pub const SESSION_LIFETIME_DAYS: i64 = 30;
 
-fn to_internal_server_error<E>(_: E) -> Response {
-    StatusCode::INTERNAL_SERVER_ERROR.into_response()
-}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UserKey(pub i64);
 
 impl<S> FromRequestParts<S> for UserKey
 where
@@ -33,75 +53,297 @@ where
             .await
             .map_err(IntoResponse::into_response)?;
 
-        let cookie_jar = CookieJar::from_request_parts(parts, state)
-            .await
-            .map_err(to_internal_server_error)?;
+        let cookie_jar = CookieJar::from_headers(&parts.headers);
 
         let session_token = cookie_jar
             .get(SESSION_COOKIE_NAME)
             .map(|cookie| cookie.value());
 
-        let basic_authorization: Option<TypedHeader<Authorization<Basic>>> =
-            match TypedHeader::from_request_parts(parts, state).await {
-                Ok(api_token) => Some(api_token),
-                Err(rejection) => {
-                    if rejection.is_missing() {
-                        None
-                    } else {
-                        return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        if let Some(session_token) = session_token {
+            find_session(session_token, &pool)
+                .await
+                .map_err(IntoResponse::into_response)?
+                // The session expired or was signed out: Forget it and log in again
+                .ok_or_else(|| match session_cookie_header("", 0) {
+                    Ok(clear_cookie) => ([clear_cookie], Redirect::to("/login/")).into_response(),
+                    Err(error) => error.into_response(),
+                })
+        } else {
+            Err(Redirect::to("/login/").into_response())
+        }
+    }
+}
+
+/// What a personal API token is allowed to do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ApiScopes {
+    pub read_status: bool,
+    pub write_days_off: bool,
+    pub admin: bool,
+}
+
+/// A scope that an API route requires, which admin tokens always have
+pub trait RequiredScope {
+    fn allowed(scopes: &ApiScopes) -> bool;
+}
+
+pub struct ReadStatus;
+
+impl RequiredScope for ReadStatus {
+    fn allowed(scopes: &ApiScopes) -> bool {
+        scopes.admin || scopes.read_status
+    }
+}
+
+pub struct WriteDaysOff;
+
+impl RequiredScope for WriteDaysOff {
+    fn allowed(scopes: &ApiScopes) -> bool {
+        scopes.admin || scopes.write_days_off
+    }
+}
+
+/// Changing projects and account settings, which only admin tokens can do
+pub struct Admin;
+
+impl RequiredScope for Admin {
+    fn allowed(scopes: &ApiScopes) -> bool {
+        scopes.admin
+    }
+}
+
+/// A user authenticated for the JSON API, either with a personal API token that has the scope `R`
+/// or with a browser session, which can do anything
+pub struct ApiUserKey<R: RequiredScope>(pub UserKey, pub PhantomData<R>);
+
+impl<S, R> FromRequestParts<S> for ApiUserKey<R>
+where
+    S: Send + Sync,
+    R: RequiredScope,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Extension(pool) = Extension::<PgPool>::from_request_parts(parts, state).await?;
+
+        let api_token = match TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
+            .await
+        {
+            Ok(TypedHeader(Authorization(bearer))) => Some(bearer.token().to_owned()),
+            Err(rejection) if rejection.is_missing() => {
+                match TypedHeader::<Authorization<Basic>>::from_request_parts(parts, state).await {
+                    Ok(TypedHeader(Authorization(basic))) => {
+                        if basic.username() == BASIC_AUTH_USERNAME {
+                            Some(basic.password().to_owned())
+                        } else {
+                            return Err(AppError::Unauthenticated.into());
+                        }
                     }
+                    Err(rejection) if rejection.is_missing() => None,
+                    Err(_) => return Err(AppError::Unauthenticated.into()),
                 }
-            };
-
-        let user_key =
-            if let Some(TypedHeader(Authorization(basic_authorization))) = basic_authorization {
-                if basic_authorization.username() == BASIC_AUTH_USERNAME {
-                    let api_token = basic_authorization.password();
-
-                    sqlx::query!(
-                        "SELECT user_key FROM users WHERE toggl_api_key = $1",
-                        api_token,
-                    )
-                    .fetch_optional(&pool)
-                    .await
-                    .map_err(to_internal_server_error)?
-                    .map(|s| s.user_key)
-                    .ok_or_else(|| StatusCode::BAD_REQUEST.into_response())?
-                } else {
-                    return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
-                }
-            } else if let Some(session_token) = session_token {
-                sqlx::query!(
-                    "SELECT user_key FROM session_tokens WHERE token = $1",
-                    session_token,
-                )
-                .fetch_optional(&pool)
-                .await
-                .map_err(to_internal_server_error)?
-                .map(|s| s.user_key)
-                .ok_or_else(|| StatusCode::BAD_REQUEST.into_response())?
-            } else {
-                return Err(Redirect::to("/login/").into_response());
-            };
-
-        Ok(Self(user_key))
+            }
+            Err(_) => return Err(AppError::Unauthenticated.into()),
+        };
+
+        let Some(api_token) = api_token else {
+            // Without an API token, fall back to the browser session
+            let cookie_jar = CookieJar::from_headers(&parts.headers);
+            let session_token = cookie_jar
+                .get(SESSION_COOKIE_NAME)
+                .ok_or(AppError::Unauthenticated)?;
+
+            let user_key = find_session(session_token.value(), &pool)
+                .await?
+                .ok_or(AppError::Unauthenticated)?;
+
+            return Ok(Self(user_key, PhantomData));
+        };
+
+        let (user_key, scopes) = find_api_token(&api_token, &pool)
+            .await?
+            .ok_or(AppError::Unauthenticated)?;
+
+        if R::allowed(&scopes) {
+            Ok(Self(user_key, PhantomData))
+        } else {
+            Err(AppError::Forbidden.into())
+        }
+    }
+}
+
+/// Look up the user and scopes of a personal API token, recording that it was used
+pub async fn find_api_token(
+    api_token: &str,
+    pool: &PgPool,
+) -> AppResult<Option<(UserKey, ApiScopes)>> {
+    let record = sqlx::query!(
+        "UPDATE api_tokens
+        SET last_used_at = now()
+        WHERE digest = $1
+        RETURNING user_key, read_status, write_days_off, admin",
+        hash_api_token(api_token),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record.map(|record| {
+        (
+            UserKey(record.user_key),
+            ApiScopes {
+                read_status: record.read_status,
+                write_days_off: record.write_days_off,
+                admin: record.admin,
+            },
+        )
+    }))
+}
+
+/// Look up the user of a session that hasn't expired, recording that it was used
+pub async fn find_session(session_token: &str, pool: &PgPool) -> AppResult<Option<UserKey>> {
+    let record = sqlx::query!(
+        "UPDATE session_tokens
+        SET last_seen_at = now()
+        WHERE token = $1
+        AND last_seen_at > $2
+        RETURNING user_key",
+        session_token,
+        session_expiry_cutoff(),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record.map(|record| UserKey(record.user_key)))
+}
+
+/// Sessions last seen before this have expired
+fn session_expiry_cutoff() -> DateTime<Utc> {
+    Utc::now() - Duration::days(SESSION_LIFETIME_DAYS)
+}
+
+/// The addresses of the reverse proxies whose `X-Forwarded-For` headers can be believed, given
+/// as a comma-separated list
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+impl TrustedProxies {
+    pub fn from_env() -> Result<Self, AddrParseError> {
+        let proxies = env::var(TRUSTED_PROXIES_VARIABLE).unwrap_or_default();
+
+        proxies
+            .split(',')
+            .map(str::trim)
+            .filter(|proxy| !proxy.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    /// The client's address, going back through `X-Forwarded-For` for as long as the request
+    /// passed through trusted proxies, since anything before them could have been made up
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        let mut client = peer;
+        let mut forwarded = forwarded_for
+            .into_iter()
+            .flat_map(|forwarded_for| forwarded_for.rsplit(','));
+
+        while self.0.contains(&client) {
+            match forwarded.next().map(|address| address.trim().parse()) {
+                Some(Ok(address)) => client = address,
+                _ => break,
+            }
+        }
+
+        client
+    }
+}
+
+/// Who a session was started by, shown when listing the user's sessions
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+
+        let user_agent = header(USER_AGENT.as_str()).map(str::to_owned);
+
+        let trusted_proxies = parts
+            .extensions
+            .get::<TrustedProxies>()
+            .cloned()
+            .unwrap_or_default();
+
+        let ip_address =
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| {
+                    trusted_proxies
+                        .client_ip(address.ip(), header("x-forwarded-for"))
+                        .to_string()
+                });
+
+        Ok(Self {
+            user_agent,
+            ip_address,
+        })
     }
 }
 
 pub async fn new_session_cookie_header(
     user_key: UserKey,
+    client_info: &ClientInfo,
     pool: &PgPool,
-) -> InternalResult<(HeaderName, HeaderValue)> {
+) -> AppResult<(HeaderName, HeaderValue)> {
     let session_token = Alphanumeric.sample_string(&mut rand::rng(), 64);
 
     sqlx::query!(
-        "INSERT INTO session_tokens(token, user_key) VALUES ($1, $2)",
+        "INSERT INTO session_tokens(token, user_key, user_agent, ip_address)
+        VALUES ($1, $2, $3, $4)",
         session_token,
         user_key.0,
+        client_info.user_agent,
+        client_info.ip_address,
     )
     .execute(pool)
     .await?;
 
+    // Clean up the user's old sessions while we're at it
+    sqlx::query!(
+        "DELETE FROM session_tokens
+        WHERE user_key = $1
+        AND last_seen_at <= $2",
+        user_key.0,
+        session_expiry_cutoff(),
+    )
+    .execute(pool)
+    .await?;
+
+    session_cookie_header(
+        &session_token,
+        Duration::days(SESSION_LIFETIME_DAYS).num_seconds(),
+    )
+}
+
+/// Set the session cookie, or clear it with an empty token and a max age of zero
+pub fn session_cookie_header(
+    session_token: &str,
+    max_age: i64,
+) -> AppResult<(HeaderName, HeaderValue)> {
     let secure = if env::var_os("YOTTACLOCK_INSECURE_COOKIES").is_some() {
         ""
     } else {
@@ -111,9 +353,28 @@ pub async fn new_session_cookie_header(
     Ok((
         SET_COOKIE,
         format!(
-            "{}={}; Max-Age=2592000; Path=/; {}HttpOnly; SameSite=Strict",
-            SESSION_COOKIE_NAME, session_token, secure,
+            "{}={}; Max-Age={}; Path=/; {}HttpOnly; SameSite=Strict",
+            SESSION_COOKIE_NAME, session_token, max_age, secure,
         )
         .parse()?,
     ))
 }
+
+/// Push back the session cookie's expiry whenever it's used, to match the sliding expiry on the
+/// server
+pub async fn renew_session_cookie(cookie_jar: CookieJar, request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+
+    let renewable = !response.headers().contains_key(SET_COOKIE)
+        && (response.status().is_success() || response.status().is_redirection());
+
+    if let (true, Some(cookie)) = (renewable, cookie_jar.get(SESSION_COOKIE_NAME)) {
+        let max_age = Duration::days(SESSION_LIFETIME_DAYS).num_seconds();
+
+        if let Ok((name, value)) = session_cookie_header(cookie.value(), max_age) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+
+    response
+}
@@ -0,0 +1,130 @@
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn test_api_scopes() {
+    let read_status = ApiScopes {
+        read_status: true,
+        ..ApiScopes::default()
+    };
+
+    assert!(ReadStatus::allowed(&read_status));
+    assert!(!WriteDaysOff::allowed(&read_status));
+    assert!(!Admin::allowed(&read_status));
+
+    let admin = ApiScopes {
+        admin: true,
+        ..ApiScopes::default()
+    };
+
+    assert!(ReadStatus::allowed(&admin));
+    assert!(WriteDaysOff::allowed(&admin));
+    assert!(Admin::allowed(&admin));
+}
+
+#[test]
+fn test_client_ip() {
+    let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+    let client: IpAddr = "203.0.113.7".parse().unwrap();
+    let trusted_proxies = TrustedProxies(Vec::from([proxy]));
+
+    // Without a trusted proxy in the way, the header is whatever the client says it is
+    assert_eq!(
+        TrustedProxies::default().client_ip(client, Some("198.51.100.1")),
+        client,
+    );
+    assert_eq!(
+        trusted_proxies.client_ip(client, Some("198.51.100.1")),
+        client,
+    );
+
+    assert_eq!(
+        trusted_proxies.client_ip(proxy, Some("203.0.113.7")),
+        client
+    );
+    assert_eq!(
+        trusted_proxies.client_ip(proxy, Some("198.51.100.1, 203.0.113.7")),
+        client,
+    );
+    assert_eq!(
+        trusted_proxies.client_ip(proxy, Some("203.0.113.7, 10.0.0.1")),
+        client,
+    );
+    assert_eq!(trusted_proxies.client_ip(proxy, None), proxy);
+    assert_eq!(trusted_proxies.client_ip(proxy, Some("nonsense")), proxy);
+}
+
+#[sqlx::test]
+async fn test_find_api_token(pool: PgPool) -> sqlx::Result<()> {
+    let user_key = sqlx::query!(
+        "INSERT INTO users(username, daily_max, timezone)
+        VALUES ('someone', 0, 'UTC')
+        RETURNING user_key"
+    )
+    .fetch_one(&pool)
+    .await?
+    .user_key;
+
+    sqlx::query!(
+        "INSERT INTO api_tokens(user_key, name, digest, read_status, write_days_off, admin)
+        VALUES ($1, 'Widget', $2, TRUE, FALSE, FALSE)",
+        user_key,
+        hash_api_token("yc_example"),
+    )
+    .execute(&pool)
+    .await?;
+
+    let (found_user_key, scopes) = find_api_token("yc_example", &pool).await.unwrap().unwrap();
+
+    assert_eq!(found_user_key.0, user_key);
+    assert!(ReadStatus::allowed(&scopes));
+    assert!(!WriteDaysOff::allowed(&scopes));
+
+    let last_used_at = sqlx::query!("SELECT last_used_at FROM api_tokens")
+        .fetch_one(&pool)
+        .await?
+        .last_used_at;
+
+    assert!(last_used_at.is_some());
+    assert!(find_api_token("yc_wrong", &pool).await.unwrap().is_none());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_find_session(pool: PgPool) -> sqlx::Result<()> {
+    let user_key = sqlx::query!(
+        "INSERT INTO users(username, daily_max, timezone)
+        VALUES ('someone', 0, 'UTC')
+        RETURNING user_key"
+    )
+    .fetch_one(&pool)
+    .await?
+    .user_key;
+
+    sqlx::query!(
+        "INSERT INTO session_tokens(token, user_key, last_seen_at)
+        VALUES ('fresh', $1, now() - INTERVAL '29 days'),
+            ('stale', $1, now() - INTERVAL '31 days')",
+        user_key,
+    )
+    .execute(&pool)
+    .await?;
+
+    let found_user_key = find_session("fresh", &pool).await.unwrap().unwrap();
+    assert_eq!(found_user_key.0, user_key);
+
+    // Using a session pushes back its expiry
+    let last_seen_at =
+        sqlx::query!("SELECT last_seen_at FROM session_tokens WHERE token = 'fresh'")
+            .fetch_one(&pool)
+            .await?
+            .last_seen_at;
+    assert!(last_seen_at > session_expiry_cutoff() + Duration::days(28));
+
+    assert!(find_session("stale", &pool).await.unwrap().is_none());
+    assert!(find_session("unknown", &pool).await.unwrap().is_none());
+
+    Ok(())
+}
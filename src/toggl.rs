@@ -2,26 +2,64 @@ use std::{
     cmp::{max, min},
     collections::{HashMap, HashSet},
     fmt::{self, Display, Formatter},
+    str::FromStr,
 };
 
-use chrono::{DateTime, Datelike, Days, Duration, FixedOffset, NaiveDate, Utc, Weekday};
+use chrono::{DateTime, Datelike, Days, Duration, FixedOffset, NaiveDate, NaiveTime, Utc, Weekday};
 use chrono_tz::Tz;
 use futures::future;
-use reqwest::{Client, StatusCode};
-use serde::{Deserialize, Serialize};
+use regex::Regex;
+use reqwest::{Client, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use sqlx::PgPool;
 use tracing::{trace, warn};
+use utoipa::ToSchema;
 
 use crate::{
-    errors::{InternalError, InternalResult},
+    crypto::ServerKey,
+    errors::{AppError, AppResult},
+    human_duration::milliseconds,
+    plan::{share, Claim},
+    project_settings::get_all_project_settings,
     session::UserKey,
+    toggl_scheduler,
 };
 
 mod tests;
 
+#[derive(Serialize, Deserialize)]
 pub struct Goal {
+    pub project_key: ProjectKey,
     pub name: String,
+    pub project_ids: Vec<ProjectId>,
+    /// How much time the project is owed, which is negative if the user is ahead on it
+    #[serde(with = "milliseconds")]
     pub time: Duration,
+    /// How much the project's debt grew today, which is zero on days it doesn't apply
+    #[serde(with = "milliseconds")]
+    pub goal_today: Duration,
+    #[serde(with = "milliseconds")]
+    pub logged_today: Duration,
+    pub day_off_today: bool,
+    pub priority: i32,
+    pub weight: i32,
+    /// How much of today's total debt is this project's to work off
+    #[serde(with = "milliseconds")]
+    pub share_today: Duration,
+    pub deadline: Option<Deadline>,
+    /// When the deadline's target will be reached at the pace kept so far, if it will be
+    pub projected_finish: Option<NaiveDate>,
+    /// For a project with a limit rather than a goal: How much of it is used up
+    pub limit: Option<LimitUsage>,
+}
+
+/// Where the user stands on their projects as of today
+#[derive(Serialize, Deserialize)]
+pub struct Status {
+    pub goals: Vec<Goal>,
+    #[serde(with = "milliseconds")]
+    pub total_debt: Duration,
+    pub today: NaiveDate,
 }
 
 #[derive(Serialize)]
@@ -41,16 +79,72 @@ struct TogglResponse {
 
 #[derive(Debug, Deserialize)]
 struct TogglResponseData {
-    pid: i64,
+    id: i64,
+    pid: Option<i64>,
+    description: Option<String>,
+    client: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    is_billable: bool,
     start: DateTime<FixedOffset>,
     dur: i64,
 }
 
+/// A Toggl project, qualified by the workspace it belongs to
+///
+/// Written as `<workspace_id>:<project_id>` in forms and JSON.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(transparent)]
-pub struct ProjectId(pub i64);
+#[serde(try_from = "String", into = "String")]
+pub struct ProjectId {
+    pub workspace_id: WorkspaceId,
+    pub project_id: i64,
+}
 
 impl Display for ProjectId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.workspace_id, self.project_id)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Unrecognized project ID: {0}")]
+pub struct ParseProjectIdError(String);
+
+impl FromStr for ProjectId {
+    type Err = ParseProjectIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseProjectIdError(s.to_string());
+
+        let (workspace_id, project_id) = s.split_once(':').ok_or_else(error)?;
+
+        Ok(Self {
+            workspace_id: WorkspaceId(workspace_id.parse().map_err(|_| error())?),
+            project_id: project_id.parse().map_err(|_| error())?,
+        })
+    }
+}
+
+impl TryFrom<String> for ProjectId {
+    type Error = ParseProjectIdError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ProjectId> for String {
+    fn from(project_id: ProjectId) -> Self {
+        project_id.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ProjectKey(pub i64);
+
+impl Display for ProjectKey {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
     }
@@ -62,7 +156,7 @@ pub struct Workspace {
     pub name: String,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct WorkspaceId(pub i64);
 
@@ -72,306 +166,1399 @@ impl Display for WorkspaceId {
     }
 }
 
+/// A Toggl user, whose API token or webhook subscription what's stored of a workspace was seen with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TogglUserId(pub i64);
+
+#[derive(Deserialize)]
+struct TogglMe {
+    id: TogglUserId,
+}
+
 #[derive(Debug)]
 struct TogglEntry {
-    project_id: ProjectId,
+    project_id: Option<ProjectId>,
+    description: String,
+    client: Option<String>,
+    tags: Vec<String>,
+    billable: bool,
     date: NaiveDate,
     duration: Duration,
 }
 
-impl From<TogglResponseData> for TogglEntry {
-    fn from(response_data: TogglResponseData) -> Self {
-        Self {
-            project_id: ProjectId(response_data.pid),
-            date: response_data.start.date_naive(),
-            duration: Duration::milliseconds(response_data.dur),
+/// Which Toggl entries count towards a project's goal
+///
+/// An entry counts if it belongs to any of the Toggl projects, any of the clients, has any of the
+/// tags, has no project while `no_project` is set, or has a description matching any of the
+/// substrings (ignoring case) or regexes.
+///
+/// A selected entry is still left out if `billable_only` is set and it isn't billable, if it has
+/// none of the `required_tags` (when there are any), or if it has any of the `excluded_tags`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GoalFilter {
+    pub project_ids: HashSet<ProjectId>,
+    pub clients: HashSet<String>,
+    pub tags: HashSet<String>,
+    pub no_project: bool,
+    pub description_substrings: HashSet<String>,
+    pub description_regexes: Vec<DescriptionRegex>,
+    pub billable_only: bool,
+    pub required_tags: HashSet<String>,
+    pub excluded_tags: HashSet<String>,
+}
+
+impl GoalFilter {
+    fn matches(&self, entry: &TogglEntry) -> bool {
+        self.selects(entry) && self.allows(entry)
+    }
+
+    fn selects(&self, entry: &TogglEntry) -> bool {
+        let matches_project = match entry.project_id {
+            Some(project_id) => self.project_ids.contains(&project_id),
+            None => self.no_project,
+        };
+
+        let description = entry.description.to_lowercase();
+
+        matches_project
+            || entry
+                .client
+                .as_ref()
+                .is_some_and(|client| self.clients.contains(client))
+            || entry.tags.iter().any(|tag| self.tags.contains(tag))
+            || self
+                .description_substrings
+                .iter()
+                .any(|substring| description.contains(&substring.to_lowercase()))
+            || self
+                .description_regexes
+                .iter()
+                .any(|regex| regex.0.is_match(&entry.description))
+    }
+
+    fn allows(&self, entry: &TogglEntry) -> bool {
+        (!self.billable_only || entry.billable)
+            && (self.required_tags.is_empty()
+                || entry
+                    .tags
+                    .iter()
+                    .any(|tag| self.required_tags.contains(tag)))
+            && !entry
+                .tags
+                .iter()
+                .any(|tag| self.excluded_tags.contains(tag))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DescriptionRegex(pub Regex);
+
+impl PartialEq for DescriptionRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Display for DescriptionRegex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhichWeekdays {
+    pub monday: bool,
+    pub tuesday: bool,
+    pub wednesday: bool,
+    pub thursday: bool,
+    pub friday: bool,
+    pub saturday: bool,
+    pub sunday: bool,
+}
+
+impl WhichWeekdays {
+    pub fn includes(&self, weekday: Weekday) -> bool {
+        match weekday {
+            Weekday::Mon => self.monday,
+            Weekday::Tue => self.tuesday,
+            Weekday::Wed => self.wednesday,
+            Weekday::Thu => self.thursday,
+            Weekday::Fri => self.friday,
+            Weekday::Sat => self.saturday,
+            Weekday::Sun => self.sunday,
+        }
+    }
+
+    pub fn num_days(&self) -> i32 {
+        let mut total = 0;
+
+        total += if self.monday { 1 } else { 0 };
+        total += if self.tuesday { 1 } else { 0 };
+        total += if self.wednesday { 1 } else { 0 };
+        total += if self.thursday { 1 } else { 0 };
+        total += if self.friday { 1 } else { 0 };
+        total += if self.saturday { 1 } else { 0 };
+        total += if self.sunday { 1 } else { 0 };
+
+        total
+    }
+}
+
+/// A total amount of time to spend on a project by a date, rather than a fixed amount each day
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Deadline {
+    pub date: NaiveDate,
+    #[serde(with = "milliseconds")]
+    pub total: Duration,
+}
+
+/// How long what's stored for a workspace that Toggl doesn't send webhook events for is used before
+/// it's fetched again
+const STORED_WORKSPACE_MAX_AGE: Duration = Duration::minutes(1);
+
+/// How long what's stored for a workspace that Toggl sends webhook events for is used before it's
+/// fetched again anyway, in case an event went missing
+const HOOKED_WORKSPACE_MAX_AGE: Duration = Duration::hours(1);
+
+/// How far ahead to look for the day a deadline's target will be reached
+const MAX_PROJECTION_DAYS: u32 = 3650;
+
+/// How often a limit's time becomes available again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitPeriod {
+    Day,
+    /// From Monday to Sunday
+    Week,
+}
+
+impl LimitPeriod {
+    /// The first day of the period that `date` is in
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            LimitPeriod::Day => date,
+            LimitPeriod::Week => date - Days::new(u64::from(date.weekday().num_days_from_monday())),
+        }
+    }
+
+    /// How long the period is, which is as much time as could ever be spent in it
+    pub fn length(&self) -> Duration {
+        match self {
+            LimitPeriod::Day => Duration::days(1),
+            LimitPeriod::Week => Duration::weeks(1),
+        }
+    }
+}
+
+impl Display for LimitPeriod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitPeriod::Day => write!(f, "day"),
+            LimitPeriod::Week => write!(f, "week"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Unrecognized limit period: {0}")]
+pub struct ParseLimitPeriodError(String);
+
+impl FromStr for LimitPeriod {
+    type Err = ParseLimitPeriodError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(LimitPeriod::Day),
+            "week" => Ok(LimitPeriod::Week),
+            _ => Err(ParseLimitPeriodError(s.to_string())),
+        }
+    }
+}
+
+/// The most time to spend on a project in a day or a week, rather than the least
+///
+/// Limits count every day from the project's starting date, whatever its days of the week and
+/// days off are.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Limit {
+    #[serde(with = "milliseconds")]
+    pub time: Duration,
+    pub period: LimitPeriod,
+}
+
+/// How much of a limit has been used up in the current period
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LimitUsage {
+    pub limit: Limit,
+    #[serde(with = "milliseconds")]
+    pub used: Duration,
+}
+
+impl LimitUsage {
+    /// How much time is left before the limit is reached, which is negative once it's exceeded
+    pub fn remaining(&self) -> Duration {
+        self.limit.time - self.used
+    }
+
+    pub fn exceeded(&self) -> bool {
+        self.used > self.limit.time
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Project {
+    pub name: String,
+    pub filter: GoalFilter,
+    pub starting_date: NaiveDate,
+    pub daily_goal: Duration,
+    pub days_off: HashSet<NaiveDate>,
+    pub weekdays: WhichWeekdays,
+    pub priority: i32,
+    pub weight: i32,
+    /// Replaces the daily goal if the project has one
+    pub deadline: Option<Deadline>,
+    /// Makes the project a cap rather than a goal, so it never has any debt
+    pub limit: Option<Limit>,
+    /// Kept with its settings, but left out of goals and days off
+    pub archived: bool,
+}
+
+#[derive(Debug, PartialEq)]
+struct ProjectWithDebt {
+    project: Project,
+    debt: Duration,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+pub struct TogglProject {
+    /// Archived projects are inactive
+    pub active: bool,
+    pub name: String,
+    pub id: i64,
+    pub workspace_id: WorkspaceId,
+    pub client_id: Option<i64>,
+    /// A hex color like `#2da608`
+    pub color: Option<String>,
+}
+
+impl TogglProject {
+    pub fn project_id(&self) -> ProjectId {
+        ProjectId {
+            workspace_id: self.workspace_id,
+            project_id: self.id,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TogglClient {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TogglTag {
+    pub name: String,
+}
+
+pub async fn get_workspaces(
+    toggl_api_token: &str,
+    client: Client,
+) -> AppResult<Option<Vec<Workspace>>> {
+    let response = toggl_scheduler::send(
+        toggl_api_token,
+        client.get("https://api.track.toggl.com/api/v9/workspaces"),
+    )
+    .await?;
+
+    match response.status() {
+        StatusCode::OK => Ok(response.json().await?),
+        _ => Ok(None),
+    }
+}
+
+pub async fn get_workspace_details(
+    toggl_api_token: &str,
+    workspace_id: WorkspaceId,
+    client: Client,
+) -> AppResult<Option<Workspace>> {
+    let url = format!(
+        "https://api.track.toggl.com/api/v9/workspaces/{}",
+        workspace_id,
+    );
+
+    let response = toggl_scheduler::send(toggl_api_token, client.get(&url)).await?;
+
+    match response.status() {
+        StatusCode::OK => Ok(response.json().await?),
+        _ => Ok(None),
+    }
+}
+
+pub async fn get_toggl_projects(
+    toggl_api_token: &str,
+    workspace_id: WorkspaceId,
+    client: &Client,
+) -> AppResult<Vec<TogglProject>> {
+    let all_projects =
+        get_toggl_projects_including_archived(toggl_api_token, workspace_id, client).await?;
+
+    Ok(all_projects
+        .into_iter()
+        .filter(|project| project.active)
+        .collect())
+}
+
+async fn get_toggl_projects_including_archived(
+    toggl_api_token: &str,
+    workspace_id: WorkspaceId,
+    client: &Client,
+) -> AppResult<Vec<TogglProject>> {
+    let url = format!(
+        "https://api.track.toggl.com/api/v9/workspaces/{}/projects",
+        workspace_id,
+    );
+
+    let response = toggl_scheduler::send(toggl_api_token, client.get(&url)).await?;
+
+    read_toggl_json(response).await
+}
+
+pub async fn get_toggl_clients(
+    toggl_api_token: &str,
+    workspace_id: WorkspaceId,
+    client: &Client,
+) -> AppResult<Vec<TogglClient>> {
+    let url = format!(
+        "https://api.track.toggl.com/api/v9/workspaces/{}/clients",
+        workspace_id,
+    );
+
+    let response = toggl_scheduler::send(toggl_api_token, client.get(&url)).await?;
+
+    // Toggl responds with null rather than an empty list when there are no clients
+    let all_clients: Option<Vec<TogglClient>> = read_toggl_json(response).await?;
+
+    Ok(all_clients.unwrap_or_default())
+}
+
+pub async fn get_toggl_tags(
+    toggl_api_token: &str,
+    workspace_id: WorkspaceId,
+    client: &Client,
+) -> AppResult<Vec<TogglTag>> {
+    let url = format!(
+        "https://api.track.toggl.com/api/v9/workspaces/{}/tags",
+        workspace_id,
+    );
+
+    let response = toggl_scheduler::send(toggl_api_token, client.get(&url)).await?;
+
+    // Toggl responds with null rather than an empty list when there are no tags
+    let all_tags: Option<Vec<TogglTag>> = read_toggl_json(response).await?;
+
+    Ok(all_tags.unwrap_or_default())
+}
+
+pub async fn get_user_toggl_api_token(
+    user_key: UserKey,
+    pool: &PgPool,
+    server_key: &ServerKey,
+) -> AppResult<String> {
+    let record = sqlx::query!(
+        r#"SELECT toggl_api_key_ciphertext AS "toggl_api_key_ciphertext!"
+        FROM users
+        WHERE user_key = $1"#,
+        user_key.0,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    server_key.decrypt(&record.toggl_api_key_ciphertext)
+}
+
+/// The Toggl user that the user's API token belongs to, which is looked up the first time it's
+/// needed
+pub async fn get_toggl_user_id(
+    user_key: UserKey,
+    toggl_api_token: &str,
+    client: &Client,
+    pool: &PgPool,
+) -> AppResult<TogglUserId> {
+    if let Some(toggl_user_id) = get_stored_toggl_user_id(user_key, pool).await? {
+        return Ok(toggl_user_id);
+    }
+
+    let response = toggl_scheduler::send(
+        toggl_api_token,
+        client.get("https://api.track.toggl.com/api/v9/me"),
+    )
+    .await?;
+
+    let me: TogglMe = read_toggl_json(response).await?;
+
+    sqlx::query!(
+        "UPDATE users
+        SET toggl_user_id = $1
+        WHERE user_key = $2",
+        me.id.0,
+        user_key.0,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(me.id)
+}
+
+/// The Toggl user that the user's API token belongs to, if it's been looked up since the token was
+/// last replaced
+async fn get_stored_toggl_user_id(
+    user_key: UserKey,
+    pool: &PgPool,
+) -> AppResult<Option<TogglUserId>> {
+    let record = sqlx::query!(
+        "SELECT toggl_user_id
+        FROM users
+        WHERE user_key = $1",
+        user_key.0,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(record.toggl_user_id.map(TogglUserId))
+}
+
+pub async fn get_user_today(user_key: UserKey, pool: &PgPool) -> AppResult<NaiveDate> {
+    let record = sqlx::query!("SELECT timezone FROM users WHERE user_key = $1", user_key.0,)
+        .fetch_one(pool)
+        .await?;
+
+    today_in_timezone(&record.timezone)
+}
+
+pub async fn get_user_workspace_ids(
+    user_key: UserKey,
+    pool: &PgPool,
+) -> AppResult<Vec<WorkspaceId>> {
+    let records = sqlx::query!(
+        "SELECT workspace_id
+        FROM users_to_workspaces
+        WHERE user_key = $1
+        ORDER BY workspace_id",
+        user_key.0,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| WorkspaceId(record.workspace_id))
+        .collect())
+}
+
+pub async fn get_all_toggl_projects(
+    toggl_api_token: &str,
+    workspace_ids: &[WorkspaceId],
+    client: &Client,
+) -> AppResult<Vec<TogglProject>> {
+    let workspace_projects = future::try_join_all(
+        workspace_ids
+            .iter()
+            .map(|&workspace_id| get_toggl_projects(toggl_api_token, workspace_id, client)),
+    )
+    .await?;
+
+    Ok(workspace_projects.into_iter().flatten().collect())
+}
+
+/// Every project in the workspaces, including the ones that are archived in Toggl
+pub async fn get_all_toggl_projects_including_archived(
+    toggl_api_token: &str,
+    workspace_ids: &[WorkspaceId],
+    client: &Client,
+) -> AppResult<Vec<TogglProject>> {
+    let workspace_projects = future::try_join_all(workspace_ids.iter().map(|&workspace_id| {
+        get_toggl_projects_including_archived(toggl_api_token, workspace_id, client)
+    }))
+    .await?;
+
+    Ok(workspace_projects.into_iter().flatten().collect())
+}
+
+pub async fn calculate_goals(
+    user_key: UserKey,
+    pool: PgPool,
+    client: Client,
+    server_key: ServerKey,
+) -> AppResult<Option<Status>> {
+    let record = sqlx::query!(
+        "SELECT daily_max, timezone
+        FROM users
+        WHERE user_key = $1",
+        user_key.0,
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    let toggl_api_key = get_user_toggl_api_token(user_key, &pool, &server_key).await?;
+
+    let workspace_ids = get_user_workspace_ids(user_key, &pool).await?;
+
+    let projects =
+        get_user_projects(&toggl_api_key, &workspace_ids, &client, user_key, &pool).await?;
+
+    Ok(if !projects.is_empty() {
+        let earliest_start = earliest_start_date(&projects);
+
+        let tz: Tz = record
+            .timezone
+            .parse()
+            .map_err(AppError::UnrecognizedTimezone)?;
+
+        let toggl_entries = get_toggl_entries(
+            user_key,
+            &workspace_ids,
+            &toggl_api_key,
+            &earliest_start,
+            tz,
+            &client,
+            &pool,
+        )
+        .await?;
+
+        let today = today_in_timezone(&record.timezone)?;
+
+        let logged: HashMap<ProjectKey, (Duration, Duration, Option<LimitUsage>)> = projects
+            .iter()
+            .map(|(&project_key, project)| {
+                (
+                    project_key,
+                    (
+                        time_logged_on(&toggl_entries, project, today),
+                        time_logged_until(&toggl_entries, project, today),
+                        limit_usage(&toggl_entries, project, today),
+                    ),
+                )
+            })
+            .collect();
+
+        // Time spent on a limit isn't owed to anything, so limits stay out of the debts
+        let (limits, projects): (HashMap<ProjectKey, Project>, HashMap<ProjectKey, Project>) =
+            projects
+                .into_iter()
+                .partition(|(_, project)| project.limit.is_some());
+
+        let (project_debts, total_debt) = process_toggl_data(
+            toggl_entries,
+            projects,
+            Duration::seconds(record.daily_max),
+            today,
+        );
+
+        let limits = limits.into_iter().map(|(project_key, project)| {
+            (
+                project_key,
+                ProjectWithDebt {
+                    project,
+                    debt: Duration::zero(),
+                },
+            )
+        });
+
+        let mut goals: Vec<Goal> = project_debts
+            .into_iter()
+            .chain(limits)
+            .map(|(project_key, ProjectWithDebt { project, debt })| {
+                let mut project_ids: Vec<ProjectId> =
+                    project.filter.project_ids.iter().copied().collect();
+                project_ids
+                    .sort_by_key(|project_id| (project_id.workspace_id, project_id.project_id));
+
+                let (logged_today, logged_total, limit) = logged
+                    .get(&project_key)
+                    .copied()
+                    .unwrap_or((Duration::zero(), Duration::zero(), None));
+
+                Goal {
+                    project_key,
+                    project_ids,
+                    time: debt,
+                    goal_today: project.goal_on(today, logged_total - logged_today),
+                    logged_today,
+                    day_off_today: project.days_off.contains(&today),
+                    deadline: project.deadline,
+                    projected_finish: project.projected_finish(logged_total, today),
+                    priority: project.priority,
+                    weight: project.weight,
+                    share_today: Duration::zero(),
+                    limit,
+                    name: project.name,
+                }
+            })
+            .collect();
+        goals.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+
+        // The total debt is capped at the daily max, so split what's left of it between projects
+        let claims: Vec<Claim> = goals
+            .iter()
+            .map(|goal| Claim {
+                owed: goal.time,
+                priority: goal.priority,
+                weight: goal.weight,
+            })
+            .collect();
+
+        for (goal, share) in goals.iter_mut().zip(share(&claims, total_debt)) {
+            goal.share_today = share;
+        }
+
+        Some(Status {
+            goals,
+            total_debt,
+            today,
+        })
+    } else {
+        None
+    })
+}
+
+/// Every project in the workspaces, including the ones that are archived in Toggl, or the ones the
+/// user's projects count as they were last seen if Toggl can't be reached
+pub async fn get_toggl_projects_or_stored(
+    toggl_api_token: &str,
+    workspace_ids: &[WorkspaceId],
+    client: &Client,
+    user_key: UserKey,
+    pool: &PgPool,
+) -> AppResult<Vec<TogglProject>> {
+    match get_all_toggl_projects_including_archived(toggl_api_token, workspace_ids, client).await {
+        Err(error) if error.toggl_unreachable() => {
+            warn!("Using last known Toggl projects, since Toggl can't be reached: {error}");
+
+            get_last_known_toggl_projects(user_key, workspace_ids, pool).await
+        }
+        result => result,
+    }
+}
+
+/// The user's projects that aren't archived
+pub async fn get_user_projects(
+    toggl_api_token: &str,
+    workspace_ids: &[WorkspaceId],
+    client: &Client,
+    user_key: UserKey,
+    pool: &PgPool,
+) -> AppResult<HashMap<ProjectKey, Project>> {
+    let toggl_user_id = get_toggl_user_id(user_key, toggl_api_token, client, pool).await?;
+    let stored_workspaces = get_stored_workspaces(toggl_user_id, workspace_ids, pool).await?;
+    let now = Utc::now();
+
+    // Workspaces that were stored recently enough don't need their projects fetched
+    let (stored, fetched): (Vec<WorkspaceId>, Vec<WorkspaceId>) =
+        workspace_ids.iter().copied().partition(|workspace_id| {
+            stored_workspaces
+                .get(workspace_id)
+                .is_some_and(|stored_workspace| stored_workspace.is_fresh(now))
+        });
+
+    let mut toggl_projects =
+        get_toggl_projects_or_stored(toggl_api_token, &fetched, client, user_key, pool).await?;
+    toggl_projects.extend(get_stored_toggl_projects(toggl_user_id, &stored, pool).await?);
+
+    let mut projects =
+        get_user_projects_from_toggl_projects(&toggl_projects, user_key, pool).await?;
+    projects.retain(|_, project| !project.archived);
+
+    Ok(projects)
+}
+
+/// Every one of the user's projects, named after the Toggl projects they count if they weren't
+/// given names
+///
+/// The names of the Toggl projects are remembered, so that projects counting ones that have since
+/// been archived or deleted are still named after them.
+pub async fn get_user_projects_from_toggl_projects(
+    toggl_projects: &[TogglProject],
+    user_key: UserKey,
+    pool: &PgPool,
+) -> AppResult<HashMap<ProjectKey, Project>> {
+    let settings = get_all_project_settings(user_key, pool).await?;
+
+    remember_toggl_projects(toggl_projects, user_key, pool).await?;
+    let last_known_names = get_last_known_toggl_project_names(user_key, pool).await?;
+
+    let project_id_to_name: HashMap<ProjectId, &str> = last_known_names
+        .iter()
+        .map(|(project_id, name)| (*project_id, name.as_str()))
+        .collect();
+
+    let archived_projects: HashSet<ProjectKey> = sqlx::query!(
+        "SELECT project_key
+        FROM projects
+        WHERE user_key = $1
+        AND archived",
+        user_key.0,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|record| ProjectKey(record.project_key))
+    .collect();
+
+    let mut futures = Vec::new();
+    for (project_key, settings) in settings {
+        let filter = settings.filter()?;
+
+        let project_name = settings
+            .name
+            .unwrap_or_else(|| default_project_name(&filter, &project_id_to_name));
+
+        let archived = archived_projects.contains(&project_key);
+
+        futures.push(async move {
+            let days_off = sqlx::query!(
+                "SELECT days_off.day_off
+                FROM days_off
+                INNER JOIN days_off_to_projects
+                ON days_off.day_off_key = days_off_to_projects.day_off_key
+                WHERE days_off_to_projects.project_key = $1",
+                project_key.0,
+            )
+            .fetch_all(pool)
+            .await?;
+
+            Ok((
+                project_key,
+                Project {
+                    name: project_name,
+                    filter,
+                    starting_date: settings.starting_date,
+                    daily_goal: settings.daily_goal,
+                    days_off: days_off.into_iter().map(|record| record.day_off).collect(),
+                    weekdays: settings.weekdays,
+                    priority: settings.priority,
+                    weight: settings.weight,
+                    deadline: settings.deadline,
+                    limit: settings.limit,
+                    archived,
+                },
+            ))
+        });
+    }
+
+    let future_results: Vec<AppResult<(ProjectKey, Project)>> = future::join_all(futures).await;
+
+    future_results
+        .into_iter()
+        .try_fold(HashMap::new(), |mut acc, futures_result| {
+            let (project_key, project) = futures_result?;
+            acc.insert(project_key, project);
+            Ok(acc)
+        })
+}
+
+/// Remember the current names and colors of the Toggl projects counted by the user's projects, and
+/// whether they're active
+async fn remember_toggl_projects(
+    toggl_projects: &[TogglProject],
+    user_key: UserKey,
+    pool: &PgPool,
+) -> AppResult<()> {
+    let workspace_ids: Vec<i64> = toggl_projects
+        .iter()
+        .map(|project| project.workspace_id.0)
+        .collect();
+    let project_ids: Vec<i64> = toggl_projects.iter().map(|project| project.id).collect();
+    let names: Vec<String> = toggl_projects
+        .iter()
+        .map(|project| project.name.clone())
+        .collect();
+    let colors: Vec<Option<String>> = toggl_projects
+        .iter()
+        .map(|project| project.color.clone())
+        .collect();
+    let active: Vec<bool> = toggl_projects
+        .iter()
+        .map(|project| project.active)
+        .collect();
+
+    sqlx::query!(
+        "UPDATE projects_to_toggl_projects
+        SET last_known_name = toggl_projects.name,
+            last_known_color = toggl_projects.color,
+            last_known_active = toggl_projects.active
+        FROM UNNEST($2::BIGINT[], $3::BIGINT[], $4::TEXT[], $5::TEXT[], $6::BOOLEAN[])
+            AS toggl_projects(workspace_id, project_id, name, color, active),
+            projects
+        WHERE projects_to_toggl_projects.project_key = projects.project_key
+        AND projects.user_key = $1
+        AND projects_to_toggl_projects.workspace_id = toggl_projects.workspace_id
+        AND projects_to_toggl_projects.project_id = toggl_projects.project_id
+        AND (projects_to_toggl_projects.last_known_name, projects_to_toggl_projects.last_known_color,
+            projects_to_toggl_projects.last_known_active)
+            IS DISTINCT FROM (toggl_projects.name, toggl_projects.color, toggl_projects.active)",
+        user_key.0,
+        &workspace_ids,
+        &project_ids,
+        &names,
+        &colors as &[Option<String>],
+        &active,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Remember everything that was just stored for the user of the Toggl projects counted by their
+/// projects, including their clients, which are only fetched when syncing
+async fn remember_stored_toggl_projects(
+    user_key: UserKey,
+    toggl_user_id: TogglUserId,
+    pool: &PgPool,
+) -> AppResult<()> {
+    sqlx::query!(
+        "UPDATE projects_to_toggl_projects
+        SET last_known_name = toggl_projects.name,
+            last_known_color = toggl_projects.color,
+            last_known_client = toggl_projects.client,
+            last_known_active = toggl_projects.active
+        FROM toggl_projects, projects
+        WHERE projects_to_toggl_projects.project_key = projects.project_key
+        AND projects.user_key = $1
+        AND toggl_projects.toggl_user_id = $2
+        AND projects_to_toggl_projects.workspace_id = toggl_projects.workspace_id
+        AND projects_to_toggl_projects.project_id = toggl_projects.project_id
+        AND (projects_to_toggl_projects.last_known_name, projects_to_toggl_projects.last_known_color,
+            projects_to_toggl_projects.last_known_client,
+            projects_to_toggl_projects.last_known_active)
+            IS DISTINCT FROM (toggl_projects.name, toggl_projects.color, toggl_projects.client,
+            toggl_projects.active)",
+        user_key.0,
+        toggl_user_id.0,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The Toggl projects counted by the user's projects in the workspaces, as they were last seen
+async fn get_last_known_toggl_projects(
+    user_key: UserKey,
+    workspace_ids: &[WorkspaceId],
+    pool: &PgPool,
+) -> AppResult<Vec<TogglProject>> {
+    let workspace_ids: Vec<i64> = workspace_ids
+        .iter()
+        .map(|workspace_id| workspace_id.0)
+        .collect();
+
+    // Several of the user's projects can count the same Toggl project
+    let records = sqlx::query!(
+        r#"SELECT DISTINCT ON (projects_to_toggl_projects.workspace_id,
+                projects_to_toggl_projects.project_id)
+            projects_to_toggl_projects.workspace_id, projects_to_toggl_projects.project_id,
+            projects_to_toggl_projects.last_known_name AS "name!",
+            projects_to_toggl_projects.last_known_color AS color,
+            projects_to_toggl_projects.last_known_active AS active
+        FROM projects_to_toggl_projects
+        INNER JOIN projects
+        ON projects.project_key = projects_to_toggl_projects.project_key
+        WHERE projects.user_key = $1
+        AND projects_to_toggl_projects.workspace_id = ANY($2::BIGINT[])
+        AND projects_to_toggl_projects.last_known_name IS NOT NULL"#,
+        user_key.0,
+        &workspace_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| TogglProject {
+            // Links that haven't been refreshed since this started being remembered don't say
+            active: record.active.unwrap_or(true),
+            name: record.name,
+            id: record.project_id,
+            workspace_id: WorkspaceId(record.workspace_id),
+            client_id: None,
+            color: record.color,
+        })
+        .collect())
+}
+
+/// The name each Toggl project counted by the user's projects had when it was last seen
+pub async fn get_last_known_toggl_project_names(
+    user_key: UserKey,
+    pool: &PgPool,
+) -> AppResult<HashMap<ProjectId, String>> {
+    let records = sqlx::query!(
+        "SELECT projects_to_toggl_projects.workspace_id, projects_to_toggl_projects.project_id,
+            projects_to_toggl_projects.last_known_name
+        FROM projects_to_toggl_projects
+        INNER JOIN projects
+        ON projects.project_key = projects_to_toggl_projects.project_key
+        WHERE projects.user_key = $1
+        AND projects_to_toggl_projects.last_known_name IS NOT NULL",
+        user_key.0,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .filter_map(|record| {
+            Some((
+                ProjectId {
+                    workspace_id: WorkspaceId(record.workspace_id),
+                    project_id: record.project_id,
+                },
+                record.last_known_name?,
+            ))
+        })
+        .collect())
+}
+
+/// The color each Toggl project counted by the user's projects had when it was last seen
+pub async fn get_toggl_project_colors(
+    user_key: UserKey,
+    pool: &PgPool,
+) -> AppResult<HashMap<ProjectId, String>> {
+    let records = sqlx::query!(
+        r#"SELECT projects_to_toggl_projects.workspace_id, projects_to_toggl_projects.project_id,
+            projects_to_toggl_projects.last_known_color AS "color!"
+        FROM projects_to_toggl_projects
+        INNER JOIN projects
+        ON projects.project_key = projects_to_toggl_projects.project_key
+        WHERE projects.user_key = $1
+        AND projects_to_toggl_projects.last_known_color IS NOT NULL"#,
+        user_key.0,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        // They end up in style attributes, so anything else that Toggl sends is left out
+        .filter(|record| is_hex_color(&record.color))
+        .map(|record| {
+            (
+                ProjectId {
+                    workspace_id: WorkspaceId(record.workspace_id),
+                    project_id: record.project_id,
+                },
+                record.color,
+            )
+        })
+        .collect())
+}
+
+/// Whether a color is written like `#2da608`
+pub fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Name a project that wasn't given one after whatever its filter matches
+fn default_project_name(
+    filter: &GoalFilter,
+    project_id_to_name: &HashMap<ProjectId, &str>,
+) -> String {
+    let mut project_names: Vec<String> = filter
+        .project_ids
+        .iter()
+        .map(|project_id| match project_id_to_name.get(project_id) {
+            Some(name) => name.to_string(),
+            None => format!("Unknown project {}", project_id),
+        })
+        .collect();
+    project_names.sort();
+
+    if filter.no_project {
+        project_names.push(String::from("No project"));
+    }
+
+    let mut clients: Vec<String> = filter.clients.iter().cloned().collect();
+    clients.sort();
+
+    let mut tags: Vec<String> = filter.tags.iter().map(|tag| format!("#{}", tag)).collect();
+    tags.sort();
+
+    let mut descriptions: Vec<String> = filter
+        .description_substrings
+        .iter()
+        .map(|substring| format!("\"{}\"", substring))
+        .chain(
+            filter
+                .description_regexes
+                .iter()
+                .map(|regex| format!("/{}/", regex)),
+        )
+        .collect();
+    descriptions.sort();
+
+    let parts: Vec<String> = project_names
+        .into_iter()
+        .chain(clients)
+        .chain(tags)
+        .chain(descriptions)
+        .collect();
+
+    parts.join(", ")
+}
+
+fn earliest_start_date(projects: &HashMap<ProjectKey, Project>) -> NaiveDate {
+    let mut earliest = NaiveDate::MAX;
+
+    for project in projects.values() {
+        if project.starting_date < earliest {
+            earliest = project.starting_date;
         }
     }
+
+    earliest
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct WhichWeekdays {
-    pub monday: bool,
-    pub tuesday: bool,
-    pub wednesday: bool,
-    pub thursday: bool,
-    pub friday: bool,
-    pub saturday: bool,
-    pub sunday: bool,
+/// The entries of every workspace from `since` that the user can see, which are fetched from Toggl
+/// first for any workspace that wasn't stored recently enough
+async fn get_toggl_entries(
+    user_key: UserKey,
+    workspace_ids: &[WorkspaceId],
+    api_token: &str,
+    since: &NaiveDate,
+    tz: Tz,
+    client: &Client,
+    pool: &PgPool,
+) -> AppResult<Vec<TogglEntry>> {
+    let toggl_user_id =
+        sync_toggl_workspaces(user_key, workspace_ids, api_token, since, client, pool).await?;
+
+    get_stored_toggl_entries(toggl_user_id, workspace_ids, since, tz, pool).await
 }
 
-impl WhichWeekdays {
-    pub fn num_days(&self) -> i32 {
-        let mut total = 0;
+/// What's stored for a workspace, as a Toggl user sees it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoredWorkspace {
+    /// Whether Toggl sends webhook events for the workspace, which keep what's stored up to date
+    pub hooked: bool,
+    /// The day the stored entries go back to, once they've been fetched
+    pub entries_since: Option<NaiveDate>,
+    pub synced_at: Option<DateTime<Utc>>,
+}
 
-        total += if self.monday { 1 } else { 0 };
-        total += if self.tuesday { 1 } else { 0 };
-        total += if self.wednesday { 1 } else { 0 };
-        total += if self.thursday { 1 } else { 0 };
-        total += if self.friday { 1 } else { 0 };
-        total += if self.saturday { 1 } else { 0 };
-        total += if self.sunday { 1 } else { 0 };
+impl StoredWorkspace {
+    /// Whether what's stored can be used rather than fetching it from Toggl again
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        let max_age = if self.hooked {
+            HOOKED_WORKSPACE_MAX_AGE
+        } else {
+            STORED_WORKSPACE_MAX_AGE
+        };
 
-        total
+        self.entries_since.is_some()
+            && self
+                .synced_at
+                .is_some_and(|synced_at| now - synced_at < max_age)
     }
-}
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Project {
-    pub name: String,
-    pub starting_date: NaiveDate,
-    pub daily_goal: Duration,
-    pub days_off: HashSet<NaiveDate>,
-    pub weekdays: WhichWeekdays,
+    /// Whether entries from `since` have to be fetched from Toggl before they can be used
+    pub fn needs_sync(&self, since: NaiveDate, now: DateTime<Utc>) -> bool {
+        !self.is_fresh(now)
+            || self
+                .entries_since
+                .is_some_and(|entries_since| since < entries_since)
+    }
 }
 
-#[derive(Debug, PartialEq)]
-struct ProjectWithDebt {
-    project: Project,
-    debt: Duration,
-}
+pub async fn get_stored_workspaces(
+    toggl_user_id: TogglUserId,
+    workspace_ids: &[WorkspaceId],
+    pool: &PgPool,
+) -> AppResult<HashMap<WorkspaceId, StoredWorkspace>> {
+    let workspace_ids: Vec<i64> = workspace_ids
+        .iter()
+        .map(|workspace_id| workspace_id.0)
+        .collect();
 
-#[derive(Debug, Deserialize)]
-pub struct TogglProject {
-    active: bool,
-    pub name: String,
-    pub id: ProjectId,
+    let records = sqlx::query!(
+        "SELECT workspace_id, hooked_at, entries_since, synced_at
+        FROM toggl_workspaces
+        WHERE toggl_user_id = $1
+        AND workspace_id = ANY($2::BIGINT[])",
+        toggl_user_id.0,
+        &workspace_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            (
+                WorkspaceId(record.workspace_id),
+                StoredWorkspace {
+                    hooked: record.hooked_at.is_some(),
+                    entries_since: record.entries_since,
+                    synced_at: record.synced_at,
+                },
+            )
+        })
+        .collect())
 }
 
-pub async fn get_workspaces(
-    toggl_api_token: &str,
-    client: Client,
-) -> InternalResult<Option<Vec<Workspace>>> {
-    loop {
-        let response = client
-            .get("https://api.track.toggl.com/api/v9/workspaces")
-            .basic_auth(toggl_api_token, Some("api_token"))
-            .send()
-            .await?;
+/// Fetch the entries from `since` and the projects of any of the workspaces that weren't stored
+/// recently enough for the user, or that don't have entries stored from that far back
+///
+/// Returns the user's Toggl user ID, which what's stored for them is kept under.
+pub async fn sync_toggl_workspaces(
+    user_key: UserKey,
+    workspace_ids: &[WorkspaceId],
+    api_token: &str,
+    since: &NaiveDate,
+    client: &Client,
+    pool: &PgPool,
+) -> AppResult<TogglUserId> {
+    let toggl_user_id = get_toggl_user_id(user_key, api_token, client, pool).await?;
+    let stored_workspaces = get_stored_workspaces(toggl_user_id, workspace_ids, pool).await?;
+    let now = Utc::now();
 
-        match response.status() {
-            StatusCode::TOO_MANY_REQUESTS => {
-                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-            }
-            StatusCode::OK => break Ok(response.json().await?),
-            _ => break Ok(None),
+    let mut syncs = Vec::new();
+    for &workspace_id in workspace_ids {
+        let stored_workspace = stored_workspaces.get(&workspace_id);
+
+        if stored_workspace
+            .is_some_and(|stored_workspace| !stored_workspace.needs_sync(*since, now))
+        {
+            continue;
         }
+
+        // Entries from further back might have been needed before, when a project started earlier
+        let since = stored_workspace
+            .and_then(|stored_workspace| stored_workspace.entries_since)
+            .map_or(*since, |entries_since| entries_since.min(*since));
+
+        syncs.push(async move {
+            sync_toggl_workspace(toggl_user_id, workspace_id, api_token, &since, client, pool).await
+        });
     }
-}
 
-pub async fn get_workspace_details(
-    toggl_api_token: &str,
-    workspace_id: WorkspaceId,
-    client: Client,
-) -> InternalResult<Option<Workspace>> {
-    let url = format!(
-        "https://api.track.toggl.com/api/v9/workspaces/{}",
-        workspace_id,
-    );
+    future::try_join_all(syncs).await?;
 
-    loop {
-        let response = client
-            .get(&url)
-            .basic_auth(toggl_api_token, Some("api_token"))
-            .send()
-            .await?;
+    remember_stored_toggl_projects(user_key, toggl_user_id, pool).await?;
 
-        match response.status() {
-            StatusCode::TOO_MANY_REQUESTS => {
-                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-            }
-            StatusCode::OK => break Ok(response.json().await?),
-            _ => break Ok(None),
-        }
-    }
+    Ok(toggl_user_id)
 }
 
-pub async fn get_toggl_projects(
-    toggl_api_token: &str,
+/// Replace what's stored of a workspace for a Toggl user with what Toggl shows their API token,
+/// from `since`
+///
+/// Only what's stored for that user is replaced, since someone else sharing the workspace might
+/// see different entries and projects in it.
+async fn sync_toggl_workspace(
+    toggl_user_id: TogglUserId,
     workspace_id: WorkspaceId,
+    api_token: &str,
+    since: &NaiveDate,
     client: &Client,
-) -> InternalResult<Vec<TogglProject>> {
-    let url = format!(
-        "https://api.track.toggl.com/api/v9/workspaces/{}/projects",
-        workspace_id,
+    pool: &PgPool,
+) -> AppResult<()> {
+    trace!(
+        "Storing Toggl entries (user {}, workspace {}, since {})",
+        toggl_user_id.0,
+        workspace_id.0,
+        since,
     );
 
-    loop {
-        let response = client
-            .get(&url)
-            .basic_auth(toggl_api_token, Some("api_token"))
-            .send()
-            .await?;
+    let (report_data, toggl_projects, toggl_clients) = future::try_join3(
+        get_toggl_report_data(workspace_id, api_token, since, client),
+        get_toggl_projects_including_archived(api_token, workspace_id, client),
+        get_toggl_clients(api_token, workspace_id, client),
+    )
+    .await?;
 
-        if let StatusCode::TOO_MANY_REQUESTS = response.status() {
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-        } else {
-            let all_projects: Vec<TogglProject> = response.json().await?;
+    let client_names: HashMap<i64, String> = toggl_clients
+        .into_iter()
+        .map(|toggl_client| (toggl_client.id, toggl_client.name))
+        .collect();
 
-            break Ok(all_projects
-                .into_iter()
-                .filter(|project| project.active)
-                .collect());
-        }
-    }
-}
+    let mut transaction = pool.begin().await?;
 
-pub async fn calculate_goals(
-    user_key: UserKey,
-    pool: PgPool,
-    client: Client,
-) -> InternalResult<Option<(Vec<Goal>, Duration)>> {
-    let record = sqlx::query!(
-        "SELECT toggl_api_key, workspace_id, daily_max, timezone
-        FROM users
-        WHERE user_key = $1",
-        user_key.0,
+    sqlx::query!(
+        "INSERT INTO toggl_workspaces(toggl_user_id, workspace_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING",
+        toggl_user_id.0,
+        workspace_id.0,
     )
-    .fetch_one(&pool)
+    .execute(&mut *transaction)
     .await?;
 
-    let projects = get_user_projects(
-        &record.toggl_api_key,
-        WorkspaceId(record.workspace_id),
-        &client,
-        user_key,
-        &pool,
+    sqlx::query!(
+        "DELETE FROM toggl_entries WHERE toggl_user_id = $1 AND workspace_id = $2",
+        toggl_user_id.0,
+        workspace_id.0,
     )
+    .execute(&mut *transaction)
     .await?;
 
-    Ok(if !projects.is_empty() {
-        let earliest_start = earliest_start_date(&projects);
+    for response_data in report_data {
+        sqlx::query!(
+            "INSERT INTO toggl_entries(toggl_user_id, workspace_id, entry_id, project_id,
+                description, client, tags, billable, start, duration)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT DO NOTHING",
+            toggl_user_id.0,
+            workspace_id.0,
+            response_data.id,
+            response_data.pid,
+            response_data.description.unwrap_or_default(),
+            response_data.client,
+            &response_data.tags,
+            response_data.is_billable,
+            response_data.start.with_timezone(&Utc),
+            response_data.dur,
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
 
-        let toggl_entries = get_raw_toggl_data(
-            WorkspaceId(record.workspace_id),
-            &record.toggl_api_key,
-            &earliest_start,
-            &client,
+    sqlx::query!(
+        "DELETE FROM toggl_projects WHERE toggl_user_id = $1 AND workspace_id = $2",
+        toggl_user_id.0,
+        workspace_id.0,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    for toggl_project in toggl_projects {
+        sqlx::query!(
+            "INSERT INTO toggl_projects(toggl_user_id, workspace_id, project_id, name, active,
+                client, color)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            toggl_user_id.0,
+            workspace_id.0,
+            toggl_project.id,
+            toggl_project.name,
+            toggl_project.active,
+            toggl_project
+                .client_id
+                .and_then(|client_id| client_names.get(&client_id)),
+            toggl_project.color,
         )
+        .execute(&mut *transaction)
         .await?;
+    }
 
-        let (project_debts, total_debt) = process_toggl_data(
-            toggl_entries,
-            projects,
-            Duration::seconds(record.daily_max),
-            today_in_timezone(&record.timezone)?,
-        );
+    sqlx::query!(
+        "UPDATE toggl_workspaces
+        SET entries_since = $3, synced_at = now()
+        WHERE toggl_user_id = $1
+        AND workspace_id = $2",
+        toggl_user_id.0,
+        workspace_id.0,
+        since,
+    )
+    .execute(&mut *transaction)
+    .await?;
 
-        let mut goals: Vec<Goal> = project_debts
-            .into_iter()
-            .map(|(_, ProjectWithDebt { project, debt })| Goal {
-                name: project.name,
-                time: debt,
-            })
-            .collect();
-        goals.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+    transaction.commit().await?;
 
-        Some((goals, total_debt))
-    } else {
-        None
-    })
+    Ok(())
 }
 
-pub async fn get_user_projects(
-    toggl_api_token: &str,
-    workspace_id: WorkspaceId,
-    client: &Client,
-    user_key: UserKey,
+/// The entries of workspaces from `since` that are stored for a Toggl user, dated in the user's
+/// timezone
+async fn get_stored_toggl_entries(
+    toggl_user_id: TogglUserId,
+    workspace_ids: &[WorkspaceId],
+    since: &NaiveDate,
+    tz: Tz,
     pool: &PgPool,
-) -> InternalResult<HashMap<ProjectId, Project>> {
-    let toggl_projects = get_toggl_projects(toggl_api_token, workspace_id, client).await?;
+) -> AppResult<Vec<TogglEntry>> {
+    let workspace_ids: Vec<i64> = workspace_ids
+        .iter()
+        .map(|workspace_id| workspace_id.0)
+        .collect();
 
-    get_user_projects_from_toggl_projects(toggl_projects, user_key, pool).await
-}
+    // A day starts less than a day before it does in UTC in every timezone
+    let earliest_start = (*since - Days::new(1)).and_time(NaiveTime::MIN).and_utc();
 
-async fn get_user_projects_from_toggl_projects(
-    toggl_projects: Vec<TogglProject>,
-    user_key: UserKey,
-    pool: &PgPool,
-) -> InternalResult<HashMap<ProjectId, Project>> {
     let records = sqlx::query!(
-        "SELECT project_key, project_id, starting_date, daily_goal,
-            monday, tuesday, wednesday, thursday, friday, saturday, sunday
-        FROM projects
-        WHERE user_key = $1",
-        user_key.0,
+        "SELECT workspace_id, project_id, description, client, tags, billable, start, duration
+        FROM toggl_entries
+        WHERE toggl_user_id = $1
+        AND workspace_id = ANY($2::BIGINT[])
+        AND start >= $3",
+        toggl_user_id.0,
+        &workspace_ids,
+        earliest_start,
     )
     .fetch_all(pool)
     .await?;
 
-    let project_id_to_name: HashMap<ProjectId, String> = toggl_projects
-        .into_iter()
-        .map(|project| (project.id, project.name))
-        .collect();
-
-    let mut futures = Vec::new();
-    for record in records {
-        if let Some(project_name) = project_id_to_name.get(&ProjectId(record.project_id)) {
-            futures.push(async move {
-                let days_off = sqlx::query!(
-                    "SELECT days_off.day_off
-                    FROM days_off
-                    INNER JOIN days_off_to_projects
-                    ON days_off.day_off_key = days_off_to_projects.day_off_key
-                    WHERE days_off_to_projects.project_key = $1",
-                    record.project_key,
-                )
-                .fetch_all(pool)
-                .await?;
-
-                let weekdays = WhichWeekdays {
-                    monday: record.monday,
-                    tuesday: record.tuesday,
-                    wednesday: record.wednesday,
-                    thursday: record.thursday,
-                    friday: record.friday,
-                    saturday: record.saturday,
-                    sunday: record.sunday,
-                };
-
-                Ok((
-                    ProjectId(record.project_id),
-                    Project {
-                        name: project_name.clone(),
-                        starting_date: record.starting_date,
-                        daily_goal: Duration::seconds(record.daily_goal),
-                        days_off: days_off.into_iter().map(|record| record.day_off).collect(),
-                        weekdays,
-                    },
-                ))
-            });
-        }
-    }
-
-    let future_results: Vec<InternalResult<(ProjectId, Project)>> = future::join_all(futures).await;
-
-    future_results
+    Ok(records
         .into_iter()
-        .try_fold(HashMap::new(), |mut acc, futures_result| {
-            let (project_id, project) = futures_result?;
-            acc.insert(project_id, project);
-            Ok(acc)
+        .map(|record| TogglEntry {
+            project_id: record.project_id.map(|project_id| ProjectId {
+                workspace_id: WorkspaceId(record.workspace_id),
+                project_id,
+            }),
+            description: record.description,
+            client: record.client,
+            tags: record.tags,
+            billable: record.billable,
+            date: record.start.with_timezone(&tz).date_naive(),
+            duration: Duration::milliseconds(record.duration),
         })
+        .filter(|entry| entry.date >= *since)
+        .collect())
 }
 
-fn earliest_start_date(projects: &HashMap<ProjectId, Project>) -> NaiveDate {
-    let mut earliest = NaiveDate::MAX;
+/// The projects of workspaces that are stored for a Toggl user, including the ones that are
+/// archived in Toggl
+async fn get_stored_toggl_projects(
+    toggl_user_id: TogglUserId,
+    workspace_ids: &[WorkspaceId],
+    pool: &PgPool,
+) -> AppResult<Vec<TogglProject>> {
+    let workspace_ids: Vec<i64> = workspace_ids
+        .iter()
+        .map(|workspace_id| workspace_id.0)
+        .collect();
 
-    for project in projects.values() {
-        if project.starting_date < earliest {
-            earliest = project.starting_date;
-        }
-    }
+    let records = sqlx::query!(
+        "SELECT workspace_id, project_id, name, active, color
+        FROM toggl_projects
+        WHERE toggl_user_id = $1
+        AND workspace_id = ANY($2::BIGINT[])",
+        toggl_user_id.0,
+        &workspace_ids,
+    )
+    .fetch_all(pool)
+    .await?;
 
-    earliest
+    Ok(records
+        .into_iter()
+        .map(|record| TogglProject {
+            active: record.active,
+            name: record.name,
+            id: record.project_id,
+            workspace_id: WorkspaceId(record.workspace_id),
+            client_id: None,
+            color: record.color,
+        })
+        .collect())
 }
 
-async fn get_raw_toggl_data(
+async fn get_toggl_report_data(
     workspace_id: WorkspaceId,
     api_token: &str,
     since: &NaiveDate,
     client: &Client,
-) -> InternalResult<Vec<TogglEntry>> {
+) -> AppResult<Vec<TogglResponseData>> {
     // Make one call to the API to determine the total number of pages
     let initial_call = call_toggl_api(workspace_id, api_token, since, client, 1).await?;
 
@@ -383,26 +1570,13 @@ async fn get_raw_toggl_data(
     )
     .await;
 
-    // Collect the results from the initial call into a Vec
-    let toggl_entires_from_initial_call: Vec<TogglEntry> = initial_call
-        .data
+    // Fold the results from the subsequent calls into the results from the initial call
+    subsequent_calls
         .into_iter()
-        .map(TogglResponseData::into)
-        .collect();
-
-    // Fold the results from the subsequent calls into the Vec
-    subsequent_calls.into_iter().try_fold(
-        toggl_entires_from_initial_call,
-        |mut acc, subsequent_call| {
-            acc.extend(
-                subsequent_call?
-                    .data
-                    .into_iter()
-                    .map(TogglResponseData::into),
-            );
+        .try_fold(initial_call.data, |mut acc, subsequent_call| {
+            acc.extend(subsequent_call?.data);
             Ok(acc)
-        },
-    )
+        })
 }
 
 async fn call_toggl_api(
@@ -411,59 +1585,95 @@ async fn call_toggl_api(
     since: &NaiveDate,
     client: &Client,
     page: usize,
-) -> InternalResult<TogglResponse> {
+) -> AppResult<TogglResponse> {
     trace!(
         "Calling Toggl API (workspace {}, page {})",
         workspace_id.0,
         page,
     );
 
-    loop {
-        let response = client
+    let response = toggl_scheduler::send(
+        api_token,
+        client
             .get("https://api.track.toggl.com/reports/api/v2/details")
             .query(&TogglQuery {
                 user_agent: "yottaclock.com",
                 workspace_id: workspace_id.0,
                 since,
                 page,
-            })
-            .basic_auth(api_token, Some("api_token"))
-            .send()
-            .await?;
+            }),
+    )
+    .await?;
 
-        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
-            warn!(
-                "Got 429 response from Toggl API (workspace {}, page {})",
-                workspace_id.0, page,
-            );
+    trace!(
+        "Got response from Toggl API (workspace {}, page {})",
+        workspace_id.0,
+        page,
+    );
 
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-        } else {
-            trace!(
-                "Got response from Toggl API (workspace {}, page {})",
-                workspace_id.0,
-                page,
-            );
+    read_toggl_json(response).await
+}
 
-            break Ok(response.json().await?);
-        }
-    }
+/// Read what Toggl responded with, unless it refused the request, for instance because the API
+/// token was revoked
+async fn read_toggl_json<T: DeserializeOwned>(response: Response) -> AppResult<T> {
+    Ok(response.error_for_status()?.json().await?)
 }
 
-fn today_in_timezone(timezone: &str) -> InternalResult<NaiveDate> {
-    let tz: Tz = timezone
-        .parse()
-        .map_err(InternalError::UnrecognizedTimezone)?;
+fn today_in_timezone(timezone: &str) -> AppResult<NaiveDate> {
+    let tz: Tz = timezone.parse().map_err(AppError::UnrecognizedTimezone)?;
 
     Ok(Utc::now().with_timezone(&tz).date_naive())
 }
 
+/// How much time counted towards a project from `from` to `to`, including both
+///
+/// Nothing counts from before the project's starting date.
+fn time_logged_between(
+    toggl_entries: &[TogglEntry],
+    project: &Project,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Duration {
+    toggl_entries
+        .iter()
+        .filter(|entry| {
+            project.starting_date <= entry.date
+                && from <= entry.date
+                && entry.date <= to
+                && project.filter.matches(entry)
+        })
+        .fold(Duration::zero(), |total, entry| total + entry.duration)
+}
+
+/// How much time counted towards a project from when it started up to and including a day
+fn time_logged_until(toggl_entries: &[TogglEntry], project: &Project, date: NaiveDate) -> Duration {
+    time_logged_between(toggl_entries, project, project.starting_date, date)
+}
+
+/// How much time counted towards a project on one day
+fn time_logged_on(toggl_entries: &[TogglEntry], project: &Project, date: NaiveDate) -> Duration {
+    time_logged_between(toggl_entries, project, date, date)
+}
+
+/// How much of a project's limit is used up in the period that `today` is in
+fn limit_usage(
+    toggl_entries: &[TogglEntry],
+    project: &Project,
+    today: NaiveDate,
+) -> Option<LimitUsage> {
+    project.limit.map(|limit| LimitUsage {
+        limit,
+        used: time_logged_between(toggl_entries, project, limit.period.start(today), today),
+    })
+}
+
 fn process_toggl_data(
     toggl_entries: Vec<TogglEntry>,
-    projects: HashMap<ProjectId, Project>,
+    projects: HashMap<ProjectKey, Project>,
     daily_max: Duration,
     today: NaiveDate,
-) -> (HashMap<ProjectId, ProjectWithDebt>, Duration) {
+) -> (HashMap<ProjectKey, ProjectWithDebt>, Duration) {
     // Sort the entries by their date
     let sorted_toggl_entries = {
         let mut v = toggl_entries;
@@ -473,11 +1683,11 @@ fn process_toggl_data(
 
     let mut current_date = earliest_start_date(&projects) - Days::new(1);
 
-    let mut projects_with_debts: HashMap<ProjectId, ProjectWithDebt> = projects
+    let mut projects_with_debts: HashMap<ProjectKey, ProjectWithDebt> = projects
         .into_iter()
-        .map(|(project_id, project)| {
+        .map(|(project_key, project)| {
             (
-                project_id,
+                project_key,
                 ProjectWithDebt {
                     project,
                     debt: Duration::zero(),
@@ -488,11 +1698,15 @@ fn process_toggl_data(
 
     let mut total_debt = Duration::zero();
 
+    // How much has counted towards each project so far, which deadlines' daily goals depend on
+    let mut logged: HashMap<ProjectKey, Duration> = HashMap::new();
+
     for entry in sorted_toggl_entries {
         // Increment the current date until it's caught up to this entry
         while current_date < entry.date {
             total_debt = advance_debt(
                 &mut projects_with_debts,
+                &logged,
                 &mut current_date,
                 total_debt,
                 daily_max,
@@ -506,15 +1720,15 @@ fn process_toggl_data(
             );
         }
 
-        // Subtract this entry from the project debt and the total debt
-        if let Some(ProjectWithDebt { project, debt }) =
-            projects_with_debts.get_mut(&entry.project_id)
-        {
-            if project.starting_date <= entry.date {
+        // Subtract this entry from the debt of every project it counts towards and the total debt
+        for (project_key, ProjectWithDebt { project, debt }) in projects_with_debts.iter_mut() {
+            if project.starting_date <= entry.date && project.filter.matches(&entry) {
                 // Only subtract from the total debt while the project debt is positive
-                total_debt = total_debt - max(min(*debt, entry.duration), Duration::zero());
+                total_debt -= max(min(*debt, entry.duration), Duration::zero());
+
+                *debt -= entry.duration;
 
-                *debt = *debt - entry.duration;
+                *logged.entry(*project_key).or_insert_with(Duration::zero) += entry.duration;
             }
         }
     }
@@ -523,6 +1737,7 @@ fn process_toggl_data(
     while current_date < today {
         total_debt = advance_debt(
             &mut projects_with_debts,
+            &logged,
             &mut current_date,
             total_debt,
             daily_max,
@@ -533,7 +1748,8 @@ fn process_toggl_data(
 }
 
 fn advance_debt(
-    projects_with_debts: &mut HashMap<ProjectId, ProjectWithDebt>,
+    projects_with_debts: &mut HashMap<ProjectKey, ProjectWithDebt>,
+    logged: &HashMap<ProjectKey, Duration>,
     current_date: &mut NaiveDate,
     previous_total_debt: Duration,
     daily_max: Duration,
@@ -544,12 +1760,24 @@ fn advance_debt(
     let mut total_debt = Duration::zero();
 
     // Increase the debts
-    for ProjectWithDebt { project, debt } in projects_with_debts.values_mut() {
-        if project.advance_debt_on(*current_date) {
-            *debt = *debt + project.daily_goal;
+    for (project_key, ProjectWithDebt { project, debt }) in projects_with_debts.iter_mut() {
+        let logged_before = logged
+            .get(project_key)
+            .copied()
+            .unwrap_or_else(Duration::zero);
+
+        match project.deadline {
+            // A deadline's daily goal already makes up for time that was missed or worked ahead,
+            // so the debt starts over every day until the deadline and is left as it was after
+            Some(deadline) => {
+                if *current_date <= deadline.date {
+                    *debt = project.goal_on(*current_date, logged_before);
+                }
+            }
+            None => *debt += project.goal_on(*current_date, logged_before),
         }
 
-        total_debt = total_debt + max(*debt, Duration::zero());
+        total_debt += max(*debt, Duration::zero());
     }
 
     // Ensure the total debt doesn't exceed the daily max
@@ -559,7 +1787,7 @@ fn advance_debt(
 
     // If they exceeded their goal yesterday, carry over the extra to today
     if previous_total_debt < Duration::zero() {
-        total_debt = total_debt + previous_total_debt;
+        total_debt += previous_total_debt;
     }
 
     total_debt
@@ -575,17 +1803,87 @@ impl Project {
             return false;
         }
 
-        match date.weekday() {
-            Weekday::Mon if !self.weekdays.monday => return false,
-            Weekday::Tue if !self.weekdays.tuesday => return false,
-            Weekday::Wed if !self.weekdays.wednesday => return false,
-            Weekday::Thu if !self.weekdays.thursday => return false,
-            Weekday::Fri if !self.weekdays.friday => return false,
-            Weekday::Sat if !self.weekdays.saturday => return false,
-            Weekday::Sun if !self.weekdays.sunday => return false,
-            _ => (),
+        self.weekdays.includes(date.weekday())
+    }
+
+    /// How many days from `from` to `to`, including both, the project's debt grows on
+    ///
+    /// Counted week by week rather than day by day, since deadlines can be years away.
+    fn scheduled_days(&self, from: NaiveDate, to: NaiveDate) -> i32 {
+        let from = from.max(self.starting_date);
+
+        if to < from {
+            return 0;
+        }
+
+        let num_days = (to - from).num_days() + 1;
+
+        let full_weeks = num_days / 7 * i64::from(self.weekdays.num_days());
+        let rest_of_week = (0..num_days % 7)
+            .filter(|&offset| {
+                self.weekdays
+                    .includes((from + Duration::days(offset)).weekday())
+            })
+            .count() as i64;
+        let days_off = self
+            .days_off
+            .iter()
+            .filter(|&&day_off| {
+                (from..=to).contains(&day_off) && self.weekdays.includes(day_off.weekday())
+            })
+            .count() as i64;
+
+        (full_weeks + rest_of_week - days_off) as i32
+    }
+
+    /// How much the project's debt grows on a day, given how much time counted towards it before
+    ///
+    /// For a project with a deadline, that's whatever is left of the target split evenly between
+    /// the scheduled days left until the deadline.
+    pub fn goal_on(&self, date: NaiveDate, logged_before: Duration) -> Duration {
+        if self.limit.is_some() || !self.advance_debt_on(date) {
+            return Duration::zero();
+        }
+
+        let Some(deadline) = self.deadline else {
+            return self.daily_goal;
+        };
+
+        let remaining = deadline.total - logged_before;
+        let days_left = i64::from(self.scheduled_days(date, deadline.date));
+
+        if remaining <= Duration::zero() || days_left == 0 {
+            Duration::zero()
+        } else {
+            // Round up so that the target is reached by the deadline rather than a few seconds short
+            Duration::seconds((remaining.num_seconds() + days_left - 1) / days_left)
+        }
+    }
+
+    /// When a deadline's target will be reached if the user keeps up the pace of the scheduled
+    /// days so far, or `None` if the project has no deadline or no pace to keep up
+    pub fn projected_finish(&self, logged: Duration, today: NaiveDate) -> Option<NaiveDate> {
+        let deadline = self.deadline?;
+
+        if logged >= deadline.total {
+            return Some(today);
+        }
+
+        let days_so_far = self.scheduled_days(self.starting_date, today);
+
+        if days_so_far == 0 || logged <= Duration::zero() {
+            return None;
         }
 
-        true
+        let pace = logged / days_so_far;
+        let mut remaining = deadline.total - logged;
+
+        (1..=MAX_PROJECTION_DAYS)
+            .map(|days| today + Days::new(u64::from(days)))
+            .filter(|&date| self.advance_debt_on(date))
+            .find(|_| {
+                remaining -= pace;
+                remaining <= Duration::zero()
+            })
     }
 }
@@ -2,18 +2,34 @@
 
 use super::*;
 
+fn toggl_project_id(project_id: i64) -> ProjectId {
+    ProjectId {
+        workspace_id: WorkspaceId(1234567),
+        project_id,
+    }
+}
+
 #[test]
 fn test_total_debt_overflow() {
-    let project_id = ProjectId(1234);
+    let project_key = ProjectKey(1);
+    let project_id = toggl_project_id(1234);
 
     let toggl_entries = vec![TogglEntry {
-        project_id,
+        project_id: Some(project_id),
+        description: String::new(),
+        client: None,
+        tags: Vec::new(),
+        billable: false,
         date: NaiveDate::from_ymd_opt(2000, 1, 9).unwrap(),
         duration: Duration::hours(4),
     }];
 
     let example_project = Project {
         name: String::from("Example Project"),
+        filter: GoalFilter {
+            project_ids: HashSet::from([project_id]),
+            ..GoalFilter::default()
+        },
         starting_date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
         daily_goal: Duration::hours(1),
         days_off: HashSet::new(),
@@ -26,9 +42,14 @@ fn test_total_debt_overflow() {
             saturday: true,
             sunday: true,
         },
+        priority: 0,
+        weight: 1,
+        deadline: None,
+        limit: None,
+        archived: false,
     };
 
-    let user_projects = HashMap::from([(project_id, example_project.clone())]);
+    let user_projects = HashMap::from([(project_key, example_project.clone())]);
 
     let daily_max = Duration::hours(3);
 
@@ -39,7 +60,7 @@ fn test_total_debt_overflow() {
     assert_eq!(
         r1,
         HashMap::from([(
-            project_id,
+            project_key,
             ProjectWithDebt {
                 project: example_project,
                 debt: Duration::hours(6),
@@ -52,12 +73,17 @@ fn test_total_debt_overflow() {
 
 #[test]
 fn test_days_off() {
-    let project_id = ProjectId(185068848);
+    let project_key = ProjectKey(1);
+    let project_id = toggl_project_id(185068848);
 
     let toggl_entries = Vec::new();
 
     let example_project = Project {
         name: String::from("EECS 575"),
+        filter: GoalFilter {
+            project_ids: HashSet::from([project_id]),
+            ..GoalFilter::default()
+        },
         starting_date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
         daily_goal: Duration::hours(1),
         days_off: HashSet::from([NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()]),
@@ -70,9 +96,14 @@ fn test_days_off() {
             saturday: true,
             sunday: true,
         },
+        priority: 0,
+        weight: 1,
+        deadline: None,
+        limit: None,
+        archived: false,
     };
 
-    let user_projects = HashMap::from([(project_id, example_project.clone())]);
+    let user_projects = HashMap::from([(project_key, example_project.clone())]);
 
     let daily_max = Duration::hours(5);
 
@@ -83,7 +114,7 @@ fn test_days_off() {
     assert_eq!(
         r1,
         HashMap::from([(
-            project_id,
+            project_key,
             ProjectWithDebt {
                 project: example_project,
                 debt: Duration::hours(1),
@@ -94,14 +125,374 @@ fn test_days_off() {
     assert_eq!(r2, Duration::hours(1));
 }
 
+#[test]
+fn test_goal_filter() {
+    let project_key = ProjectKey(1);
+
+    let toggl_entries = vec![
+        TogglEntry {
+            project_id: Some(toggl_project_id(1)),
+            description: String::new(),
+            client: None,
+            tags: Vec::new(),
+            billable: false,
+            date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            duration: Duration::minutes(10),
+        },
+        TogglEntry {
+            project_id: Some(toggl_project_id(2)),
+            description: String::new(),
+            client: None,
+            tags: Vec::new(),
+            billable: false,
+            date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            duration: Duration::minutes(20),
+        },
+        TogglEntry {
+            project_id: Some(toggl_project_id(3)),
+            description: String::new(),
+            client: Some(String::from("University")),
+            tags: Vec::new(),
+            billable: false,
+            date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            duration: Duration::minutes(30),
+        },
+        TogglEntry {
+            project_id: Some(toggl_project_id(4)),
+            description: String::new(),
+            client: None,
+            tags: vec![String::from("thesis")],
+            billable: false,
+            date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            duration: Duration::minutes(40),
+        },
+        TogglEntry {
+            project_id: Some(toggl_project_id(5)),
+            description: String::new(),
+            client: Some(String::from("Employer")),
+            tags: vec![String::from("email")],
+            billable: false,
+            date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            duration: Duration::minutes(50),
+        },
+    ];
+
+    let example_project = Project {
+        name: String::from("Thesis"),
+        filter: GoalFilter {
+            project_ids: HashSet::from([toggl_project_id(1), toggl_project_id(2)]),
+            clients: HashSet::from([String::from("University")]),
+            tags: HashSet::from([String::from("thesis")]),
+            ..GoalFilter::default()
+        },
+        starting_date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        daily_goal: Duration::hours(2),
+        days_off: HashSet::new(),
+        weekdays: WhichWeekdays {
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: true,
+            sunday: true,
+        },
+        priority: 0,
+        weight: 1,
+        deadline: None,
+        limit: None,
+        archived: false,
+    };
+
+    let user_projects = HashMap::from([(project_key, example_project.clone())]);
+
+    let daily_max = Duration::hours(5);
+
+    let today = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+
+    let (r1, r2) = process_toggl_data(toggl_entries, user_projects, daily_max, today);
+
+    assert_eq!(
+        r1,
+        HashMap::from([(
+            project_key,
+            ProjectWithDebt {
+                project: example_project,
+                debt: Duration::minutes(20),
+            }
+        )])
+    );
+
+    assert_eq!(r2, Duration::minutes(20));
+}
+
+#[test]
+fn test_goal_filter_descriptions() {
+    let project_key = ProjectKey(1);
+
+    let entry = |project_id: Option<i64>, description: &str, minutes: i64| TogglEntry {
+        project_id: project_id.map(toggl_project_id),
+        description: String::from(description),
+        client: None,
+        tags: Vec::new(),
+        billable: false,
+        date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        duration: Duration::minutes(minutes),
+    };
+
+    let toggl_entries = vec![
+        entry(None, "Untracked", 1),
+        entry(Some(1), "Reading for THESIS", 2),
+        entry(Some(1), "EECS 575 homework", 4),
+        entry(Some(1), "EECS homework", 8),
+        entry(Some(1), "Email", 16),
+    ];
+
+    let example_project = Project {
+        name: String::from("Catch-all"),
+        filter: GoalFilter {
+            no_project: true,
+            description_substrings: HashSet::from([String::from("thesis")]),
+            description_regexes: vec![DescriptionRegex(Regex::new(r"^EECS \d+").unwrap())],
+            ..GoalFilter::default()
+        },
+        starting_date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        daily_goal: Duration::hours(1),
+        days_off: HashSet::new(),
+        weekdays: WhichWeekdays {
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: true,
+            sunday: true,
+        },
+        priority: 0,
+        weight: 1,
+        deadline: None,
+        limit: None,
+        archived: false,
+    };
+
+    let user_projects = HashMap::from([(project_key, example_project.clone())]);
+
+    let daily_max = Duration::hours(5);
+
+    let today = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+
+    let (r1, r2) = process_toggl_data(toggl_entries, user_projects, daily_max, today);
+
+    assert_eq!(
+        r1,
+        HashMap::from([(
+            project_key,
+            ProjectWithDebt {
+                project: example_project,
+                debt: Duration::minutes(53),
+            }
+        )])
+    );
+
+    assert_eq!(r2, Duration::minutes(53));
+}
+
+#[test]
+fn test_goal_filter_restrictions() {
+    let project_key = ProjectKey(1);
+    let project_id = toggl_project_id(1);
+
+    let entry = |tags: &[&str], billable: bool, minutes: i64| TogglEntry {
+        project_id: Some(project_id),
+        description: String::new(),
+        client: None,
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        billable,
+        date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        duration: Duration::minutes(minutes),
+    };
+
+    let toggl_entries = vec![
+        entry(&["client-work"], true, 1),
+        entry(&["client-work"], false, 2),
+        entry(&[], true, 4),
+        entry(&["client-work", "meeting"], true, 8),
+    ];
+
+    let example_project = Project {
+        name: String::from("Billable"),
+        filter: GoalFilter {
+            project_ids: HashSet::from([project_id]),
+            billable_only: true,
+            required_tags: HashSet::from([String::from("client-work")]),
+            excluded_tags: HashSet::from([String::from("meeting")]),
+            ..GoalFilter::default()
+        },
+        starting_date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        daily_goal: Duration::hours(1),
+        days_off: HashSet::new(),
+        weekdays: WhichWeekdays {
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: true,
+            sunday: true,
+        },
+        priority: 0,
+        weight: 1,
+        deadline: None,
+        limit: None,
+        archived: false,
+    };
+
+    let user_projects = HashMap::from([(project_key, example_project.clone())]);
+
+    let daily_max = Duration::hours(5);
+
+    let today = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+
+    let (r1, r2) = process_toggl_data(toggl_entries, user_projects, daily_max, today);
+
+    assert_eq!(
+        r1,
+        HashMap::from([(
+            project_key,
+            ProjectWithDebt {
+                project: example_project,
+                debt: Duration::minutes(59),
+            }
+        )])
+    );
+
+    assert_eq!(r2, Duration::minutes(59));
+}
+
+#[test]
+fn test_goal_filter_overlap() {
+    let thesis_key = ProjectKey(1);
+    let university_key = ProjectKey(2);
+
+    // The first entry matches both projects' filters, so it counts in full towards each of them
+    let toggl_entries = vec![
+        TogglEntry {
+            project_id: Some(toggl_project_id(1)),
+            description: String::new(),
+            client: Some(String::from("University")),
+            tags: Vec::new(),
+            billable: false,
+            date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            duration: Duration::minutes(30),
+        },
+        TogglEntry {
+            project_id: Some(toggl_project_id(1)),
+            description: String::new(),
+            client: None,
+            tags: Vec::new(),
+            billable: false,
+            date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            duration: Duration::minutes(20),
+        },
+    ];
+
+    let thesis_project = Project {
+        name: String::from("Thesis"),
+        filter: GoalFilter {
+            project_ids: HashSet::from([toggl_project_id(1)]),
+            ..GoalFilter::default()
+        },
+        starting_date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        daily_goal: Duration::hours(1),
+        days_off: HashSet::new(),
+        weekdays: WhichWeekdays {
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: true,
+            sunday: true,
+        },
+        priority: 0,
+        weight: 1,
+        deadline: None,
+        limit: None,
+        archived: false,
+    };
+
+    let university_project = Project {
+        name: String::from("University"),
+        filter: GoalFilter {
+            clients: HashSet::from([String::from("University")]),
+            ..GoalFilter::default()
+        },
+        ..thesis_project.clone()
+    };
+
+    let user_projects = HashMap::from([
+        (thesis_key, thesis_project.clone()),
+        (university_key, university_project.clone()),
+    ]);
+
+    let daily_max = Duration::hours(5);
+
+    let today = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+
+    let (r1, r2) = process_toggl_data(toggl_entries, user_projects, daily_max, today);
+
+    assert_eq!(
+        r1,
+        HashMap::from([
+            (
+                thesis_key,
+                ProjectWithDebt {
+                    project: thesis_project,
+                    debt: Duration::minutes(10),
+                }
+            ),
+            (
+                university_key,
+                ProjectWithDebt {
+                    project: university_project,
+                    debt: Duration::minutes(30),
+                }
+            ),
+        ])
+    );
+
+    // The total debt is what each project still owes, so the shared entry comes off it twice
+    assert_eq!(r2, Duration::minutes(40));
+}
+
+#[test]
+fn test_project_id_round_trip() {
+    let project_id = ProjectId {
+        workspace_id: WorkspaceId(1234567),
+        project_id: 185068848,
+    };
+
+    assert_eq!(project_id.to_string(), "1234567:185068848");
+    assert_eq!(
+        "1234567:185068848".parse::<ProjectId>().unwrap(),
+        project_id
+    );
+    assert!("185068848".parse::<ProjectId>().is_err());
+}
+
 #[sqlx::test]
 async fn test_get_user_projects(pool: PgPool) {
     let user_key = UserKey(1);
 
-    let project_key = 2;
-    let project_id = ProjectId(12345678);
+    let project_key = ProjectKey(2);
+    let project_id = toggl_project_id(12345678);
     let project = Project {
-        name: String::from("Example Project"),
+        name: String::from("Example Project, Example Client"),
+        filter: GoalFilter {
+            project_ids: HashSet::from([project_id]),
+            clients: HashSet::from([String::from("Example Client")]),
+            ..GoalFilter::default()
+        },
         starting_date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
         daily_goal: Duration::hours(1),
         days_off: HashSet::from([NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()]),
@@ -114,26 +505,40 @@ async fn test_get_user_projects(pool: PgPool) {
             saturday: true,
             sunday: true,
         },
+        priority: 0,
+        weight: 1,
+        deadline: None,
+        limit: None,
+        archived: false,
     };
 
     let day_off_key = 3;
 
     sqlx::query!(
-        "INSERT INTO users(user_key, toggl_api_key, workspace_id, daily_max, timezone)
-        VALUES ($1, '1971800d4d82861d8f2c1651fea4d212', 1234567, 3600, 'UTC')",
+        "INSERT INTO users(user_key, toggl_api_key, daily_max, timezone)
+        VALUES ($1, '1971800d4d82861d8f2c1651fea4d212', 3600, 'UTC')",
+        user_key.0,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query!(
+        "INSERT INTO users_to_workspaces(user_key, workspace_id)
+        VALUES ($1, $2)",
         user_key.0,
+        project_id.workspace_id.0,
     )
     .execute(&pool)
     .await
     .unwrap();
 
     sqlx::query!(
-        "INSERT INTO projects(project_key, user_key, project_id, starting_date, daily_goal,
+        "INSERT INTO projects(project_key, user_key, starting_date, daily_goal,
             monday, tuesday, wednesday, thursday, friday, saturday, sunday)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
-        project_key,
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+        project_key.0,
         user_key.0,
-        project_id.0,
         project.starting_date,
         project.daily_goal.num_seconds(),
         project.weekdays.monday,
@@ -148,6 +553,26 @@ async fn test_get_user_projects(pool: PgPool) {
     .await
     .unwrap();
 
+    sqlx::query!(
+        "INSERT INTO projects_to_toggl_projects(project_key, workspace_id, project_id)
+        VALUES ($1, $2, $3)",
+        project_key.0,
+        project_id.workspace_id.0,
+        project_id.project_id,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query!(
+        "INSERT INTO projects_to_toggl_clients(project_key, client)
+        VALUES ($1, 'Example Client')",
+        project_key.0,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
     sqlx::query!(
         "INSERT INTO days_off(day_off_key, user_key, day_off)
         VALUES ($1, $2, '2000-01-01')",
@@ -161,7 +586,7 @@ async fn test_get_user_projects(pool: PgPool) {
     sqlx::query!(
         "INSERT INTO days_off_to_projects(project_key, day_off_key)
         VALUES ($1, $2)",
-        project_key,
+        project_key.0,
         day_off_key,
     )
     .execute(&pool)
@@ -171,12 +596,448 @@ async fn test_get_user_projects(pool: PgPool) {
     let toggl_projects = vec![TogglProject {
         active: true,
         name: String::from("Example Project"),
-        id: project_id,
+        id: project_id.project_id,
+        workspace_id: project_id.workspace_id,
+        client_id: None,
+        color: Some(String::from("#2da608")),
     }];
 
-    let projects = get_user_projects_from_toggl_projects(toggl_projects, user_key, &pool)
+    let projects = get_user_projects_from_toggl_projects(&toggl_projects, user_key, &pool)
+        .await
+        .unwrap();
+
+    assert_eq!(projects, HashMap::from([(project_key, project.clone())]));
+
+    // The Toggl project's name is remembered after it's archived or deleted
+    let projects = get_user_projects_from_toggl_projects(&[], user_key, &pool)
         .await
         .unwrap();
 
-    assert_eq!(projects, HashMap::from([(project_id, project)]));
+    assert_eq!(projects, HashMap::from([(project_key, project)]));
+
+    // So is the rest of it, for when Toggl can't be reached
+    assert_eq!(
+        get_last_known_toggl_projects(user_key, &[project_id.workspace_id], &pool)
+            .await
+            .unwrap(),
+        toggl_projects,
+    );
+    assert_eq!(
+        get_toggl_project_colors(user_key, &pool).await.unwrap(),
+        HashMap::from([(project_id, String::from("#2da608"))]),
+    );
+}
+
+#[test]
+fn test_is_hex_color() {
+    assert!(is_hex_color("#2da608"));
+    assert!(is_hex_color("#2DA608"));
+    assert!(!is_hex_color("2da608"));
+    assert!(!is_hex_color("#2da60"));
+    assert!(!is_hex_color("#2da608; background: red"));
+}
+
+#[test]
+fn test_time_logged_on() {
+    let project_id = toggl_project_id(1234);
+    let today = NaiveDate::from_ymd_opt(2000, 1, 10).unwrap();
+
+    let entry = |project_id, date, minutes| TogglEntry {
+        project_id: Some(project_id),
+        description: String::new(),
+        client: None,
+        tags: Vec::new(),
+        billable: false,
+        date,
+        duration: Duration::minutes(minutes),
+    };
+
+    let toggl_entries = vec![
+        entry(project_id, today, 30),
+        entry(project_id, today, 45),
+        entry(project_id, today - Days::new(1), 60),
+        entry(toggl_project_id(4321), today, 90),
+    ];
+
+    let project = Project {
+        name: String::from("Example Project"),
+        filter: GoalFilter {
+            project_ids: HashSet::from([project_id]),
+            ..GoalFilter::default()
+        },
+        starting_date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        daily_goal: Duration::hours(1),
+        days_off: HashSet::new(),
+        weekdays: WhichWeekdays {
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: true,
+            sunday: true,
+        },
+        priority: 0,
+        weight: 1,
+        deadline: None,
+        limit: None,
+        archived: false,
+    };
+
+    assert_eq!(
+        time_logged_on(&toggl_entries, &project, today),
+        Duration::minutes(75)
+    );
+
+    let not_started = Project {
+        starting_date: today + Days::new(1),
+        ..project
+    };
+
+    assert_eq!(
+        time_logged_on(&toggl_entries, &not_started, today),
+        Duration::zero()
+    );
+}
+
+fn deadline_project(project_id: ProjectId) -> Project {
+    Project {
+        name: String::from("Thesis"),
+        filter: GoalFilter {
+            project_ids: HashSet::from([project_id]),
+            ..GoalFilter::default()
+        },
+        // A Monday
+        starting_date: NaiveDate::from_ymd_opt(2000, 1, 3).unwrap(),
+        daily_goal: Duration::zero(),
+        days_off: HashSet::new(),
+        weekdays: WhichWeekdays {
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: false,
+            sunday: false,
+        },
+        priority: 0,
+        weight: 1,
+        // Ten weekdays later
+        deadline: Some(Deadline {
+            date: NaiveDate::from_ymd_opt(2000, 1, 14).unwrap(),
+            total: Duration::hours(10),
+        }),
+        limit: None,
+        archived: false,
+    }
+}
+
+#[test]
+fn test_scheduled_days() {
+    let project = Project {
+        days_off: HashSet::from([
+            // A Wednesday and a Saturday, which isn't one of the project's days anyway
+            NaiveDate::from_ymd_opt(2000, 1, 12).unwrap(),
+            NaiveDate::from_ymd_opt(2000, 1, 15).unwrap(),
+        ]),
+        ..deadline_project(toggl_project_id(1234))
+    };
+
+    let start = NaiveDate::from_ymd_opt(1999, 12, 25).unwrap();
+    for from in start.iter_days().take(20) {
+        for to in from.iter_days().take(40) {
+            let counted = from
+                .iter_days()
+                .take_while(|&date| date <= to)
+                .filter(|&date| project.advance_debt_on(date))
+                .count() as i32;
+
+            assert_eq!(project.scheduled_days(from, to), counted, "{from} to {to}");
+        }
+    }
+
+    assert_eq!(
+        project.scheduled_days(
+            NaiveDate::from_ymd_opt(2000, 1, 3).unwrap(),
+            NaiveDate::from_ymd_opt(9999, 12, 31).unwrap(),
+        ),
+        // Every weekday until then, less the Wednesday off
+        2_087_100 - 1,
+    );
+}
+
+#[test]
+fn test_deadline_goal() {
+    let project = deadline_project(toggl_project_id(1234));
+    let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+
+    assert_eq!(
+        project.goal_on(date(3), Duration::zero()),
+        Duration::hours(1)
+    );
+    assert_eq!(project.goal_on(date(8), Duration::zero()), Duration::zero());
+    assert_eq!(
+        project.goal_on(date(4), Duration::hours(2)),
+        Duration::seconds(8 * 3600 / 9),
+    );
+    assert_eq!(
+        project.goal_on(date(17), Duration::zero()),
+        Duration::zero()
+    );
+    assert_eq!(
+        project.goal_on(date(4), Duration::hours(11)),
+        Duration::zero()
+    );
+
+    // Days off leave fewer days to reach the target in
+    let with_day_off = Project {
+        days_off: HashSet::from([date(5)]),
+        ..project
+    };
+    assert_eq!(
+        with_day_off.goal_on(date(4), Duration::hours(2)),
+        Duration::hours(1),
+    );
+}
+
+#[test]
+fn test_deadline_debt() {
+    let project_key = ProjectKey(1);
+    let project_id = toggl_project_id(1234);
+    let project = deadline_project(project_id);
+
+    let toggl_entries = vec![TogglEntry {
+        project_id: Some(project_id),
+        description: String::new(),
+        client: None,
+        tags: Vec::new(),
+        billable: false,
+        date: NaiveDate::from_ymd_opt(2000, 1, 3).unwrap(),
+        duration: Duration::hours(2),
+    }];
+
+    let today = NaiveDate::from_ymd_opt(2000, 1, 4).unwrap();
+
+    let (r1, r2) = process_toggl_data(
+        toggl_entries,
+        HashMap::from([(project_key, project.clone())]),
+        Duration::hours(8),
+        today,
+    );
+
+    // Working ahead yesterday makes today's goal smaller instead of carrying over as a surplus
+    assert_eq!(
+        r1,
+        HashMap::from([(
+            project_key,
+            ProjectWithDebt {
+                project,
+                debt: Duration::seconds(8 * 3600 / 9),
+            }
+        )])
+    );
+
+    assert_eq!(r2, Duration::seconds(8 * 3600 / 9));
+}
+
+#[test]
+fn test_projected_finish() {
+    let project = deadline_project(toggl_project_id(1234));
+    let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+
+    // An hour a day for the two days so far leaves eight more weekdays to go
+    assert_eq!(
+        project.projected_finish(Duration::hours(2), date(4)),
+        Some(date(14)),
+    );
+    assert_eq!(
+        project.projected_finish(Duration::hours(1), date(4)),
+        Some(date(28)),
+    );
+    assert_eq!(project.projected_finish(Duration::zero(), date(4)), None);
+    assert_eq!(
+        project.projected_finish(Duration::hours(10), date(4)),
+        Some(date(4)),
+    );
+
+    let without_deadline = Project {
+        deadline: None,
+        ..project
+    };
+    assert_eq!(
+        without_deadline.projected_finish(Duration::hours(2), date(4)),
+        None,
+    );
+}
+
+#[test]
+fn test_limit_usage() {
+    let project_id = toggl_project_id(1234);
+    let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+
+    let entry = |day, hours| TogglEntry {
+        project_id: Some(project_id),
+        description: String::new(),
+        client: None,
+        tags: Vec::new(),
+        billable: false,
+        date: date(day),
+        duration: Duration::hours(hours),
+    };
+
+    // Sunday, then Monday to Wednesday of the next week
+    let toggl_entries = vec![entry(2, 4), entry(3, 1), entry(4, 2), entry(5, 3)];
+
+    let project = Project {
+        limit: Some(Limit {
+            time: Duration::hours(5),
+            period: LimitPeriod::Week,
+        }),
+        deadline: None,
+        starting_date: date(1),
+        ..deadline_project(project_id)
+    };
+
+    assert_eq!(LimitPeriod::Week.start(date(5)), date(3));
+    assert_eq!(LimitPeriod::Day.start(date(5)), date(5));
+
+    let weekly = limit_usage(&toggl_entries, &project, date(4)).unwrap();
+    assert_eq!(weekly.used, Duration::hours(3));
+    assert_eq!(weekly.remaining(), Duration::hours(2));
+    assert!(!weekly.exceeded());
+
+    let weekly = limit_usage(&toggl_entries, &project, date(5)).unwrap();
+    assert_eq!(weekly.remaining(), Duration::hours(-1));
+    assert!(weekly.exceeded());
+
+    let daily = Project {
+        limit: Some(Limit {
+            time: Duration::hours(1),
+            period: LimitPeriod::Day,
+        }),
+        ..project.clone()
+    };
+    let usage = limit_usage(&toggl_entries, &daily, date(3)).unwrap();
+    assert_eq!(usage.remaining(), Duration::zero());
+    assert!(!usage.exceeded());
+
+    // A limit never has a goal to make up
+    assert_eq!(daily.goal_on(date(3), Duration::zero()), Duration::zero());
+    assert_eq!(
+        limit_usage(&toggl_entries, &deadline_project(project_id), date(3)),
+        None,
+    );
+}
+
+#[test]
+fn test_stored_workspace_freshness() {
+    let now: DateTime<Utc> = "2026-10-18T12:00:00Z".parse().unwrap();
+    let since = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+
+    let never_synced = StoredWorkspace {
+        hooked: true,
+        entries_since: None,
+        synced_at: None,
+    };
+    assert!(!never_synced.is_fresh(now));
+    assert!(never_synced.needs_sync(since, now));
+
+    let synced = StoredWorkspace {
+        hooked: false,
+        entries_since: Some(since),
+        synced_at: Some(now - Duration::seconds(30)),
+    };
+    assert!(synced.is_fresh(now));
+    assert!(!synced.needs_sync(since, now));
+    assert!(!synced.needs_sync(since + Days::new(7), now));
+
+    // Entries from before what's stored have to be fetched however fresh it is
+    assert!(synced.needs_sync(since - Days::new(1), now));
+
+    // Only a webhook keeps what's stored fresh for longer
+    let later = now + STORED_WORKSPACE_MAX_AGE;
+    assert!(!synced.is_fresh(later));
+    assert!(synced.needs_sync(since, later));
+
+    let hooked = StoredWorkspace {
+        hooked: true,
+        ..synced
+    };
+    assert!(hooked.is_fresh(later));
+    assert!(!hooked.needs_sync(since, later));
+
+    // In case an event went missing, even a webhook doesn't keep it fresh forever
+    let much_later = now + HOOKED_WORKSPACE_MAX_AGE;
+    assert!(!hooked.is_fresh(much_later));
+    assert!(hooked.needs_sync(since, much_later));
+}
+
+#[sqlx::test]
+async fn test_stored_toggl_entries_by_owner(pool: PgPool) -> AppResult<()> {
+    let workspace_id = WorkspaceId(1234567);
+    let owner = TogglUserId(1111111);
+    let teammate = TogglUserId(2222222);
+    let since = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+
+    // Both of them see the shared entry, but only the owner sees their private one
+    for (toggl_user_id, entry_id) in [(owner, 1), (owner, 2), (teammate, 1)] {
+        sqlx::query!(
+            "INSERT INTO toggl_workspaces(toggl_user_id, workspace_id, entries_since, synced_at)
+            VALUES ($1, $2, $3, now())
+            ON CONFLICT DO NOTHING",
+            toggl_user_id.0,
+            workspace_id.0,
+            since,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO toggl_entries(toggl_user_id, workspace_id, entry_id, description, tags,
+                billable, start, duration)
+            VALUES ($1, $2, $3, '', '{}', FALSE, '2026-10-18T12:00:00Z', 60000)",
+            toggl_user_id.0,
+            workspace_id.0,
+            entry_id,
+        )
+        .execute(&pool)
+        .await?;
+    }
+
+    let entries = get_stored_toggl_entries(owner, &[workspace_id], &since, Tz::UTC, &pool).await?;
+    assert_eq!(entries.len(), 2);
+
+    let entries =
+        get_stored_toggl_entries(teammate, &[workspace_id], &since, Tz::UTC, &pool).await?;
+    assert_eq!(entries.len(), 1);
+
+    let stored_workspaces = get_stored_workspaces(teammate, &[workspace_id], &pool).await?;
+    assert_eq!(
+        stored_workspaces
+            .get(&workspace_id)
+            .and_then(|stored_workspace| stored_workspace.entries_since),
+        Some(since),
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_toggl_json_refused() {
+    let response = Response::from(
+        axum::http::Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized")
+            .unwrap(),
+    );
+
+    let error = read_toggl_json::<Vec<TogglProject>>(response)
+        .await
+        .unwrap_err();
+
+    assert!(!error.toggl_unreachable());
+    assert_eq!(
+        error.detail(),
+        "Toggl didn't accept your API token. Replace it in your account settings.",
+    );
 }
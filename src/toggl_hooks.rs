@@ -0,0 +1,280 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    errors::AppResult,
+    toggl::{TogglUserId, WorkspaceId},
+};
+
+mod tests;
+
+pub static TOGGL_WEBHOOK_SECRET_VARIABLE: &str = "TOGGL_WEBHOOK_SECRET";
+
+/// The secret that the Toggl webhook subscriptions pointed at `/hooks/toggl/` sign events with
+#[derive(Clone)]
+pub struct TogglHookSecret(pub String);
+
+impl TogglHookSecret {
+    pub fn from_env() -> Option<Self> {
+        std::env::var(TOGGL_WEBHOOK_SECRET_VARIABLE)
+            .ok()
+            .filter(|secret| !secret.is_empty())
+            .map(Self)
+    }
+}
+
+/// An event sent by the Toggl Webhooks API
+#[derive(Debug, Deserialize)]
+struct TogglEvent {
+    /// Who created the subscription, which only sends what they can see
+    creator_id: Option<TogglUserId>,
+    metadata: Option<TogglEventMetadata>,
+    payload: serde_json::Value,
+    validation_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TogglEventMetadata {
+    #[serde(default)]
+    action: String,
+    #[serde(default)]
+    model: String,
+}
+
+/// A time entry as Toggl sends it in an event
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct HookEntry {
+    pub id: i64,
+    pub workspace_id: WorkspaceId,
+    pub project_id: Option<i64>,
+    pub description: Option<String>,
+    /// Toggl sends null rather than an empty list when there are no tags
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub billable: bool,
+    pub start: DateTime<Utc>,
+    /// In seconds, and negative while the entry is running
+    pub duration: i64,
+}
+
+/// A project as Toggl sends it in an event
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct HookProject {
+    pub id: i64,
+    pub workspace_id: WorkspaceId,
+    pub name: String,
+    pub active: bool,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeletedObject {
+    id: i64,
+    workspace_id: WorkspaceId,
+}
+
+/// What changed in Toggl, and whose webhook subscription said so
+#[derive(Debug, PartialEq)]
+pub struct Event {
+    pub subscriber: Option<TogglUserId>,
+    pub change: Change,
+}
+
+/// What changed in Toggl, according to an event
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    /// Sent to check a new subscription, which is only enabled once the code is echoed back
+    Ping(String),
+    EntrySaved(HookEntry),
+    EntryDeleted {
+        workspace_id: WorkspaceId,
+        entry_id: i64,
+    },
+    ProjectSaved(HookProject),
+    ProjectDeleted {
+        workspace_id: WorkspaceId,
+        project_id: i64,
+    },
+    /// Something that nothing stored depends on
+    Ignored,
+}
+
+impl Change {
+    pub fn workspace_id(&self) -> Option<WorkspaceId> {
+        match self {
+            Change::EntrySaved(entry) => Some(entry.workspace_id),
+            Change::EntryDeleted { workspace_id, .. } => Some(*workspace_id),
+            Change::ProjectSaved(project) => Some(project.workspace_id),
+            Change::ProjectDeleted { workspace_id, .. } => Some(*workspace_id),
+            Change::Ping(_) | Change::Ignored => None,
+        }
+    }
+}
+
+pub fn parse_event(body: &[u8]) -> Result<Event, serde_json::Error> {
+    let event: TogglEvent = serde_json::from_slice(body)?;
+
+    Ok(Event {
+        subscriber: event.creator_id,
+        change: parse_change(event)?,
+    })
+}
+
+fn parse_change(event: TogglEvent) -> Result<Change, serde_json::Error> {
+    if let Some(validation_code) = event.validation_code {
+        return Ok(Change::Ping(validation_code));
+    }
+
+    let Some(metadata) = event.metadata else {
+        return Ok(Change::Ignored);
+    };
+
+    Ok(match (metadata.model.as_str(), metadata.action.as_str()) {
+        ("time_entry", "deleted") => {
+            let deleted: DeletedObject = serde_json::from_value(event.payload)?;
+
+            Change::EntryDeleted {
+                workspace_id: deleted.workspace_id,
+                entry_id: deleted.id,
+            }
+        }
+        ("time_entry", "created" | "updated") => {
+            Change::EntrySaved(serde_json::from_value(event.payload)?)
+        }
+        ("project", "deleted") => {
+            let deleted: DeletedObject = serde_json::from_value(event.payload)?;
+
+            Change::ProjectDeleted {
+                workspace_id: deleted.workspace_id,
+                project_id: deleted.id,
+            }
+        }
+        ("project", "created" | "updated") => {
+            Change::ProjectSaved(serde_json::from_value(event.payload)?)
+        }
+        _ => Change::Ignored,
+    })
+}
+
+/// Update what's stored of a workspace for the subscriber after something changed in it
+///
+/// The subscriber's first event from a workspace marks it as hooked for them, after which what's
+/// stored of it is used for much longer before it's fetched again. Until its entries are first
+/// stored there's nothing to update, and whatever changed will be included when they're fetched.
+pub async fn apply_change(
+    subscriber: TogglUserId,
+    change: &Change,
+    pool: &PgPool,
+) -> AppResult<()> {
+    let Some(workspace_id) = change.workspace_id() else {
+        return Ok(());
+    };
+
+    let entries_since = sqlx::query!(
+        "INSERT INTO toggl_workspaces(toggl_user_id, workspace_id, hooked_at)
+        VALUES ($1, $2, now())
+        ON CONFLICT (toggl_user_id, workspace_id) DO UPDATE
+        SET hooked_at = COALESCE(toggl_workspaces.hooked_at, EXCLUDED.hooked_at)
+        RETURNING entries_since",
+        subscriber.0,
+        workspace_id.0,
+    )
+    .fetch_one(pool)
+    .await?
+    .entries_since;
+
+    if entries_since.is_none() {
+        return Ok(());
+    }
+
+    match change {
+        // Like the Reports API, running entries don't count until they're stopped
+        Change::EntrySaved(entry) if entry.duration < 0 => {
+            delete_entry(subscriber, workspace_id, entry.id, pool).await?;
+        }
+        Change::EntrySaved(entry) => {
+            sqlx::query!(
+                "INSERT INTO toggl_entries(toggl_user_id, workspace_id, entry_id, project_id,
+                    description, client, tags, billable, start, duration)
+                VALUES (
+                    $1, $2, $3, $4, $5,
+                    (
+                        SELECT client
+                        FROM toggl_projects
+                        WHERE toggl_user_id = $1 AND workspace_id = $2 AND project_id = $4
+                    ),
+                    $6, $7, $8, $9
+                )
+                ON CONFLICT (toggl_user_id, workspace_id, entry_id) DO UPDATE
+                SET project_id = EXCLUDED.project_id, description = EXCLUDED.description,
+                    client = EXCLUDED.client, tags = EXCLUDED.tags, billable = EXCLUDED.billable,
+                    start = EXCLUDED.start, duration = EXCLUDED.duration",
+                subscriber.0,
+                workspace_id.0,
+                entry.id,
+                entry.project_id,
+                entry.description.clone().unwrap_or_default(),
+                &entry.tags.clone().unwrap_or_default(),
+                entry.billable,
+                entry.start,
+                entry.duration * 1000,
+            )
+            .execute(pool)
+            .await?;
+        }
+        Change::EntryDeleted { entry_id, .. } => {
+            delete_entry(subscriber, workspace_id, *entry_id, pool).await?;
+        }
+        Change::ProjectSaved(project) => {
+            sqlx::query!(
+                "INSERT INTO toggl_projects(toggl_user_id, workspace_id, project_id, name, active,
+                    color)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (toggl_user_id, workspace_id, project_id) DO UPDATE
+                SET name = EXCLUDED.name, active = EXCLUDED.active, color = EXCLUDED.color",
+                subscriber.0,
+                workspace_id.0,
+                project.id,
+                project.name,
+                project.active,
+                project.color,
+            )
+            .execute(pool)
+            .await?;
+        }
+        Change::ProjectDeleted { project_id, .. } => {
+            sqlx::query!(
+                "DELETE FROM toggl_projects
+                WHERE toggl_user_id = $1 AND workspace_id = $2 AND project_id = $3",
+                subscriber.0,
+                workspace_id.0,
+                project_id,
+            )
+            .execute(pool)
+            .await?;
+        }
+        Change::Ping(_) | Change::Ignored => {}
+    }
+
+    Ok(())
+}
+
+async fn delete_entry(
+    subscriber: TogglUserId,
+    workspace_id: WorkspaceId,
+    entry_id: i64,
+    pool: &PgPool,
+) -> AppResult<()> {
+    sqlx::query!(
+        "DELETE FROM toggl_entries
+        WHERE toggl_user_id = $1 AND workspace_id = $2 AND entry_id = $3",
+        subscriber.0,
+        workspace_id.0,
+        entry_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
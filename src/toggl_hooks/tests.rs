@@ -0,0 +1,269 @@
+#![cfg(test)]
+
+use super::*;
+
+const PING: &str = r#"{
+    "created_at": "2026-10-18T13:59:00.120Z",
+    "creator_id": 1111111,
+    "metadata": {"event_user_id": "1111111", "request_type": "POST"},
+    "payload": "ping",
+    "subscription_id": 5555,
+    "timestamp": "2026-10-18T13:59:00.240Z",
+    "url_callback": "https://yottaclock.com/hooks/toggl/",
+    "validation_code": "6a3c8e1f-4b2d-4f0e-9a7c-2d5b8e1f3a6c",
+    "validation_code_url": "https://track.toggl.com/webhooks/api/v1/validate/2222222/5555/6a3c8e1f-4b2d-4f0e-9a7c-2d5b8e1f3a6c"
+}"#;
+
+const ENTRY_CREATED: &str = r#"{
+    "event_id": 9000000001,
+    "created_at": "2026-10-18T14:00:01.512Z",
+    "creator_id": 1111111,
+    "metadata": {
+        "action": "created",
+        "event_user_id": "1111111",
+        "model": "time_entry",
+        "path": "/api/v9/workspaces/2222222/time_entries",
+        "request_type": "POST",
+        "time_entry_id": "3333333333",
+        "workspace_id": "2222222"
+    },
+    "payload": {
+        "at": "2026-10-18T14:00:01+00:00",
+        "billable": true,
+        "description": "Chapter 3",
+        "duration": 1800,
+        "duronly": true,
+        "id": 3333333333,
+        "pid": 12345678,
+        "project_id": 12345678,
+        "server_deleted_at": null,
+        "start": "2026-10-18T13:30:00+00:00",
+        "stop": "2026-10-18T14:00:00+00:00",
+        "tag_ids": [4444],
+        "tags": ["focus"],
+        "task_id": null,
+        "uid": 1111111,
+        "user_id": 1111111,
+        "wid": 2222222,
+        "workspace_id": 2222222
+    },
+    "subscription_id": 5555,
+    "timestamp": "2026-10-18T14:00:01.650Z",
+    "url_callback": "https://yottaclock.com/hooks/toggl/"
+}"#;
+
+const ENTRY_STARTED: &str = r#"{
+    "event_id": 9000000002,
+    "created_at": "2026-10-18T14:05:00.000Z",
+    "creator_id": 1111111,
+    "metadata": {"action": "updated", "model": "time_entry", "workspace_id": "2222222"},
+    "payload": {
+        "billable": false,
+        "description": "Chapter 3",
+        "duration": -1792332300,
+        "id": 3333333333,
+        "project_id": 12345678,
+        "start": "2026-10-18T14:05:00+00:00",
+        "stop": null,
+        "tags": null,
+        "workspace_id": 2222222
+    },
+    "subscription_id": 5555,
+    "timestamp": "2026-10-18T14:05:00.100Z",
+    "url_callback": "https://yottaclock.com/hooks/toggl/"
+}"#;
+
+const ENTRY_DELETED: &str = r#"{
+    "event_id": 9000000003,
+    "created_at": "2026-10-18T14:10:00.000Z",
+    "creator_id": 1111111,
+    "metadata": {"action": "deleted", "model": "time_entry", "workspace_id": "2222222"},
+    "payload": {"id": 3333333333, "workspace_id": 2222222},
+    "subscription_id": 5555,
+    "timestamp": "2026-10-18T14:10:00.100Z",
+    "url_callback": "https://yottaclock.com/hooks/toggl/"
+}"#;
+
+const PROJECT_ARCHIVED: &str = r##"{
+    "event_id": 9000000004,
+    "created_at": "2026-10-18T14:15:00.000Z",
+    "creator_id": 1111111,
+    "metadata": {"action": "updated", "model": "project", "workspace_id": "2222222"},
+    "payload": {
+        "active": false,
+        "client_id": 7777,
+        "color": "#2da608",
+        "id": 12345678,
+        "is_private": false,
+        "name": "Novel (first draft)",
+        "workspace_id": 2222222
+    },
+    "subscription_id": 5555,
+    "timestamp": "2026-10-18T14:15:00.100Z",
+    "url_callback": "https://yottaclock.com/hooks/toggl/"
+}"##;
+
+const TAG_CREATED: &str = r#"{
+    "event_id": 9000000005,
+    "created_at": "2026-10-18T14:20:00.000Z",
+    "creator_id": 1111111,
+    "metadata": {"action": "created", "model": "tag", "workspace_id": "2222222"},
+    "payload": {"id": 4445, "name": "deep work", "workspace_id": 2222222},
+    "subscription_id": 5555,
+    "timestamp": "2026-10-18T14:20:00.100Z",
+    "url_callback": "https://yottaclock.com/hooks/toggl/"
+}"#;
+
+const WORKSPACE_ID: WorkspaceId = WorkspaceId(2222222);
+
+const SUBSCRIBER: TogglUserId = TogglUserId(1111111);
+
+#[test]
+fn test_parse_event() {
+    assert_eq!(
+        parse_event(PING.as_bytes()).unwrap().change,
+        Change::Ping("6a3c8e1f-4b2d-4f0e-9a7c-2d5b8e1f3a6c".to_owned()),
+    );
+
+    assert_eq!(
+        parse_event(ENTRY_CREATED.as_bytes()).unwrap().change,
+        Change::EntrySaved(HookEntry {
+            id: 3333333333,
+            workspace_id: WORKSPACE_ID,
+            project_id: Some(12345678),
+            description: Some("Chapter 3".to_owned()),
+            tags: Some(Vec::from(["focus".to_owned()])),
+            billable: true,
+            start: "2026-10-18T13:30:00Z".parse().unwrap(),
+            duration: 1800,
+        }),
+    );
+
+    assert_eq!(
+        parse_event(ENTRY_DELETED.as_bytes()).unwrap().change,
+        Change::EntryDeleted {
+            workspace_id: WORKSPACE_ID,
+            entry_id: 3333333333,
+        },
+    );
+
+    assert_eq!(
+        parse_event(PROJECT_ARCHIVED.as_bytes()).unwrap().change,
+        Change::ProjectSaved(HookProject {
+            id: 12345678,
+            workspace_id: WORKSPACE_ID,
+            name: "Novel (first draft)".to_owned(),
+            active: false,
+            color: Some("#2da608".to_owned()),
+        }),
+    );
+
+    assert_eq!(
+        parse_event(TAG_CREATED.as_bytes()).unwrap().change,
+        Change::Ignored
+    );
+
+    assert_eq!(
+        parse_event(ENTRY_DELETED.as_bytes()).unwrap().subscriber,
+        Some(SUBSCRIBER),
+    );
+
+    assert!(parse_event(b"not json").is_err());
+}
+
+fn change(event: &str) -> Change {
+    parse_event(event.as_bytes()).unwrap().change
+}
+
+async fn stored_entries(
+    toggl_user_id: TogglUserId,
+    pool: &PgPool,
+) -> Vec<(i64, Option<String>, Vec<String>, i64)> {
+    sqlx::query!(
+        "SELECT entry_id, client, tags, duration
+        FROM toggl_entries
+        WHERE toggl_user_id = $1
+        ORDER BY entry_id",
+        toggl_user_id.0,
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|record| (record.entry_id, record.client, record.tags, record.duration))
+    .collect()
+}
+
+#[sqlx::test]
+async fn test_apply_change(pool: PgPool) -> AppResult<()> {
+    // Nothing is stored until the workspace's entries are first fetched
+    apply_change(SUBSCRIBER, &change(ENTRY_CREATED), &pool).await?;
+
+    let workspace = sqlx::query!("SELECT workspace_id, entries_since FROM toggl_workspaces")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(workspace.workspace_id, WORKSPACE_ID.0);
+    assert_eq!(workspace.entries_since, None);
+    assert!(stored_entries(SUBSCRIBER, &pool).await.is_empty());
+
+    sqlx::query!("UPDATE toggl_workspaces SET entries_since = '2026-10-01', synced_at = now()")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query!(
+        "INSERT INTO toggl_projects(toggl_user_id, workspace_id, project_id, name, active, client)
+        VALUES ($1, $2, 12345678, 'Novel', TRUE, 'Writing')",
+        SUBSCRIBER.0,
+        WORKSPACE_ID.0,
+    )
+    .execute(&pool)
+    .await?;
+
+    apply_change(SUBSCRIBER, &change(ENTRY_CREATED), &pool).await?;
+    assert_eq!(
+        stored_entries(SUBSCRIBER, &pool).await,
+        Vec::from([(
+            3333333333,
+            Some("Writing".to_owned()),
+            Vec::from(["focus".to_owned()]),
+            1800 * 1000,
+        )]),
+    );
+
+    // Someone else sharing the workspace has what they can see of it stored separately
+    let teammate = TogglUserId(6666666);
+    sqlx::query!(
+        "INSERT INTO toggl_workspaces(toggl_user_id, workspace_id, entries_since, synced_at)
+        VALUES ($1, $2, '2026-10-01', now())",
+        teammate.0,
+        WORKSPACE_ID.0,
+    )
+    .execute(&pool)
+    .await?;
+
+    apply_change(teammate, &change(ENTRY_DELETED), &pool).await?;
+    assert_eq!(stored_entries(SUBSCRIBER, &pool).await.len(), 1);
+    assert!(stored_entries(teammate, &pool).await.is_empty());
+
+    // Restarting an entry takes it out until it's stopped again
+    apply_change(SUBSCRIBER, &change(ENTRY_STARTED), &pool).await?;
+    assert!(stored_entries(SUBSCRIBER, &pool).await.is_empty());
+
+    apply_change(SUBSCRIBER, &change(ENTRY_CREATED), &pool).await?;
+    apply_change(SUBSCRIBER, &change(ENTRY_DELETED), &pool).await?;
+    assert!(stored_entries(SUBSCRIBER, &pool).await.is_empty());
+
+    apply_change(SUBSCRIBER, &change(PROJECT_ARCHIVED), &pool).await?;
+    let project = sqlx::query!(
+        "SELECT name, active, client, color FROM toggl_projects WHERE toggl_user_id = $1",
+        SUBSCRIBER.0,
+    )
+    .fetch_one(&pool)
+    .await?;
+    assert_eq!(project.name, "Novel (first draft)");
+    assert!(!project.active);
+    assert_eq!(project.client.as_deref(), Some("Writing"));
+    assert_eq!(project.color.as_deref(), Some("#2da608"));
+
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex, PoisonError},
+    time::Duration,
+};
+
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    RequestBuilder, Response, StatusCode,
+};
+use sha2::{Digest, Sha256};
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::errors::AppResult;
+
+mod tests;
+
+/// How many requests can be sent at once with an API token that hasn't been used in a while
+const BUCKET_CAPACITY: f64 = 3.0;
+
+/// Toggl allows about one request a second for each API token
+const REQUESTS_PER_SECOND: f64 = 1.0;
+
+/// How many times to try a request that's rate limited or that Toggl fails to respond to
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// How long to wait before the first retry, which doubles after every attempt that fails
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The rate limit of every API token that's been used recently, keyed by a digest of the token so
+/// that the tokens themselves aren't kept around
+///
+/// Buckets that have refilled are dropped, so tokens that were replaced or belong to deleted users
+/// aren't kept either.
+static BUCKETS: LazyLock<Mutex<HashMap<Vec<u8>, TokenBucket>>> = LazyLock::new(Default::default);
+
+/// How many requests can be sent right away with an API token, which refills at the rate Toggl
+/// allows
+#[derive(Debug)]
+pub struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// Set when Toggl says how long to wait, which holds off every request with the token
+    blocked_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            tokens: BUCKET_CAPACITY,
+            updated_at: now,
+            blocked_until: None,
+        }
+    }
+
+    /// Take a token if there is one, or else say how long until there will be
+    pub fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        if let Some(blocked_until) = self.blocked_until {
+            if now < blocked_until {
+                return Err(blocked_until - now);
+            }
+
+            self.blocked_until = None;
+        }
+
+        let elapsed = now.saturating_duration_since(self.updated_at);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * REQUESTS_PER_SECOND).min(BUCKET_CAPACITY);
+        self.updated_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / REQUESTS_PER_SECOND,
+            ))
+        }
+    }
+
+    /// Whether the bucket has refilled and isn't blocked, which makes it no different from a new one
+    pub fn is_full(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated_at);
+
+        self.blocked_until
+            .is_none_or(|blocked_until| blocked_until <= now)
+            && self.tokens + elapsed.as_secs_f64() * REQUESTS_PER_SECOND >= BUCKET_CAPACITY
+    }
+
+    /// Hold off every request until `until`, after which the bucket starts refilling from empty
+    pub fn block_until(&mut self, until: Instant) {
+        let until = self
+            .blocked_until
+            .map_or(until, |blocked| blocked.max(until));
+
+        self.blocked_until = Some(until);
+        self.tokens = 0.0;
+        self.updated_at = until;
+    }
+}
+
+/// How long to wait before trying a request again after it failed `attempts` times
+///
+/// `jitter` scales the delay so that requests that failed together aren't all tried again
+/// together.
+pub fn backoff(attempts: u32, jitter: f64) -> Duration {
+    FIRST_BACKOFF
+        .saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF)
+        .mul_f64(jitter)
+}
+
+/// How long a `Retry-After` header says to wait, if it's given in seconds
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(Duration::from_secs(seconds).min(MAX_BACKOFF))
+}
+
+fn with_bucket<T>(key: &[u8], f: impl FnOnce(&mut TokenBucket, Instant) -> T) -> T {
+    let mut buckets = BUCKETS.lock().unwrap_or_else(PoisonError::into_inner);
+    let now = Instant::now();
+
+    let bucket = buckets
+        .entry(key.to_vec())
+        .or_insert_with(|| TokenBucket::new(now));
+
+    f(bucket, now)
+}
+
+async fn wait_for_token(key: &[u8]) {
+    while let Err(wait) = with_bucket(key, |bucket, now| bucket.take(now)) {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Send a request to Toggl with an API token once the token's rate limit allows
+///
+/// Requests that are rate limited, or that fail because Toggl can't be reached or has a problem of
+/// its own, are tried again after a backoff or after as long as Toggl says to wait. Once they've
+/// been tried `MAX_ATTEMPTS` times, the last failure is returned as an error. Any other response
+/// is returned as it is.
+pub async fn send(api_token: &str, request: RequestBuilder) -> AppResult<Response> {
+    let key = Sha256::digest(api_token.as_bytes()).to_vec();
+    let request = request.basic_auth(api_token, Some("api_token"));
+
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+
+        wait_for_token(&key).await;
+
+        let result = request
+            .try_clone()
+            .expect("Toggl requests don't have streaming bodies")
+            .send()
+            .await;
+
+        let delay = match &result {
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                let delay = retry_after(response.headers())
+                    .unwrap_or_else(|| backoff(attempts, rand::random_range(0.5..=1.0)));
+
+                // Every other request with the token would be rate limited too
+                with_bucket(&key, |bucket, now| bucket.block_until(now + delay));
+
+                delay
+            }
+            Ok(response) if response.status().is_server_error() => retry_after(response.headers())
+                .unwrap_or_else(|| backoff(attempts, rand::random_range(0.5..=1.0))),
+            Err(error) if error.is_connect() || error.is_timeout() => {
+                backoff(attempts, rand::random_range(0.5..=1.0))
+            }
+            _ => return Ok(result?),
+        };
+
+        if attempts >= MAX_ATTEMPTS {
+            warn!("Giving up on a Toggl request after {attempts} attempts");
+
+            return Ok(result?.error_for_status()?);
+        }
+
+        warn!("Toggl request failed (attempt {attempts}), trying again in {delay:?}");
+
+        tokio::time::sleep(delay).await;
+    }
+}
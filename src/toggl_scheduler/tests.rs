@@ -0,0 +1,154 @@
+#![cfg(test)]
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use axum::{extract::Extension, http::HeaderValue, response::IntoResponse, routing::get, Router};
+use reqwest::Client;
+use tokio::net::TcpListener;
+
+use super::*;
+
+#[test]
+fn test_token_bucket() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(start);
+
+    // A burst goes through right away, and then requests wait for the bucket to refill
+    assert_eq!(bucket.take(start), Ok(()));
+    assert_eq!(bucket.take(start), Ok(()));
+    assert_eq!(bucket.take(start), Ok(()));
+    assert_eq!(bucket.take(start), Err(Duration::from_secs(1)));
+
+    let later = start + Duration::from_millis(1500);
+    assert_eq!(bucket.take(later), Ok(()));
+    assert_eq!(bucket.take(later), Err(Duration::from_millis(500)));
+
+    // The bucket never holds more than a burst
+    let much_later = later + Duration::from_secs(60);
+    assert_eq!(bucket.take(much_later), Ok(()));
+    assert_eq!(bucket.take(much_later), Ok(()));
+    assert_eq!(bucket.take(much_later), Ok(()));
+    assert!(bucket.take(much_later).is_err());
+}
+
+#[test]
+fn test_token_bucket_blocked() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(start);
+
+    bucket.block_until(start + Duration::from_secs(30));
+    assert_eq!(bucket.take(start), Err(Duration::from_secs(30)));
+
+    // A shorter wait doesn't cut a longer one short
+    bucket.block_until(start + Duration::from_secs(10));
+    assert_eq!(
+        bucket.take(start + Duration::from_secs(20)),
+        Err(Duration::from_secs(10)),
+    );
+
+    // The bucket starts refilling from empty once the wait is over
+    let unblocked = start + Duration::from_secs(30);
+    assert_eq!(bucket.take(unblocked), Err(Duration::from_secs(1)));
+    assert_eq!(bucket.take(unblocked + Duration::from_secs(1)), Ok(()));
+}
+
+#[test]
+fn test_token_bucket_is_full() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(start);
+    assert!(bucket.is_full(start));
+
+    assert_eq!(bucket.take(start), Ok(()));
+    assert!(!bucket.is_full(start));
+    assert!(bucket.is_full(start + Duration::from_secs(1)));
+
+    // A blocked bucket isn't full until it's refilled after the wait
+    bucket.block_until(start + Duration::from_secs(10));
+    assert!(!bucket.is_full(start + Duration::from_secs(12)));
+    assert!(bucket.is_full(start + Duration::from_secs(13)));
+}
+
+#[test]
+fn test_backoff() {
+    assert_eq!(backoff(1, 1.0), Duration::from_secs(1));
+    assert_eq!(backoff(2, 1.0), Duration::from_secs(2));
+    assert_eq!(backoff(4, 1.0), Duration::from_secs(8));
+    assert_eq!(backoff(4, 0.5), Duration::from_secs(4));
+    assert_eq!(backoff(10, 1.0), MAX_BACKOFF);
+    assert_eq!(backoff(u32::MAX, 1.0), MAX_BACKOFF);
+}
+
+#[test]
+fn test_retry_after() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(retry_after(&headers), None);
+
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("5"));
+    assert_eq!(retry_after(&headers), Some(Duration::from_secs(5)));
+
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("86400"));
+    assert_eq!(retry_after(&headers), Some(MAX_BACKOFF));
+
+    headers.insert(
+        RETRY_AFTER,
+        HeaderValue::from_static("Wed, 21 Oct 2026 07:28:00 GMT"),
+    );
+    assert_eq!(retry_after(&headers), None);
+}
+
+/// A Toggl stand-in that rate limits the first `limited` requests it gets
+async fn fake_toggl(limited: u32) -> (String, Arc<AtomicU32>) {
+    let requests = Arc::new(AtomicU32::new(0));
+
+    let app = Router::new()
+        .route(
+            "/workspaces",
+            get(
+                move |Extension(requests): Extension<Arc<AtomicU32>>| async move {
+                    if requests.fetch_add(1, Ordering::SeqCst) < limited {
+                        (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, "0")]).into_response()
+                    } else {
+                        StatusCode::OK.into_response()
+                    }
+                },
+            ),
+        )
+        .layer(Extension(requests.clone()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    (format!("http://{address}/workspaces"), requests)
+}
+
+#[tokio::test]
+async fn test_send_retries() {
+    let (url, requests) = fake_toggl(2).await;
+
+    let response = send("retried token", Client::new().get(&url))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_send_gives_up() {
+    let (url, requests) = fake_toggl(u32::MAX).await;
+
+    let error = send("limited token", Client::new().get(&url))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        crate::errors::AppError::Toggl(ref error)
+            if error.status() == Some(StatusCode::TOO_MANY_REQUESTS)
+    ));
+    assert_eq!(requests.load(Ordering::SeqCst), MAX_ATTEMPTS);
+}
@@ -0,0 +1,90 @@
+use chrono::NaiveDate;
+use futures::{stream, StreamExt};
+use reqwest::Client;
+use sqlx::PgPool;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, warn};
+
+use crate::{
+    crypto::ServerKey,
+    errors::AppResult,
+    session::UserKey,
+    toggl::{get_user_toggl_api_token, get_user_workspace_ids, sync_toggl_workspaces},
+};
+
+/// How often to fetch whatever of the users' Toggl data wasn't stored recently enough, so that
+/// it's ready before they need it
+///
+/// This matches how long stored data is used for, so that none of it is older than a minute.
+const SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How many users to sync at once, each of whom has their own Toggl rate limit
+const SYNC_CONCURRENCY: usize = 8;
+
+/// Sync the users' Toggl data for as long as the server runs
+pub async fn run(pool: PgPool, client: Client, server_key: ServerKey) {
+    let mut interval = tokio::time::interval(SYNC_INTERVAL);
+    // A sync that runs long is followed by a whole interval rather than a burst of them
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = sync_users(&pool, &client, &server_key).await {
+            error!("Could not sync Toggl data: {error}");
+        }
+    }
+}
+
+async fn sync_users(pool: &PgPool, client: &Client, server_key: &ServerKey) -> AppResult<()> {
+    // Only users with projects need anything from Toggl, going back to their earliest project
+    let records = sqlx::query!(
+        r#"SELECT users.user_key, MIN(projects.starting_date) AS "earliest_start!"
+        FROM users
+        INNER JOIN projects
+        ON users.user_key = projects.user_key
+        WHERE users.toggl_api_key_ciphertext IS NOT NULL
+        GROUP BY users.user_key
+        ORDER BY users.user_key"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    stream::iter(records)
+        .for_each_concurrent(SYNC_CONCURRENCY, |record| async move {
+            let user_key = UserKey(record.user_key);
+
+            if let Err(error) =
+                sync_user(user_key, &record.earliest_start, pool, client, server_key).await
+            {
+                warn!("Could not sync Toggl data for user {user_key:?}: {error}");
+            }
+        })
+        .await;
+
+    Ok(())
+}
+
+async fn sync_user(
+    user_key: UserKey,
+    since: &NaiveDate,
+    pool: &PgPool,
+    client: &Client,
+    server_key: &ServerKey,
+) -> AppResult<()> {
+    let toggl_api_token = get_user_toggl_api_token(user_key, pool, server_key).await?;
+
+    let workspace_ids = get_user_workspace_ids(user_key, pool).await?;
+
+    sync_toggl_workspaces(
+        user_key,
+        &workspace_ids,
+        &toggl_api_token,
+        since,
+        client,
+        pool,
+    )
+    .await?;
+
+    Ok(())
+}
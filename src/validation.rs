@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+mod tests;
+
+/// Messages for the fields of a form that failed validation, keyed by field name, so that the
+/// form can be rendered again with them next to the user's input
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FieldErrors(BTreeMap<&'static str, String>);
+
+impl FieldErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an error for a field, keeping the first one if there are several
+    pub fn add(&mut self, field: &'static str, message: impl Into<String>) {
+        self.0.entry(field).or_insert_with(|| message.into());
+    }
+
+    /// Record an error for a field if a condition doesn't hold
+    pub fn require(&mut self, condition: bool, field: &'static str, message: impl Into<String>) {
+        if !condition {
+            self.add(field, message);
+        }
+    }
+
+    /// Record the errors from another check, keeping the ones already recorded
+    pub fn merge(&mut self, other: FieldErrors) {
+        for (field, message) in other.0 {
+            self.add(field, message);
+        }
+    }
+
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.0.get(field).map(String::as_str)
+    }
+
+    pub fn has(&self, field: &str) -> bool {
+        self.0.contains_key(field)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Every message in one sentence each, for when they can't be shown next to their fields
+    pub fn summary(&self) -> String {
+        self.0
+            .values()
+            .map(|message| format!("{message}."))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
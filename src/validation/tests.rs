@@ -0,0 +1,22 @@
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn test_field_errors() {
+    let mut errors = FieldErrors::new();
+    assert!(errors.is_empty());
+
+    errors.require(true, "date", "Choose a date");
+    assert!(errors.is_empty());
+
+    errors.require(false, "date", "Choose a date");
+    errors.add("date", "You already have a day off on this date");
+    errors.add("project_keys", "Unknown project");
+
+    assert!(!errors.is_empty());
+    assert!(errors.has("date"));
+    assert_eq!(errors.get("date"), Some("Choose a date"));
+    assert_eq!(errors.get("project_keys"), Some("Unknown project"));
+    assert_eq!(errors.get("name"), None);
+}
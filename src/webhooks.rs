@@ -0,0 +1,538 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::{error, warn};
+
+use crate::{
+    crypto::{sign_webhook_payload, ServerKey},
+    errors::AppResult,
+    notifications::is_webhook_url,
+    session::UserKey,
+    toggl::{calculate_goals, ProjectKey, Status},
+};
+
+mod tests;
+
+/// How often to check whether anything happened that webhooks are subscribed to
+const EVENT_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// How often to send deliveries that are due, including retries
+const DELIVERY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How long to wait for a webhook or notification to respond, so that a slow one doesn't hold up
+/// the rest
+pub const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How many times to try a delivery before giving up on it
+pub const MAX_ATTEMPTS: i32 = 8;
+
+/// How long to wait before the first retry, which doubles after every attempt that fails
+const FIRST_RETRY_DELAY_MINUTES: i64 = 1;
+
+/// The most deliveries to send at once
+const DELIVERY_BATCH_SIZE: i64 = 100;
+
+/// Something that happened that a webhook can be subscribed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    /// A project with a goal today has no debt left
+    GoalMet,
+    /// There's no total debt left today
+    DebtCleared,
+    /// The total debt is over the webhook's threshold
+    DebtExceeded,
+    /// It's a new day in the user's timezone
+    DayStarted,
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::GoalMet => write!(f, "goal_met"),
+            Event::DebtCleared => write!(f, "debt_cleared"),
+            Event::DebtExceeded => write!(f, "debt_exceeded"),
+            Event::DayStarted => write!(f, "day_started"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Unrecognized webhook event: {0}")]
+pub struct ParseEventError(String);
+
+impl FromStr for Event {
+    type Err = ParseEventError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "goal_met" => Ok(Event::GoalMet),
+            "debt_cleared" => Ok(Event::DebtCleared),
+            "debt_exceeded" => Ok(Event::DebtExceeded),
+            "day_started" => Ok(Event::DayStarted),
+            _ => Err(ParseEventError(s.to_string())),
+        }
+    }
+}
+
+/// Which events a webhook is sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscriptions {
+    pub goal_met: bool,
+    pub debt_cleared: bool,
+    pub debt_exceeded: bool,
+    pub day_started: bool,
+    /// How much total debt counts as exceeded
+    pub debt_threshold: Duration,
+}
+
+/// The project an event is about, with every duration in seconds
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ProjectPayload {
+    pub project_key: ProjectKey,
+    pub name: String,
+    pub debt: i64,
+    pub logged_today: i64,
+}
+
+/// What's sent to a webhook, with every duration in seconds
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Payload {
+    pub event: Event,
+    /// The user's date when the event happened
+    pub date: NaiveDate,
+    pub total_debt: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<ProjectPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debt_threshold: Option<i64>,
+}
+
+/// The events a webhook is subscribed to that have happened as of `status`
+///
+/// Events are worked out from where the user stands rather than from what changed since the last
+/// check, and each one is only delivered once a day, so one that happens while the server is down
+/// is still delivered once it's back.
+pub fn events(status: &Status, subscriptions: &Subscriptions) -> Vec<Payload> {
+    let payload = |event, project, debt_threshold| Payload {
+        event,
+        date: status.today,
+        total_debt: status.total_debt.num_seconds(),
+        project,
+        debt_threshold,
+    };
+
+    let mut events = Vec::new();
+
+    if subscriptions.day_started {
+        events.push(payload(Event::DayStarted, None, None));
+    }
+
+    if subscriptions.goal_met {
+        for goal in &status.goals {
+            if goal.limit.is_none()
+                && goal.goal_today > Duration::zero()
+                && goal.time <= Duration::zero()
+            {
+                let project = ProjectPayload {
+                    project_key: goal.project_key,
+                    name: goal.name.clone(),
+                    debt: goal.time.num_seconds(),
+                    logged_today: goal.logged_today.num_seconds(),
+                };
+
+                events.push(payload(Event::GoalMet, Some(project), None));
+            }
+        }
+    }
+
+    // A day without anything to do doesn't count as clearing it
+    let anything_today = status
+        .goals
+        .iter()
+        .any(|goal| goal.goal_today > Duration::zero());
+
+    if subscriptions.debt_cleared && anything_today && status.total_debt <= Duration::zero() {
+        events.push(payload(Event::DebtCleared, None, None));
+    }
+
+    if subscriptions.debt_exceeded && status.total_debt > subscriptions.debt_threshold {
+        events.push(payload(
+            Event::DebtExceeded,
+            None,
+            Some(subscriptions.debt_threshold.num_seconds()),
+        ));
+    }
+
+    events
+}
+
+/// How long to wait after a failed attempt before trying again, or `None` to give up
+pub fn retry_delay(attempts: i32) -> Option<Duration> {
+    if attempts >= MAX_ATTEMPTS {
+        None
+    } else {
+        Some(Duration::minutes(
+            FIRST_RETRY_DELAY_MINUTES << (attempts - 1).clamp(0, 30),
+        ))
+    }
+}
+
+/// Queue a delivery of an event, unless the webhook has already been sent it today
+async fn insert_delivery(webhook_key: i64, payload: &Payload, pool: &PgPool) -> AppResult<bool> {
+    let body = serde_json::to_string(payload).expect("Payloads are always valid JSON");
+
+    Ok(sqlx::query!(
+        "INSERT INTO webhook_deliveries(webhook_key, event, event_date, project_key, payload)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT DO NOTHING",
+        webhook_key,
+        payload.event.to_string(),
+        payload.date,
+        payload
+            .project
+            .as_ref()
+            .map(|project| project.project_key.0),
+        body,
+    )
+    .execute(pool)
+    .await?
+    .rows_affected()
+        > 0)
+}
+
+/// Why a delivery didn't go through
+#[derive(Debug)]
+pub struct DeliveryFailure {
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+/// POST a signed payload to a webhook, returning the response's status if it was successful
+pub async fn send_delivery(
+    client: &Client,
+    url: &str,
+    delivery_key: i64,
+    event: &str,
+    payload: &str,
+    secret: &str,
+) -> Result<u16, DeliveryFailure> {
+    if !is_webhook_url(url) {
+        return Err(DeliveryFailure {
+            status: None,
+            message: format!("Webhooks can't be sent to {url}"),
+        });
+    }
+
+    let response = client
+        .post(url)
+        .timeout(REQUEST_TIMEOUT)
+        .header(CONTENT_TYPE, "application/json")
+        .header("X-Yottaclock-Event", event)
+        .header("X-Yottaclock-Delivery", delivery_key.to_string())
+        .header(
+            "X-Yottaclock-Signature",
+            sign_webhook_payload(secret, payload.as_bytes()),
+        )
+        .body(payload.to_owned())
+        .send()
+        .await
+        .map_err(|error| DeliveryFailure {
+            status: None,
+            message: error.to_string(),
+        })?;
+
+    let status = response.status();
+
+    if status.is_success() {
+        Ok(status.as_u16())
+    } else {
+        Err(DeliveryFailure {
+            status: Some(status.as_u16()),
+            message: format!("The webhook responded with {status}"),
+        })
+    }
+}
+
+/// Check for events and send deliveries for as long as the server runs
+pub async fn run(pool: PgPool, client: Client, webhook_client: Client, server_key: ServerKey) {
+    let mut event_check_interval = tokio::time::interval(EVENT_CHECK_INTERVAL);
+    let mut delivery_interval = tokio::time::interval(DELIVERY_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = event_check_interval.tick() => {
+                if let Err(error) = record_events(&pool, &client, &server_key).await {
+                    error!("Could not check for webhook events: {error}");
+                }
+            }
+            _ = delivery_interval.tick() => {
+                if let Err(error) = send_due_deliveries(&pool, &webhook_client, &server_key).await {
+                    error!("Could not send webhook deliveries: {error}");
+                }
+            }
+        }
+    }
+}
+
+async fn record_events(pool: &PgPool, client: &Client, server_key: &ServerKey) -> AppResult<()> {
+    let records = sqlx::query!(
+        "SELECT webhook_key, user_key, goal_met, debt_cleared, debt_exceeded, day_started,
+            debt_threshold
+        FROM webhooks
+        ORDER BY webhook_key",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut webhooks_by_user: HashMap<UserKey, Vec<(i64, Subscriptions)>> = HashMap::new();
+    for record in records {
+        webhooks_by_user
+            .entry(UserKey(record.user_key))
+            .or_default()
+            .push((
+                record.webhook_key,
+                Subscriptions {
+                    goal_met: record.goal_met,
+                    debt_cleared: record.debt_cleared,
+                    debt_exceeded: record.debt_exceeded,
+                    day_started: record.day_started,
+                    debt_threshold: Duration::seconds(record.debt_threshold),
+                },
+            ));
+    }
+
+    for (user_key, webhooks) in webhooks_by_user {
+        let status =
+            match calculate_goals(user_key, pool.clone(), client.clone(), server_key.clone()).await
+            {
+                Ok(Some(status)) => status,
+                Ok(None) => continue,
+                Err(error) => {
+                    warn!("Could not calculate goals for user {user_key:?} for webhooks: {error}");
+                    continue;
+                }
+            };
+
+        for (webhook_key, subscriptions) in webhooks {
+            for payload in events(&status, &subscriptions) {
+                insert_delivery(webhook_key, &payload, pool).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_due_deliveries(
+    pool: &PgPool,
+    client: &Client,
+    server_key: &ServerKey,
+) -> AppResult<()> {
+    let records = sqlx::query!(
+        "SELECT delivery_key, event, payload, attempts, url, secret_ciphertext
+        FROM webhook_deliveries
+        INNER JOIN webhooks
+        ON webhook_deliveries.webhook_key = webhooks.webhook_key
+        WHERE next_attempt_at <= now()
+        ORDER BY next_attempt_at
+        LIMIT $1",
+        DELIVERY_BATCH_SIZE,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for record in records {
+        let attempts = record.attempts + 1;
+
+        let secret = match server_key.decrypt(&record.secret_ciphertext) {
+            Ok(secret) => secret,
+            Err(error) => {
+                // Trying again won't help, and the rest of the batch still has to be sent
+                error!(
+                    "Could not decrypt the webhook secret for delivery {}: {error}",
+                    record.delivery_key,
+                );
+
+                sqlx::query!(
+                    "UPDATE webhook_deliveries
+                    SET attempts = $2, next_attempt_at = NULL, last_status = NULL, last_error = $3
+                    WHERE delivery_key = $1",
+                    record.delivery_key,
+                    attempts,
+                    "The webhook's secret couldn't be read",
+                )
+                .execute(pool)
+                .await?;
+
+                continue;
+            }
+        };
+
+        match send_delivery(
+            client,
+            &record.url,
+            record.delivery_key,
+            &record.event,
+            &record.payload,
+            &secret,
+        )
+        .await
+        {
+            Ok(status) => {
+                sqlx::query!(
+                    "UPDATE webhook_deliveries
+                    SET attempts = $2, next_attempt_at = NULL, delivered_at = now(),
+                        last_status = $3, last_error = NULL
+                    WHERE delivery_key = $1",
+                    record.delivery_key,
+                    attempts,
+                    i32::from(status),
+                )
+                .execute(pool)
+                .await?;
+            }
+            Err(failure) => {
+                let next_attempt_at = retry_delay(attempts).map(|delay| Utc::now() + delay);
+
+                sqlx::query!(
+                    "UPDATE webhook_deliveries
+                    SET attempts = $2, next_attempt_at = $3, last_status = $4, last_error = $5
+                    WHERE delivery_key = $1",
+                    record.delivery_key,
+                    attempts,
+                    next_attempt_at,
+                    failure.status.map(i32::from),
+                    failure.message,
+                )
+                .execute(pool)
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A URL the user subscribed to events, without its secret
+pub struct Webhook {
+    pub webhook_key: i64,
+    pub url: String,
+    pub subscriptions: Subscriptions,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn get_webhooks(user_key: UserKey, pool: &PgPool) -> AppResult<Vec<Webhook>> {
+    let records = sqlx::query!(
+        "SELECT webhook_key, url, goal_met, debt_cleared, debt_exceeded, day_started,
+            debt_threshold, created_at
+        FROM webhooks
+        WHERE user_key = $1
+        ORDER BY created_at",
+        user_key.0,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| Webhook {
+            webhook_key: record.webhook_key,
+            url: record.url,
+            subscriptions: Subscriptions {
+                goal_met: record.goal_met,
+                debt_cleared: record.debt_cleared,
+                debt_exceeded: record.debt_exceeded,
+                day_started: record.day_started,
+                debt_threshold: Duration::seconds(record.debt_threshold),
+            },
+            created_at: record.created_at,
+        })
+        .collect())
+}
+
+/// The secrets of the user's webhooks that haven't been shown yet, as URLs and secrets, which are
+/// marked as shown so that they're only ever returned once
+pub async fn take_new_webhook_secrets(
+    user_key: UserKey,
+    pool: &PgPool,
+    server_key: &ServerKey,
+) -> AppResult<Vec<(String, String)>> {
+    let records = sqlx::query!(
+        "UPDATE webhooks
+        SET secret_shown = TRUE
+        WHERE user_key = $1
+        AND NOT secret_shown
+        RETURNING url, secret_ciphertext",
+        user_key.0,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|record| Ok((record.url, server_key.decrypt(&record.secret_ciphertext)?)))
+        .collect()
+}
+
+/// An attempt, or several, to send an event to one of the user's webhooks
+pub struct Delivery {
+    pub delivery_key: i64,
+    pub url: String,
+    pub event: Event,
+    pub event_date: NaiveDate,
+    pub attempts: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub last_status: Option<i32>,
+    pub last_error: Option<String>,
+}
+
+/// The user's most recent deliveries, newest first
+pub async fn get_deliveries(
+    user_key: UserKey,
+    limit: i64,
+    pool: &PgPool,
+) -> AppResult<Vec<Delivery>> {
+    let records = sqlx::query!(
+        "SELECT delivery_key, url, event, event_date, attempts, next_attempt_at, delivered_at,
+            last_status, last_error
+        FROM webhook_deliveries
+        INNER JOIN webhooks
+        ON webhook_deliveries.webhook_key = webhooks.webhook_key
+        WHERE webhooks.user_key = $1
+        ORDER BY webhook_deliveries.created_at DESC, delivery_key DESC
+        LIMIT $2",
+        user_key.0,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|record| {
+            Ok(Delivery {
+                delivery_key: record.delivery_key,
+                url: record.url,
+                event: record
+                    .event
+                    .parse()
+                    .map_err(|error| sqlx::Error::Decode(Box::new(error)))?,
+                event_date: record.event_date,
+                attempts: record.attempts,
+                next_attempt_at: record.next_attempt_at,
+                delivered_at: record.delivered_at,
+                last_status: record.last_status,
+                last_error: record.last_error,
+            })
+        })
+        .collect()
+}
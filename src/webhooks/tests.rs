@@ -0,0 +1,373 @@
+#![cfg(test)]
+
+use axum::{
+    extract::Extension,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use tokio::{net::TcpListener, sync::mpsc};
+
+use super::*;
+use crate::{
+    crypto::generate_webhook_secret,
+    toggl::{Goal, Limit, LimitPeriod, LimitUsage},
+};
+
+fn goal(project_key: i64, name: &str, time: Duration, goal_today: Duration) -> Goal {
+    Goal {
+        project_key: ProjectKey(project_key),
+        name: name.to_owned(),
+        project_ids: Vec::new(),
+        time,
+        goal_today,
+        logged_today: Duration::hours(1),
+        day_off_today: false,
+        priority: 0,
+        weight: 1,
+        share_today: Duration::zero(),
+        deadline: None,
+        projected_finish: None,
+        limit: None,
+    }
+}
+
+fn all_events(debt_threshold: Duration) -> Subscriptions {
+    Subscriptions {
+        goal_met: true,
+        debt_cleared: true,
+        debt_exceeded: true,
+        day_started: true,
+        debt_threshold,
+    }
+}
+
+fn example_server_key() -> ServerKey {
+    ServerKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap()
+}
+
+#[test]
+fn test_events() {
+    let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+    let email = Goal {
+        limit: Some(LimitUsage {
+            limit: Limit {
+                time: Duration::hours(1),
+                period: LimitPeriod::Day,
+            },
+            used: Duration::zero(),
+        }),
+        ..goal(3, "Email", Duration::zero(), Duration::zero())
+    };
+
+    let status = Status {
+        goals: Vec::from([
+            goal(1, "Thesis", Duration::minutes(-10), Duration::hours(1)),
+            goal(2, "Reading", Duration::hours(1), Duration::hours(1)),
+            email,
+        ]),
+        total_debt: Duration::hours(1),
+        today,
+    };
+
+    let sent: Vec<(Event, Option<ProjectKey>)> = events(&status, &all_events(Duration::zero()))
+        .into_iter()
+        .map(|payload| {
+            (
+                payload.event,
+                payload.project.map(|project| project.project_key),
+            )
+        })
+        .collect();
+    assert_eq!(
+        sent,
+        Vec::from([
+            (Event::DayStarted, None),
+            (Event::GoalMet, Some(ProjectKey(1))),
+            (Event::DebtExceeded, None),
+        ]),
+    );
+
+    let caught_up = Status {
+        total_debt: Duration::zero(),
+        ..status
+    };
+    let sent: Vec<Event> = events(
+        &caught_up,
+        &Subscriptions {
+            day_started: false,
+            goal_met: false,
+            ..all_events(Duration::hours(2))
+        },
+    )
+    .into_iter()
+    .map(|payload| payload.event)
+    .collect();
+    assert_eq!(sent, Vec::from([Event::DebtCleared]));
+
+    // Nothing is cleared on a day without anything to do
+    let day_off = Status {
+        goals: Vec::from([goal(1, "Thesis", Duration::zero(), Duration::zero())]),
+        total_debt: Duration::zero(),
+        today,
+    };
+    assert!(events(
+        &day_off,
+        &Subscriptions {
+            day_started: false,
+            ..all_events(Duration::zero())
+        },
+    )
+    .is_empty());
+}
+
+#[test]
+fn test_retry_delay() {
+    assert_eq!(retry_delay(1), Some(Duration::minutes(1)));
+    assert_eq!(retry_delay(2), Some(Duration::minutes(2)));
+    assert_eq!(retry_delay(4), Some(Duration::minutes(8)));
+    assert_eq!(retry_delay(MAX_ATTEMPTS - 1), Some(Duration::minutes(64)));
+    assert_eq!(retry_delay(MAX_ATTEMPTS), None);
+}
+
+/// Serve a webhook that responds with `status` and passes on the requests it gets
+async fn fake_webhook(
+    status: StatusCode,
+) -> (String, mpsc::UnboundedReceiver<(HeaderMap, String)>) {
+    let (sender, receiver) = mpsc::unbounded_channel::<(HeaderMap, String)>();
+
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(
+                move |Extension(sender): Extension<mpsc::UnboundedSender<(HeaderMap, String)>>,
+                      headers: HeaderMap,
+                      body: String| async move {
+                    sender.send((headers, body)).unwrap();
+                    status
+                },
+            ),
+        )
+        .layer(Extension(sender));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    // Named rather than given as an address, which webhooks can't be sent to if it's private
+    (
+        format!("http://localhost:{}/hook", address.port()),
+        receiver,
+    )
+}
+
+#[tokio::test]
+async fn test_send_delivery() {
+    let (url, mut requests) = fake_webhook(StatusCode::NO_CONTENT).await;
+    let secret = generate_webhook_secret();
+    let payload = r#"{"event":"day_started"}"#;
+
+    let status = send_delivery(&Client::new(), &url, 7, "day_started", payload, &secret)
+        .await
+        .unwrap();
+    assert_eq!(status, 204);
+
+    let (headers, body) = requests.recv().await.unwrap();
+    assert_eq!(body, payload);
+    assert_eq!(headers["x-yottaclock-event"], "day_started");
+    assert_eq!(headers["x-yottaclock-delivery"], "7");
+    assert_eq!(
+        headers["x-yottaclock-signature"],
+        sign_webhook_payload(&secret, payload.as_bytes()).as_str(),
+    );
+
+    let (url, _requests) = fake_webhook(StatusCode::INTERNAL_SERVER_ERROR).await;
+    let failure = send_delivery(&Client::new(), &url, 7, "day_started", payload, &secret)
+        .await
+        .unwrap_err();
+    assert_eq!(failure.status, Some(500));
+
+    let private = send_delivery(
+        &Client::new(),
+        "http://169.254.169.254/latest/meta-data/",
+        7,
+        "day_started",
+        payload,
+        &secret,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(private.status, None);
+}
+
+#[sqlx::test]
+async fn test_deliveries(pool: PgPool) -> AppResult<()> {
+    let server_key = example_server_key();
+    let (url, mut requests) = fake_webhook(StatusCode::SERVICE_UNAVAILABLE).await;
+
+    let user_key = sqlx::query!(
+        "INSERT INTO users(username, daily_max, timezone)
+        VALUES ('someone', 0, 'UTC')
+        RETURNING user_key"
+    )
+    .fetch_one(&pool)
+    .await?
+    .user_key;
+
+    let webhook_key = sqlx::query!(
+        "INSERT INTO webhooks(user_key, url, secret_ciphertext, goal_met, debt_cleared,
+            debt_exceeded, day_started)
+        VALUES ($1, $2, $3, FALSE, FALSE, FALSE, TRUE)
+        RETURNING webhook_key",
+        user_key,
+        url,
+        server_key.encrypt(&generate_webhook_secret())?,
+    )
+    .fetch_one(&pool)
+    .await?
+    .webhook_key;
+
+    let payload = Payload {
+        event: Event::DayStarted,
+        date: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(),
+        total_debt: 3600,
+        project: None,
+        debt_threshold: None,
+    };
+
+    // The same event is only sent once a day
+    assert!(insert_delivery(webhook_key, &payload, &pool).await?);
+    assert!(!insert_delivery(webhook_key, &payload, &pool).await?);
+
+    send_due_deliveries(&pool, &Client::new(), &server_key).await?;
+
+    let (_, body) = requests.recv().await.unwrap();
+    assert_eq!(
+        body,
+        r#"{"event":"day_started","date":"2026-10-18","total_debt":3600}"#,
+    );
+
+    let deliveries = get_deliveries(UserKey(user_key), 10, &pool).await?;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].event, Event::DayStarted);
+    assert_eq!(deliveries[0].attempts, 1);
+    assert_eq!(deliveries[0].last_status, Some(503));
+    assert_eq!(deliveries[0].delivered_at, None);
+    assert!(deliveries[0].next_attempt_at.unwrap() > Utc::now());
+
+    // Not due again until the retry delay has passed
+    send_due_deliveries(&pool, &Client::new(), &server_key).await?;
+    assert_eq!(
+        get_deliveries(UserKey(user_key), 10, &pool).await?[0].attempts,
+        1,
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_undecryptable_secret(pool: PgPool) -> AppResult<()> {
+    let server_key = example_server_key();
+    let (url, mut requests) = fake_webhook(StatusCode::OK).await;
+
+    let user_key = sqlx::query!(
+        "INSERT INTO users(username, daily_max, timezone)
+        VALUES ('someone', 0, 'UTC')
+        RETURNING user_key"
+    )
+    .fetch_one(&pool)
+    .await?
+    .user_key;
+
+    let mut webhook_keys = Vec::new();
+    for secret_ciphertext in [b"not encrypted".to_vec(), server_key.encrypt("secret")?] {
+        webhook_keys.push(
+            sqlx::query!(
+                "INSERT INTO webhooks(user_key, url, secret_ciphertext, goal_met, debt_cleared,
+                    debt_exceeded, day_started)
+                VALUES ($1, $2, $3, FALSE, FALSE, FALSE, TRUE)
+                RETURNING webhook_key",
+                user_key,
+                url,
+                secret_ciphertext,
+            )
+            .fetch_one(&pool)
+            .await?
+            .webhook_key,
+        );
+    }
+
+    let payload = Payload {
+        event: Event::DayStarted,
+        date: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(),
+        total_debt: 3600,
+        project: None,
+        debt_threshold: None,
+    };
+
+    for &webhook_key in &webhook_keys {
+        insert_delivery(webhook_key, &payload, &pool).await?;
+    }
+
+    // The webhook whose secret can't be read doesn't hold up the other one
+    send_due_deliveries(&pool, &Client::new(), &server_key).await?;
+    assert!(requests.recv().await.is_some());
+
+    let deliveries = sqlx::query!(
+        "SELECT webhook_key, next_attempt_at, delivered_at, last_error
+        FROM webhook_deliveries
+        ORDER BY webhook_key"
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    assert_eq!(deliveries[0].webhook_key, webhook_keys[0]);
+    assert_eq!(deliveries[0].next_attempt_at, None);
+    assert_eq!(deliveries[0].delivered_at, None);
+    assert!(deliveries[0].last_error.is_some());
+
+    assert_eq!(deliveries[1].webhook_key, webhook_keys[1]);
+    assert!(deliveries[1].delivered_at.is_some());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn test_take_new_webhook_secrets(pool: PgPool) -> AppResult<()> {
+    let server_key = example_server_key();
+    let secret = generate_webhook_secret();
+
+    let user_key = sqlx::query!(
+        "INSERT INTO users(username, daily_max, timezone)
+        VALUES ('someone', 0, 'UTC')
+        RETURNING user_key"
+    )
+    .fetch_one(&pool)
+    .await?
+    .user_key;
+
+    sqlx::query!(
+        "INSERT INTO webhooks(user_key, url, secret_ciphertext, goal_met, debt_cleared,
+            debt_exceeded, day_started, secret_shown)
+        VALUES ($1, 'https://example.com/hook', $2, TRUE, FALSE, FALSE, FALSE, FALSE)",
+        user_key,
+        server_key.encrypt(&secret)?,
+    )
+    .execute(&pool)
+    .await?;
+
+    assert_eq!(
+        take_new_webhook_secrets(UserKey(user_key), &pool, &server_key).await?,
+        [("https://example.com/hook".to_owned(), secret)],
+    );
+
+    // The secret is only shown once
+    assert!(
+        take_new_webhook_secrets(UserKey(user_key), &pool, &server_key)
+            .await?
+            .is_empty()
+    );
+
+    Ok(())
+}